@@ -0,0 +1,387 @@
+use std::io::BufRead;
+use std::rc::Rc;
+
+use crate::error_codes::ErrorCode;
+use crate::interpreter::EvaluationType;
+use crate::typechecker::compatible;
+use crate::types::Type;
+
+/// Failure of a builtin at runtime. The caller attaches the span of the call.
+pub(crate) type BuiltinError = (ErrorCode, String);
+
+/// Native implementation of a builtin.
+#[derive(Debug, Clone, Copy)]
+pub enum BuiltinCall {
+    /// Only reads its arguments
+    Pure(fn(&[EvaluationType]) -> Result<EvaluationType, BuiltinError>),
+    /// Receives its first argument by reference so it can update the variable in place.
+    /// The remaining arguments are passed by value.
+    Mutating(fn(&mut EvaluationType, &[EvaluationType]) -> Result<EvaluationType, BuiltinError>),
+    /// Reads from the input of the interpreter
+    Input(fn(&mut dyn BufRead, &[EvaluationType]) -> Result<EvaluationType, BuiltinError>),
+}
+
+/// A function provided by the language itself and callable from any Flavor program.
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// Computes the return type from the argument types, or explains why they do not fit.
+    /// Signatures can be polymorphic, for example over the element type of an array.
+    pub signature: fn(&[Type]) -> Result<Type, String>,
+    pub call: BuiltinCall,
+}
+
+pub(crate) const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "len",
+        signature: len_signature,
+        call: BuiltinCall::Pure(len),
+    },
+    Builtin {
+        name: "push",
+        signature: push_signature,
+        call: BuiltinCall::Mutating(push),
+    },
+    Builtin {
+        name: "pop",
+        signature: pop_signature,
+        call: BuiltinCall::Mutating(pop),
+    },
+    Builtin {
+        name: "insert",
+        signature: insert_signature,
+        call: BuiltinCall::Mutating(insert),
+    },
+    Builtin {
+        name: "remove",
+        signature: remove_signature,
+        call: BuiltinCall::Mutating(remove),
+    },
+    Builtin {
+        name: "read_line",
+        signature: read_line_signature,
+        call: BuiltinCall::Input(read_line),
+    },
+    Builtin {
+        name: "eof",
+        signature: eof_signature,
+        call: BuiltinCall::Input(eof),
+    },
+    Builtin {
+        name: "wrapping_add",
+        signature: wrapping_add_signature,
+        call: BuiltinCall::Pure(wrapping_add),
+    },
+    Builtin {
+        name: "wrapping_sub",
+        signature: wrapping_sub_signature,
+        call: BuiltinCall::Pure(wrapping_sub),
+    },
+    Builtin {
+        name: "wrapping_mul",
+        signature: wrapping_mul_signature,
+        call: BuiltinCall::Pure(wrapping_mul),
+    },
+    Builtin {
+        name: "saturating_add",
+        signature: saturating_add_signature,
+        call: BuiltinCall::Pure(saturating_add),
+    },
+    Builtin {
+        name: "saturating_sub",
+        signature: saturating_sub_signature,
+        call: BuiltinCall::Pure(saturating_sub),
+    },
+    Builtin {
+        name: "saturating_mul",
+        signature: saturating_mul_signature,
+        call: BuiltinCall::Pure(saturating_mul),
+    },
+];
+
+pub(crate) fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+fn expect_arity(name: &str, args: &[Type], expected: usize) -> Result<(), String> {
+    if args.len() == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "Function argument count mismatch: '{name}' expects {expected}, found {}",
+            args.len()
+        ))
+    }
+}
+
+fn array_element<'a>(name: &str, ty: &'a Type) -> Result<&'a Type, String> {
+    match ty {
+        Type::Array(element) => Ok(element),
+        other => Err(format!(
+            "'{name}' expects an array as first argument, found {other:?}"
+        )),
+    }
+}
+
+fn expect_type(name: &str, what: &str, expected: &Type, found: &Type) -> Result<(), String> {
+    if compatible(expected, found) {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{name}' expects {what} of type {expected:?}, found {found:?}"
+        ))
+    }
+}
+
+fn len_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("len", args, 1)?;
+    match &args[0] {
+        Type::Array(_) | Type::String => Ok(Type::Int),
+        other => Err(format!(
+            "'len' expects an array or a string, found {other:?}"
+        )),
+    }
+}
+
+/// Checks the value added to an array by `name`. An array of `nothing` is most likely an
+/// empty literal whose element type could not be inferred, which the error suggests to write.
+fn expect_element(name: &str, element: &Type, found: &Type) -> Result<(), String> {
+    if *element == Type::Unit && !compatible(element, found) {
+        return Err(format!(
+            "'{name}' cannot add a value of type {found:?} to an array of nothing; declare the element type of an empty array, e.g. `let values: [{found}] = [];`"
+        ));
+    }
+    expect_type(name, "a value", element, found)
+}
+
+fn push_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("push", args, 2)?;
+    let element = array_element("push", &args[0])?;
+    expect_element("push", element, &args[1])?;
+    Ok(Type::Unit)
+}
+
+fn pop_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("pop", args, 1)?;
+    array_element("pop", &args[0]).cloned()
+}
+
+fn insert_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("insert", args, 3)?;
+    let element = array_element("insert", &args[0])?;
+    expect_type("insert", "an index", &Type::Int, &args[1])?;
+    expect_element("insert", element, &args[2])?;
+    Ok(Type::Unit)
+}
+
+fn remove_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("remove", args, 2)?;
+    let element = array_element("remove", &args[0])?;
+    expect_type("remove", "an index", &Type::Int, &args[1])?;
+    Ok(element.clone())
+}
+
+fn integer_operation_signature(name: &str, args: &[Type]) -> Result<Type, String> {
+    expect_arity(name, args, 2)?;
+    for arg in args {
+        expect_type(name, "an argument", &Type::Int, arg)?;
+    }
+    Ok(Type::Int)
+}
+
+fn wrapping_add_signature(args: &[Type]) -> Result<Type, String> {
+    integer_operation_signature("wrapping_add", args)
+}
+
+fn wrapping_sub_signature(args: &[Type]) -> Result<Type, String> {
+    integer_operation_signature("wrapping_sub", args)
+}
+
+fn wrapping_mul_signature(args: &[Type]) -> Result<Type, String> {
+    integer_operation_signature("wrapping_mul", args)
+}
+
+fn saturating_add_signature(args: &[Type]) -> Result<Type, String> {
+    integer_operation_signature("saturating_add", args)
+}
+
+fn saturating_sub_signature(args: &[Type]) -> Result<Type, String> {
+    integer_operation_signature("saturating_sub", args)
+}
+
+fn saturating_mul_signature(args: &[Type]) -> Result<Type, String> {
+    integer_operation_signature("saturating_mul", args)
+}
+
+fn read_line_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("read_line", args, 0)?;
+    Ok(Type::String)
+}
+
+fn eof_signature(args: &[Type]) -> Result<Type, String> {
+    expect_arity("eof", args, 0)?;
+    Ok(Type::Bool)
+}
+
+fn as_array<'a>(
+    name: &str,
+    value: &'a mut EvaluationType,
+) -> Result<&'a mut Vec<EvaluationType>, BuiltinError> {
+    match value {
+        EvaluationType::Array(values) => Ok(Rc::make_mut(values)),
+        other => Err((
+            ErrorCode::RuntimeTypeError,
+            format!("'{name}' expects an array, found {other}"),
+        )),
+    }
+}
+
+fn as_index(value: &EvaluationType) -> Result<usize, BuiltinError> {
+    match value {
+        EvaluationType::Int(index) if *index < 0 => {
+            Err((ErrorCode::NegativeIndex, "Negative array index".to_string()))
+        }
+        EvaluationType::Int(index) => Ok(*index as usize),
+        other => Err((
+            ErrorCode::RuntimeTypeError,
+            format!("Array index must be an integer, found {other}"),
+        )),
+    }
+}
+
+fn len(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    match &args[0] {
+        EvaluationType::Array(values) => Ok(EvaluationType::Int(values.len() as i64)),
+        EvaluationType::String(value) => Ok(EvaluationType::Int(value.chars().count() as i64)),
+        other => Err((
+            ErrorCode::RuntimeTypeError,
+            format!("'len' expects an array or a string, found {other}"),
+        )),
+    }
+}
+
+fn push(
+    target: &mut EvaluationType,
+    args: &[EvaluationType],
+) -> Result<EvaluationType, BuiltinError> {
+    as_array("push", target)?.push(args[0].clone());
+    Ok(EvaluationType::Unit)
+}
+
+fn pop(
+    target: &mut EvaluationType,
+    _args: &[EvaluationType],
+) -> Result<EvaluationType, BuiltinError> {
+    as_array("pop", target)?.pop().ok_or_else(|| {
+        (
+            ErrorCode::EmptyArray,
+            "Cannot pop from an empty array".to_string(),
+        )
+    })
+}
+
+fn insert(
+    target: &mut EvaluationType,
+    args: &[EvaluationType],
+) -> Result<EvaluationType, BuiltinError> {
+    let values = as_array("insert", target)?;
+    let index = as_index(&args[0])?;
+    if index > values.len() {
+        return Err((
+            ErrorCode::IndexOutOfBounds,
+            format!(
+                "Index {index} out of bounds for insert into array of length {}",
+                values.len()
+            ),
+        ));
+    }
+    values.insert(index, args[1].clone());
+    Ok(EvaluationType::Unit)
+}
+
+fn remove(
+    target: &mut EvaluationType,
+    args: &[EvaluationType],
+) -> Result<EvaluationType, BuiltinError> {
+    let values = as_array("remove", target)?;
+    let index = as_index(&args[0])?;
+    if index >= values.len() {
+        return Err((
+            ErrorCode::IndexOutOfBounds,
+            format!(
+                "Index {index} out of bounds for remove from array of length {}",
+                values.len()
+            ),
+        ));
+    }
+    Ok(values.remove(index))
+}
+
+/// Reads the next line of input without its line ending. Yields an empty string once the
+/// input is exhausted, like it does for an empty line, so `eof` tells the two apart.
+fn read_line(
+    input: &mut dyn BufRead,
+    _args: &[EvaluationType],
+) -> Result<EvaluationType, BuiltinError> {
+    let mut line = String::new();
+    input
+        .read_line(&mut line)
+        .map_err(|err| (ErrorCode::IoFailed, format!("Failed to read input: {err}")))?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(EvaluationType::String(line))
+}
+
+/// Tells whether the input is exhausted, waiting for more of it if needed.
+fn eof(input: &mut dyn BufRead, _args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    let buffered = input
+        .fill_buf()
+        .map_err(|err| (ErrorCode::IoFailed, format!("Failed to read input: {err}")))?;
+    Ok(EvaluationType::Bool(buffered.is_empty()))
+}
+
+/// Applies an integer operation that cannot fail, for the arithmetic builtins choosing what
+/// happens on overflow.
+fn integer_operation(
+    name: &str,
+    args: &[EvaluationType],
+    operation: fn(i64, i64) -> i64,
+) -> Result<EvaluationType, BuiltinError> {
+    match args {
+        [EvaluationType::Int(left), EvaluationType::Int(right)] => {
+            Ok(EvaluationType::Int(operation(*left, *right)))
+        }
+        _ => Err((
+            ErrorCode::RuntimeTypeError,
+            format!("'{name}' expects two integers"),
+        )),
+    }
+}
+
+fn wrapping_add(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    integer_operation("wrapping_add", args, i64::wrapping_add)
+}
+
+fn wrapping_sub(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    integer_operation("wrapping_sub", args, i64::wrapping_sub)
+}
+
+fn wrapping_mul(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    integer_operation("wrapping_mul", args, i64::wrapping_mul)
+}
+
+fn saturating_add(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    integer_operation("saturating_add", args, i64::saturating_add)
+}
+
+fn saturating_sub(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    integer_operation("saturating_sub", args, i64::saturating_sub)
+}
+
+fn saturating_mul(args: &[EvaluationType]) -> Result<EvaluationType, BuiltinError> {
+    integer_operation("saturating_mul", args, i64::saturating_mul)
+}
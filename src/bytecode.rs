@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::error::FlavorError;
+use crate::interpreter::EvaluationType;
+use crate::operations::BinaryOperator;
+use crate::types::Span;
+
+/// One instruction of the virtual machine.
+///
+/// Slots are counted from the first argument of the running function, jump targets are
+/// positions in the code of the chunk, and the other operands index the tables of the chunk.
+/// Instructions that consume values take them from the top of the stack, leftmost operand
+/// deepest.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Op {
+    Constant(u32),
+    Unit,
+    Pop,
+    GetLocal(u32),
+    /// Stores the top of the stack into a slot and leaves it there
+    SetLocal(u32),
+    GetUpvalue(u32),
+    SetUpvalue(u32),
+    GetGlobal(u32),
+    SetGlobal(u32),
+    /// Pops the top of the stack into a global, defining it if needed
+    DefineGlobal(u32),
+    /// Drops the `locals` values below the top of the stack if `keep` is set, or on top of it
+    /// otherwise, closing the upvalues pointing into them
+    EndScope {
+        locals: u32,
+        keep: bool,
+    },
+    Jump(u32),
+    /// Pops the top of the stack and jumps unless it is `true`
+    JumpIfFalse(u32),
+    Binary(BinaryOperator),
+    Negate,
+    Not,
+    /// Fails unless the top of the stack is an integer, used for range bounds
+    ExpectInt,
+    /// Fails unless the top of the stack can index an assignment target
+    CheckIndex,
+    Index,
+    GetField(u32),
+    /// Writes the value below the place indices into the place, leaving the value
+    SetPlace(u32),
+    IncrementPlace {
+        place: u32,
+        decrement: bool,
+        postfix: bool,
+    },
+    /// Calls a builtin taking its arguments by value
+    CallBuiltin {
+        builtin: u32,
+        arguments: u32,
+    },
+    /// Calls a builtin whose first argument is a place, given as indices below the arguments
+    CallMutating {
+        builtin: u32,
+        place: u32,
+        arguments: u32,
+    },
+    /// Calls the value below the arguments
+    Call(u32),
+    Return,
+    Closure(u32),
+    MakeArray(u32),
+    MakeStruct(u32),
+    MakeVariant {
+        variant: u32,
+        len: u32,
+    },
+    /// Jumps unless the value in `slot` is the named variant
+    JumpIfNotVariant {
+        slot: u32,
+        variant: u32,
+        target: u32,
+    },
+    /// Pushes a payload value of the variant held in `slot`
+    GetVariantField {
+        slot: u32,
+        index: u32,
+    },
+    /// Fails because no match arm accepted the value in the slot
+    NoMatch(u32),
+    /// Pushes the next value of the range held in `slot` and `slot + 1`, or jumps to `exit`
+    RangeNext {
+        slot: u32,
+        inclusive: bool,
+        exit: u32,
+    },
+    /// Pushes the next element of the array held in `slot`, whose position is in `slot + 1`,
+    /// or jumps to `exit`
+    ArrayNext {
+        slot: u32,
+        exit: u32,
+    },
+    Print(u32),
+    /// Raises one of the errors of the chunk, for code that could not be compiled
+    Fail(u32),
+}
+
+/// Where the variable written by a place starts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PlaceRoot {
+    Local(u32),
+    Upvalue(u32),
+    Global(u32),
+}
+
+/// Array indices and struct fields followed from the root of a place.
+#[derive(Debug)]
+pub(crate) enum Accessor {
+    Index { index_span: Span, array_span: Span },
+    Field { name: String, span: Span },
+}
+
+/// Target of an assignment, an increment or a mutating builtin. The values of its indices
+/// are on the stack when it is used.
+#[derive(Debug)]
+pub(crate) struct Place {
+    pub(crate) root: PlaceRoot,
+    pub(crate) name: String,
+    pub(crate) span: Span,
+    pub(crate) accessors: Vec<Accessor>,
+    pub(crate) indices: u32,
+}
+
+/// Fields of a struct literal, in the order they are stored. Each one is paired with the
+/// position of its value in the literal.
+#[derive(Debug)]
+pub(crate) struct StructShape {
+    pub(crate) name: String,
+    pub(crate) fields: Vec<(usize, String)>,
+}
+
+/// Instructions together with everything they refer to.
+#[derive(Debug, Default)]
+pub(crate) struct Chunk {
+    pub(crate) code: Vec<Op>,
+    /// Span of the source each instruction comes from, reported by its errors
+    pub(crate) spans: Vec<Span>,
+    pub(crate) constants: Vec<EvaluationType>,
+    pub(crate) names: Vec<String>,
+    pub(crate) places: Vec<Place>,
+    pub(crate) shapes: Vec<StructShape>,
+    pub(crate) variants: Vec<(String, String)>,
+    pub(crate) functions: Vec<Rc<Function>>,
+    pub(crate) errors: Vec<FlavorError>,
+}
+
+/// Where a closure finds one of the variables it captures when it is created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Capture {
+    /// A slot of the function creating the closure
+    Local(u32),
+    /// One of the upvalues of the function creating the closure
+    Upvalue(u32),
+}
+
+/// A compiled function, or the top level of a program.
+#[derive(Debug, Default)]
+pub(crate) struct Function {
+    pub(crate) arity: usize,
+    pub(crate) captures: Vec<Capture>,
+    pub(crate) chunk: Chunk,
+}
+
+/// A captured variable, living on the stack while its scope runs and in the upvalue after.
+#[derive(Debug)]
+pub(crate) enum Upvalue {
+    /// Absolute position on the stack
+    Open(usize),
+    Closed(EvaluationType),
+}
+
+/// A function value created by the virtual machine.
+pub struct Closure {
+    pub(crate) function: Rc<Function>,
+    pub(crate) upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+/// Upvalues are left out: a closure capturing itself would print forever.
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("arity", &self.function.arity)
+            .finish_non_exhaustive()
+    }
+}
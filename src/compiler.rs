@@ -0,0 +1,1106 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::builtins::{self, Builtin, BuiltinCall};
+use crate::bytecode::{Accessor, Capture, Chunk, Function, Op, Place, PlaceRoot, StructShape};
+use crate::error::{ErrorPhase, FlavorError};
+use crate::error_codes::ErrorCode;
+use crate::interpreter::{EnvFrame, EvaluationType, type_declarations};
+use crate::operations::BinaryOperator;
+use crate::types::{ASTNode as AST, MatchArm, Parameter, Pattern, Span, Type};
+
+/// Names of the globals of a virtual machine or an interpreter. They outlive the programs
+/// compiled or resolved for it, so later programs find the globals of earlier ones at the same
+/// index.
+#[derive(Default)]
+pub(crate) struct Globals {
+    pub(crate) names: Vec<String>,
+    indices: HashMap<String, u32>,
+    /// Whether a `let`, a `fn` or the host declared the global, rather than a reference to a
+    /// name that does not exist yet
+    declared: Vec<bool>,
+}
+
+impl Globals {
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub(crate) fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        let index = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.indices.insert(name.to_string(), index);
+        self.declared.push(false);
+        index
+    }
+
+    pub(crate) fn declare(&mut self, name: &str) -> u32 {
+        let index = self.intern(name);
+        self.declared[index as usize] = true;
+        index
+    }
+
+    fn declared(&self, name: &str) -> Option<u32> {
+        self.indices
+            .get(name)
+            .copied()
+            .filter(|&index| self.declared[index as usize])
+    }
+}
+
+/// How an identifier is reached from the code being compiled.
+enum Variable {
+    Local(u32),
+    Upvalue(u32),
+    Global(u32),
+    Builtin(usize, &'static Builtin),
+}
+
+struct Local {
+    /// Empty for the values the compiler keeps on the stack for itself
+    name: String,
+    slot: u32,
+    depth: usize,
+}
+
+struct Loop {
+    label: Option<String>,
+    /// Stack height a `break` or a `continue` returns to
+    height: u32,
+    /// Where a `continue` jumps to
+    start: usize,
+    /// Jumps to patch with the position following the loop
+    breaks: Vec<usize>,
+}
+
+/// State of one function while its body is compiled.
+struct FunctionState {
+    function: Function,
+    locals: Vec<Local>,
+    /// Stack height at the start of each open scope
+    scopes: Vec<u32>,
+    /// Number of values on the stack above the first argument, known at every instruction
+    height: u32,
+    loops: Vec<Loop>,
+}
+
+impl FunctionState {
+    fn new(parameters: &[Parameter]) -> Self {
+        Self {
+            function: Function {
+                arity: parameters.len(),
+                ..Function::default()
+            },
+            locals: parameters
+                .iter()
+                .enumerate()
+                .map(|(slot, parameter)| Local {
+                    name: parameter.name.clone(),
+                    slot: slot as u32,
+                    depth: 1,
+                })
+                .collect(),
+            scopes: vec![0],
+            height: parameters.len() as u32,
+            loops: vec![],
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+}
+
+/// Compiles checked programs into functions for the virtual machine.
+///
+/// Variables of functions and blocks live in stack slots known at compile time, and those
+/// captured by closures are shared through upvalues. The `let`s and `fn`s at the top level of
+/// a program are globals, looked up by index when they run, so they stay visible to the
+/// programs compiled afterwards.
+pub(crate) struct Compiler<'a> {
+    globals: &'a mut Globals,
+    /// Types declared at the top level of the programs compiled so far
+    global_types: &'a Rc<RefCell<EnvFrame>>,
+    /// Types declared in the bodies being compiled, innermost last
+    types: Vec<HashMap<String, Type>>,
+    functions: Vec<FunctionState>,
+}
+
+impl<'a> Compiler<'a> {
+    pub(crate) fn new(globals: &'a mut Globals, global_types: &'a Rc<RefCell<EnvFrame>>) -> Self {
+        Self {
+            globals,
+            global_types,
+            types: vec![],
+            functions: vec![],
+        }
+    }
+
+    /// Compiles a whole program into a function without parameters returning the value of its
+    /// last statement.
+    pub(crate) fn compile_program(mut self, nodes: &[AST]) -> Function {
+        self.global_types.borrow_mut().declare_types(nodes);
+        let mut script = FunctionState::new(&[]);
+        script.scopes.clear();
+        self.functions.push(script);
+
+        self.compile_sequence(nodes, true);
+        let span = nodes.last().map_or(Span::point(1, 1), |node| *node.span());
+        self.emit(Op::Return, span);
+        self.functions
+            .pop()
+            .expect("the script is compiled")
+            .function
+    }
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.functions
+            .last_mut()
+            .expect("a function is being compiled")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().function.chunk
+    }
+
+    /// Appends an instruction and tracks the stack height after it.
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        let chunk = self.chunk();
+        let effect: i64 = match op {
+            Op::Constant(_)
+            | Op::Unit
+            | Op::GetLocal(_)
+            | Op::GetUpvalue(_)
+            | Op::GetGlobal(_)
+            | Op::Closure(_)
+            | Op::GetVariantField { .. }
+            | Op::RangeNext { .. }
+            | Op::ArrayNext { .. } => 1,
+            Op::Pop
+            | Op::DefineGlobal(_)
+            | Op::JumpIfFalse(_)
+            | Op::Binary(_)
+            | Op::Index
+            | Op::Return => -1,
+            Op::EndScope { locals, .. } => -(locals as i64),
+            Op::SetPlace(place) => -(chunk.places[place as usize].indices as i64),
+            Op::IncrementPlace { place, .. } => 1 - chunk.places[place as usize].indices as i64,
+            Op::CallBuiltin { arguments, .. } => 1 - arguments as i64,
+            Op::CallMutating {
+                place, arguments, ..
+            } => 1 - arguments as i64 - chunk.places[place as usize].indices as i64,
+            Op::Call(arguments) => -(arguments as i64),
+            Op::MakeArray(len) | Op::MakeVariant { len, .. } => 1 - len as i64,
+            Op::MakeStruct(shape) => 1 - chunk.shapes[shape as usize].fields.len() as i64,
+            Op::Print(len) => -(len as i64),
+            Op::SetLocal(_)
+            | Op::SetUpvalue(_)
+            | Op::SetGlobal(_)
+            | Op::Jump(_)
+            | Op::Negate
+            | Op::Not
+            | Op::ExpectInt
+            | Op::CheckIndex
+            | Op::GetField(_)
+            | Op::JumpIfNotVariant { .. }
+            | Op::NoMatch(_)
+            | Op::Fail(_) => 0,
+        };
+        chunk.code.push(op);
+        chunk.spans.push(span);
+        let state = self.state();
+        state.height = (state.height as i64 + effect) as u32;
+        state.function.chunk.code.len() - 1
+    }
+
+    fn position(&mut self) -> usize {
+        self.chunk().code.len()
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.position() as u32;
+        match &mut self.chunk().code[at] {
+            Op::Jump(to)
+            | Op::JumpIfFalse(to)
+            | Op::JumpIfNotVariant { target: to, .. }
+            | Op::RangeNext { exit: to, .. }
+            | Op::ArrayNext { exit: to, .. } => *to = target,
+            op => unreachable!("{op:?} does not jump"),
+        }
+    }
+
+    fn constant(&mut self, value: EvaluationType, span: Span) {
+        let chunk = self.chunk();
+        chunk.constants.push(value);
+        let index = chunk.constants.len() as u32 - 1;
+        self.emit(Op::Constant(index), span);
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        let names = &mut self.chunk().names;
+        match names.iter().position(|known| known == name) {
+            Some(index) => index as u32,
+            None => {
+                names.push(name.to_string());
+                names.len() as u32 - 1
+            }
+        }
+    }
+
+    /// Compiles code raising `error` when it runs, standing for a value if one is wanted.
+    fn fail(&mut self, error: FlavorError, want: bool) {
+        let span = error.span.unwrap_or(Span::point(1, 1));
+        let errors = &mut self.chunk().errors;
+        errors.push(error);
+        let index = errors.len() as u32 - 1;
+        self.emit(Op::Fail(index), span);
+        if want {
+            self.emit(Op::Unit, span);
+        }
+    }
+
+    fn runtime_error(message: impl Into<String>, span: Span, code: ErrorCode) -> FlavorError {
+        FlavorError::with_span(ErrorPhase::Runtime, message, span).with_code(code)
+    }
+
+    fn begin_scope(&mut self, nodes: &[AST]) {
+        self.types.push(type_declarations(nodes).collect());
+        let state = self.state();
+        state.scopes.push(state.height);
+    }
+
+    /// Drops the values of the innermost scope, keeping the value on top of them if `keep`.
+    fn end_scope(&mut self, keep: bool, span: Span) {
+        self.types.pop();
+        let state = self.state();
+        let start = state.scopes.pop().expect("a scope is open");
+        let depth = state.depth();
+        while state.locals.last().is_some_and(|local| local.depth > depth) {
+            state.locals.pop();
+        }
+        let locals = state.height - start - keep as u32;
+        if locals > 0 {
+            self.emit(Op::EndScope { locals, keep }, span);
+        }
+    }
+
+    /// Opens a scope whose first value, already on the stack, is `name`.
+    fn begin_binding_scope(&mut self, name: &str) {
+        self.types.push(HashMap::new());
+        let state = self.state();
+        state.scopes.push(state.height - 1);
+        Self::add_local(state, name, state.height - 1);
+    }
+
+    fn add_local(state: &mut FunctionState, name: &str, slot: u32) {
+        let depth = state.depth();
+        state.locals.push(Local {
+            name: name.to_string(),
+            slot,
+            depth,
+        });
+    }
+
+    /// Remembers the value on top of the stack as a value the compiler itself refers to.
+    fn push_hidden(&mut self) -> u32 {
+        let state = self.state();
+        let slot = state.height - 1;
+        Self::add_local(state, "", slot);
+        slot
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.functions.len() == 1 && self.functions[0].depth() == 0
+    }
+
+    /// A local of the innermost scope named `name`, whose slot a new declaration takes over.
+    fn redeclared(&mut self, name: &str) -> Option<u32> {
+        let state = self.state();
+        let depth = state.depth();
+        state
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth == depth)
+            .find(|local| local.name == name)
+            .map(|local| local.slot)
+    }
+
+    /// Turns the value on top of the stack into the variable `name`.
+    fn define_variable(&mut self, name: &str, span: Span) {
+        if self.is_global_scope() {
+            let index = self.globals.declare(name);
+            self.emit(Op::DefineGlobal(index), span);
+        } else if let Some(slot) = self.redeclared(name) {
+            self.emit(Op::SetLocal(slot), span);
+            self.emit(Op::Pop, span);
+        } else {
+            let state = self.state();
+            Self::add_local(state, name, state.height - 1);
+        }
+    }
+
+    fn resolve(&mut self, name: &str) -> Variable {
+        let innermost = self.functions.len() - 1;
+        if let Some(slot) = Self::resolve_local(&self.functions[innermost], name) {
+            Variable::Local(slot)
+        } else if let Some(index) = self.resolve_upvalue(innermost, name) {
+            Variable::Upvalue(index)
+        } else if let Some(index) = self.globals.declared(name) {
+            Variable::Global(index)
+        } else if let Some(index) = builtins::BUILTINS
+            .iter()
+            .position(|builtin| builtin.name == name)
+        {
+            Variable::Builtin(index, &builtins::BUILTINS[index])
+        } else {
+            Variable::Global(self.globals.intern(name))
+        }
+    }
+
+    fn resolve_local(state: &FunctionState, name: &str) -> Option<u32> {
+        state
+            .locals
+            .iter()
+            .rev()
+            .find(|local| local.name == name)
+            .map(|local| local.slot)
+    }
+
+    /// Finds `name` in the functions enclosing the function at `function`, adding the
+    /// captures needed to reach it.
+    fn resolve_upvalue(&mut self, function: usize, name: &str) -> Option<u32> {
+        if function == 0 {
+            return None;
+        }
+        let capture = match Self::resolve_local(&self.functions[function - 1], name) {
+            Some(slot) => Capture::Local(slot),
+            None => Capture::Upvalue(self.resolve_upvalue(function - 1, name)?),
+        };
+        let captures = &mut self.functions[function].function.captures;
+        let index = match captures.iter().position(|known| *known == capture) {
+            Some(index) => index,
+            None => {
+                captures.push(capture);
+                captures.len() - 1
+            }
+        };
+        Some(index as u32)
+    }
+
+    fn lookup_type(&self, name: &str) -> Option<Type> {
+        self.types
+            .iter()
+            .rev()
+            .find_map(|types| types.get(name).cloned())
+            .or_else(|| EnvFrame::get_type(self.global_types, name))
+    }
+
+    /// Compiles statements evaluating to the value of the last one, or unit without any.
+    fn compile_sequence(&mut self, nodes: &[AST], want: bool) {
+        let Some((last, rest)) = nodes.split_last() else {
+            if want {
+                self.emit(Op::Unit, Span::point(1, 1));
+            }
+            return;
+        };
+        for node in rest {
+            self.compile(node, false);
+        }
+        self.compile(last, want);
+    }
+
+    /// Compiles `node`, leaving its value on the stack if `want` and nothing otherwise.
+    fn compile(&mut self, node: &AST, want: bool) {
+        match node {
+            AST::Print { expressions, span } => {
+                for expr in expressions {
+                    self.compile(expr, true);
+                }
+                self.emit(Op::Print(expressions.len() as u32), *span);
+                self.unit(want, *span);
+            }
+            AST::Body { nodes, span } => {
+                self.begin_scope(nodes);
+                self.compile_sequence(nodes, want);
+                self.end_scope(want, *span);
+            }
+            AST::If {
+                guard,
+                then_body,
+                else_body,
+                span,
+            } => {
+                self.compile(guard, true);
+                let to_else = self.emit(Op::JumpIfFalse(0), *guard.span());
+                let height = self.state().height;
+                self.compile(then_body, want);
+                let to_end = self.emit(Op::Jump(0), *span);
+                self.patch(to_else);
+                self.state().height = height;
+                match else_body {
+                    Some(else_body) => self.compile(else_body, want),
+                    None => self.unit(want, *span),
+                }
+                self.patch(to_end);
+            }
+            AST::While {
+                label,
+                guard,
+                body,
+                span,
+            } => {
+                let result = want.then(|| {
+                    self.emit(Op::Unit, *span);
+                    self.push_hidden()
+                });
+                let start = self.position();
+                self.compile(guard, true);
+                let to_exit = self.emit(Op::JumpIfFalse(0), *guard.span());
+                self.begin_loop(label, start);
+                self.compile(body, want);
+                if let Some(result) = result {
+                    self.emit(Op::SetLocal(result), *span);
+                    self.emit(Op::Pop, *span);
+                }
+                self.emit(Op::Jump(start as u32), *span);
+                self.patch(to_exit);
+                self.end_loop();
+                if result.is_some() {
+                    self.state().locals.pop();
+                }
+            }
+            AST::For {
+                label,
+                variable,
+                iterable,
+                body,
+                span,
+            } => {
+                let next = match iterable.as_ref() {
+                    AST::Range {
+                        start,
+                        end,
+                        inclusive,
+                        span: range_span,
+                    } => {
+                        let slots = [start, end].map(|bound| {
+                            self.compile(bound, true);
+                            self.emit(Op::ExpectInt, *bound.span());
+                            self.push_hidden()
+                        });
+                        let next = Op::RangeNext {
+                            slot: slots[0],
+                            inclusive: *inclusive,
+                            exit: 0,
+                        };
+                        (next, *range_span)
+                    }
+                    _ => {
+                        self.compile(iterable, true);
+                        let slot = self.push_hidden();
+                        self.constant(EvaluationType::Int(0), *iterable.span());
+                        self.push_hidden();
+                        (Op::ArrayNext { slot, exit: 0 }, *iterable.span())
+                    }
+                };
+                let start = self.position();
+                self.begin_loop(label, start);
+                self.emit(next.0, next.1);
+                // Every iteration gets its own scope so closures capture the current element.
+                self.begin_binding_scope(variable);
+                self.compile(body, false);
+                self.end_scope(false, *span);
+                self.emit(Op::Jump(start as u32), *span);
+                self.patch(start);
+                self.end_loop();
+                let state = self.state();
+                state.locals.truncate(state.locals.len() - 2);
+                self.emit(
+                    Op::EndScope {
+                        locals: 2,
+                        keep: false,
+                    },
+                    *span,
+                );
+                self.unit(want, *span);
+            }
+            AST::Range { span, .. } => self.fail(
+                Self::runtime_error(
+                    "Ranges can only be used as the iterable of a for loop",
+                    *span,
+                    ErrorCode::RangeOutsideFor,
+                ),
+                want,
+            ),
+            AST::LetDeclaration {
+                identifier,
+                expr,
+                span,
+                ..
+            } => {
+                // The program was type checked, so unlike the interpreter the declared type is
+                // not checked again at runtime.
+                self.compile(expr, true);
+                self.define_variable(identifier, *span);
+                self.unit(want, *span);
+            }
+            AST::FunctionDeclaration {
+                name,
+                parameters,
+                body,
+                span,
+                ..
+            } => {
+                // The name is declared first so the body can call the function recursively.
+                if self.is_global_scope() {
+                    let index = self.globals.declare(name);
+                    self.compile_function(parameters, body, *span);
+                    self.emit(Op::DefineGlobal(index), *span);
+                } else if let Some(slot) = self.redeclared(name) {
+                    self.compile_function(parameters, body, *span);
+                    self.emit(Op::SetLocal(slot), *span);
+                    self.emit(Op::Pop, *span);
+                } else {
+                    let state = self.state();
+                    Self::add_local(state, name, state.height);
+                    self.compile_function(parameters, body, *span);
+                }
+                self.unit(want, *span);
+            }
+            AST::FunctionExpression {
+                parameters,
+                body,
+                span,
+                ..
+            } => {
+                self.compile_function(parameters, body, *span);
+                self.discard(want, *span);
+            }
+            AST::AliasDeclaration { span, .. }
+            | AST::StructDeclaration { span, .. }
+            | AST::EnumDeclaration { span, .. } => self.unit(want, *span),
+            AST::Return { expr, span } => {
+                self.compile(expr, true);
+                self.emit(Op::Return, *span);
+                self.unit(want, *span);
+            }
+            AST::Break { label, span } => self.compile_jump(label, true, *span, want),
+            AST::Continue { label, span } => self.compile_jump(label, false, *span, want),
+            AST::FunctionCall {
+                callee,
+                arguments,
+                span,
+            } => {
+                if let AST::Identifier { name, .. } = callee.as_ref()
+                    && let Variable::Builtin(index, builtin) = self.resolve(name)
+                {
+                    self.compile_builtin_call(index, builtin, arguments, *span);
+                } else {
+                    self.compile(callee, true);
+                    for argument in arguments {
+                        self.compile(argument, true);
+                    }
+                    self.emit(Op::Call(arguments.len() as u32), *span);
+                }
+                self.discard(want, *span);
+            }
+            AST::UnitLiteral { span } => self.unit(want, *span),
+            AST::NumberLiteral { value, span } => {
+                let value = value
+                    .parse::<i64>()
+                    .expect("the parser checks integer literals");
+                self.literal(EvaluationType::Int(value), want, *span)
+            }
+            AST::FloatLiteral { value, span } => {
+                let value = value
+                    .parse::<f64>()
+                    .expect("the lexer only produces valid floats");
+                self.literal(EvaluationType::Float(value), want, *span)
+            }
+            AST::StringLiteral { value, span } => {
+                self.literal(EvaluationType::String(value.clone()), want, *span)
+            }
+            AST::BoolLiteral { value, span } => {
+                let value = value
+                    .parse::<bool>()
+                    .expect("booleans are 'true' or 'false'");
+                self.literal(EvaluationType::Bool(value), want, *span)
+            }
+            AST::Identifier { name, span, .. } => {
+                let op = match self.resolve(name) {
+                    Variable::Local(slot) => Op::GetLocal(slot),
+                    Variable::Upvalue(index) => Op::GetUpvalue(index),
+                    Variable::Global(index) => Op::GetGlobal(index),
+                    Variable::Builtin(_, builtin) => {
+                        return self.literal(EvaluationType::Builtin(builtin), want, *span);
+                    }
+                };
+                self.emit(op, *span);
+                self.discard(want, *span);
+            }
+            AST::ArrayLiteral { elements, span } => {
+                for element in elements {
+                    self.compile(element, true);
+                }
+                self.emit(Op::MakeArray(elements.len() as u32), *span);
+                self.discard(want, *span);
+            }
+            AST::StructLiteral { name, fields, span } => {
+                for (_, expr) in fields {
+                    self.compile(expr, true);
+                }
+                // Fields are stored in declaration order so printing does not depend on the
+                // order they were written in the literal.
+                let mut order: Vec<(usize, String)> = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(position, (field, _))| (position, field.clone()))
+                    .collect();
+                if let Some(Type::Struct {
+                    fields: declared, ..
+                }) = self.lookup_type(name)
+                {
+                    order.sort_by_key(|(_, field)| {
+                        declared
+                            .iter()
+                            .position(|(declared_field, _)| declared_field == field)
+                    });
+                }
+                let shapes = &mut self.chunk().shapes;
+                shapes.push(StructShape {
+                    name: name.clone(),
+                    fields: order,
+                });
+                let shape = shapes.len() as u32 - 1;
+                self.emit(Op::MakeStruct(shape), *span);
+                self.discard(want, *span);
+            }
+            AST::EnumVariant {
+                enum_name,
+                variant,
+                arguments,
+                span,
+            } => {
+                for argument in arguments {
+                    self.compile(argument, true);
+                }
+                let variants = &mut self.chunk().variants;
+                variants.push((enum_name.clone(), variant.clone()));
+                let variant = variants.len() as u32 - 1;
+                self.emit(
+                    Op::MakeVariant {
+                        variant,
+                        len: arguments.len() as u32,
+                    },
+                    *span,
+                );
+                self.discard(want, *span);
+            }
+            AST::Match {
+                scrutinee,
+                arms,
+                span,
+            } => self.compile_match(scrutinee, arms, *span, want),
+            AST::FieldAccess {
+                object,
+                field,
+                span,
+            } => {
+                self.compile(object, true);
+                let name = self.name(field);
+                self.emit(Op::GetField(name), *span);
+                self.discard(want, *span);
+            }
+            AST::ArrayAccess { array, index, span } => {
+                self.compile(array, true);
+                self.compile(index, true);
+                self.emit(Op::Index, *span);
+                self.discard(want, *span);
+            }
+            AST::BinaryExpression {
+                left,
+                operator,
+                right,
+                span,
+            } if operator == "=" => {
+                self.compile(right, true);
+                match left.as_ref() {
+                    AST::Identifier { name, span, .. } => {
+                        let op = match self.resolve(name) {
+                            Variable::Local(slot) => Op::SetLocal(slot),
+                            Variable::Upvalue(index) => Op::SetUpvalue(index),
+                            Variable::Global(index) => Op::SetGlobal(index),
+                            Variable::Builtin(..) => Op::SetGlobal(self.globals.intern(name)),
+                        };
+                        self.emit(op, *span);
+                    }
+                    AST::ArrayAccess { .. } | AST::FieldAccess { .. } => {
+                        match self.compile_place(left) {
+                            Ok(place) => {
+                                self.emit(Op::SetPlace(place), *span);
+                            }
+                            Err(error) => self.fail(error, false),
+                        }
+                    }
+                    _ => self.fail(
+                        Self::runtime_error(
+                            "Left side of assignment must be an identifier, array access or field access",
+                            *left.span(),
+                            ErrorCode::RuntimeTypeError,
+                        ),
+                        false,
+                    ),
+                }
+                self.discard(want, *span);
+            }
+            AST::BinaryExpression {
+                left,
+                operator,
+                right,
+                span,
+            } => {
+                self.compile(left, true);
+                self.compile(right, true);
+                match BinaryOperator::parse(operator) {
+                    Some(operator) => {
+                        self.emit(Op::Binary(operator), *span);
+                    }
+                    None => {
+                        self.emit(Op::Pop, *span);
+                        self.fail(
+                            Self::runtime_error(
+                                format!("Unsupported binary operator {operator}"),
+                                *span,
+                                ErrorCode::RuntimeTypeError,
+                            ),
+                            false,
+                        );
+                    }
+                }
+                self.discard(want, *span);
+            }
+            AST::UnaryExpression {
+                operator,
+                operand,
+                is_postfix,
+                span,
+            } => {
+                match (operator.as_str(), is_postfix) {
+                    ("-", false) => {
+                        self.compile(operand, true);
+                        self.emit(Op::Negate, *span);
+                    }
+                    ("!", false) => {
+                        self.compile(operand, true);
+                        self.emit(Op::Not, *operand.span());
+                    }
+                    ("++" | "--", _) => match self.compile_place(operand) {
+                        Ok(place) => {
+                            let op = Op::IncrementPlace {
+                                place,
+                                decrement: operator == "--",
+                                postfix: *is_postfix,
+                            };
+                            self.emit(op, *span);
+                        }
+                        Err(error) => self.fail(error, true),
+                    },
+                    _ => {
+                        self.compile(operand, true);
+                        self.fail(
+                            Self::runtime_error(
+                                format!(
+                                    "Unsupported unary operation: {operator} (postfix: {is_postfix})"
+                                ),
+                                *span,
+                                ErrorCode::RuntimeTypeError,
+                            ),
+                            false,
+                        );
+                    }
+                }
+                self.discard(want, *span);
+            }
+            AST::ExpressionStatement { expr, .. } => self.compile(expr, want),
+        }
+    }
+
+    /// Pushes unit when a value is wanted from a node that has none.
+    fn unit(&mut self, want: bool, span: Span) {
+        if want {
+            self.emit(Op::Unit, span);
+        }
+    }
+
+    /// Pops the value just computed when it is not wanted.
+    fn discard(&mut self, want: bool, span: Span) {
+        if !want {
+            self.emit(Op::Pop, span);
+        }
+    }
+
+    fn literal(&mut self, value: EvaluationType, want: bool, span: Span) {
+        if want {
+            self.constant(value, span);
+        }
+    }
+
+    fn begin_loop(&mut self, label: &Option<String>, start: usize) {
+        let state = self.state();
+        let height = state.height;
+        state.loops.push(Loop {
+            label: label.clone(),
+            height,
+            start,
+            breaks: vec![],
+        });
+    }
+
+    fn end_loop(&mut self) {
+        let breaks = self.state().loops.pop().expect("a loop is open").breaks;
+        for at in breaks {
+            self.patch(at);
+        }
+    }
+
+    /// Compiles `break` or `continue`, dropping the values pushed since the loop started.
+    fn compile_jump(&mut self, label: &Option<String>, is_break: bool, span: Span, want: bool) {
+        let state = self.state();
+        // Unlabeled jumps always stop at the innermost loop.
+        let Some(target) = state
+            .loops
+            .iter()
+            .rposition(|target| label.is_none() || target.label == *label)
+        else {
+            let keyword = if is_break { "break" } else { "continue" };
+            let error = Self::runtime_error(
+                format!("Unexpected '{keyword}' outside of loop"),
+                span,
+                ErrorCode::JumpOutOfFunction,
+            );
+            return self.fail(error, want);
+        };
+
+        let height = state.height;
+        let locals = height - state.loops[target].height;
+        let start = state.loops[target].start as u32;
+        if locals > 0 {
+            self.emit(
+                Op::EndScope {
+                    locals,
+                    keep: false,
+                },
+                span,
+            );
+        }
+        if is_break {
+            let at = self.emit(Op::Jump(0), span);
+            self.state().loops[target].breaks.push(at);
+        } else {
+            self.emit(Op::Jump(start), span);
+        }
+        self.state().height = height;
+        self.unit(want, span);
+    }
+
+    /// Compiles a function and the instruction creating its closure.
+    fn compile_function(&mut self, parameters: &[Parameter], body: &AST, span: Span) {
+        self.functions.push(FunctionState::new(parameters));
+        self.compile(body, true);
+        self.emit(Op::Return, *body.span());
+        let function = self
+            .functions
+            .pop()
+            .expect("the function is compiled")
+            .function;
+
+        let functions = &mut self.chunk().functions;
+        functions.push(Rc::new(function));
+        let index = functions.len() as u32 - 1;
+        self.emit(Op::Closure(index), span);
+    }
+
+    fn compile_builtin_call(
+        &mut self,
+        index: usize,
+        builtin: &'static Builtin,
+        arguments: &[AST],
+        span: Span,
+    ) {
+        let builtin_index = index as u32;
+        match builtin.call {
+            BuiltinCall::Mutating(_) => {
+                let Some((target, rest)) = arguments.split_first() else {
+                    let error = Self::runtime_error(
+                        format!("'{}' expects at least one argument", builtin.name),
+                        span,
+                        ErrorCode::RuntimeTypeError,
+                    );
+                    return self.fail(error, true);
+                };
+                let place = match self.compile_place(target) {
+                    Ok(place) => place,
+                    Err(error) => return self.fail(error, true),
+                };
+                for argument in rest {
+                    self.compile(argument, true);
+                }
+                let op = Op::CallMutating {
+                    builtin: builtin_index,
+                    place,
+                    arguments: rest.len() as u32,
+                };
+                self.emit(op, span);
+            }
+            BuiltinCall::Pure(_) | BuiltinCall::Input(_) => {
+                for argument in arguments {
+                    self.compile(argument, true);
+                }
+                let op = Op::CallBuiltin {
+                    builtin: builtin_index,
+                    arguments: arguments.len() as u32,
+                };
+                self.emit(op, span);
+            }
+        }
+    }
+
+    /// Compiles the indices of an assignable expression, returning the place it refers to.
+    fn compile_place(&mut self, node: &AST) -> Result<u32, FlavorError> {
+        let mut accessors = Vec::new();
+        let mut current = node;
+        let (name, span) = loop {
+            match current {
+                AST::Identifier { name, span, .. } => break (name, *span),
+                AST::ArrayAccess { array, .. } => {
+                    accessors.push(current);
+                    current = array.as_ref();
+                }
+                AST::FieldAccess { object, .. } => {
+                    accessors.push(current);
+                    current = object.as_ref();
+                }
+                _ => {
+                    return Err(Self::runtime_error(
+                        "Assignment target must be an identifier, array access or field access",
+                        *current.span(),
+                        ErrorCode::RuntimeTypeError,
+                    ));
+                }
+            }
+        };
+
+        let root = match self.resolve(name) {
+            Variable::Local(slot) => PlaceRoot::Local(slot),
+            Variable::Upvalue(index) => PlaceRoot::Upvalue(index),
+            Variable::Global(index) => PlaceRoot::Global(index),
+            Variable::Builtin(..) => PlaceRoot::Global(self.globals.intern(name)),
+        };
+        let mut place = Place {
+            root,
+            name: name.clone(),
+            span,
+            accessors: Vec::with_capacity(accessors.len()),
+            indices: 0,
+        };
+        for accessor in accessors.into_iter().rev() {
+            match accessor {
+                AST::ArrayAccess { array, index, .. } => {
+                    self.compile(index, true);
+                    self.emit(Op::CheckIndex, *index.span());
+                    place.indices += 1;
+                    place.accessors.push(Accessor::Index {
+                        index_span: *index.span(),
+                        array_span: *array.span(),
+                    });
+                }
+                AST::FieldAccess { field, span, .. } => place.accessors.push(Accessor::Field {
+                    name: field.clone(),
+                    span: *span,
+                }),
+                _ => unreachable!("only accessors are collected"),
+            }
+        }
+
+        let places = &mut self.chunk().places;
+        places.push(place);
+        Ok(places.len() as u32 - 1)
+    }
+
+    /// The scrutinee stays in a slot while the arms are tried in order. Block arms evaluate to
+    /// unit, like the type checker expects.
+    fn compile_match(&mut self, scrutinee: &AST, arms: &[MatchArm], span: Span, want: bool) {
+        self.compile(scrutinee, true);
+        let slot = self.push_hidden();
+        let mut to_end = Vec::with_capacity(arms.len());
+        for arm in arms {
+            let height = self.state().height;
+            let mut to_next = None;
+            match &arm.pattern {
+                Pattern::Wildcard { .. } => {
+                    self.types.push(HashMap::new());
+                    let state = self.state();
+                    state.scopes.push(state.height);
+                }
+                Pattern::Binding { name, span } => {
+                    self.emit(Op::GetLocal(slot), *span);
+                    self.begin_binding_scope(name);
+                }
+                Pattern::Variant {
+                    variant,
+                    bindings,
+                    span,
+                    ..
+                } => {
+                    let variant = self.name(variant);
+                    let test = Op::JumpIfNotVariant {
+                        slot,
+                        variant,
+                        target: 0,
+                    };
+                    to_next = Some(self.emit(test, *span));
+                    self.types.push(HashMap::new());
+                    let state = self.state();
+                    state.scopes.push(state.height);
+                    for (index, name) in bindings.iter().enumerate() {
+                        if name == "_" {
+                            continue;
+                        }
+                        let index = index as u32;
+                        self.emit(Op::GetVariantField { slot, index }, *span);
+                        self.define_variable(name, *span);
+                    }
+                }
+            }
+            if matches!(arm.body, AST::Body { .. }) {
+                self.compile(&arm.body, false);
+                self.unit(want, arm.span);
+            } else {
+                self.compile(&arm.body, want);
+            }
+            self.end_scope(want, arm.span);
+            to_end.push(self.emit(Op::Jump(0), arm.span));
+            if let Some(to_next) = to_next {
+                self.patch(to_next);
+            }
+            self.state().height = height;
+        }
+        self.emit(Op::NoMatch(slot), span);
+        self.unit(want, span);
+        for at in to_end {
+            self.patch(at);
+        }
+        self.state().locals.pop();
+        let op = Op::EndScope {
+            locals: 1,
+            keep: want,
+        };
+        self.emit(op, span);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::error::FlavorError;
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use crate::interpreter::{EvalOutcome, EvaluationType, Interpreter, NativeFunction};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::typechecker::TypeChecker;
+use crate::types::{ASTNode, Type};
+use crate::vm::Vm;
+
+/// How an `Engine` runs the programs it checked.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    /// Walk the syntax tree with an `Interpreter`
+    #[default]
+    Interpreter,
+    /// Compile to bytecode run by a `Vm`, which is faster
+    Bytecode,
+}
+
+enum Runtime {
+    Interpreter(Interpreter),
+    Bytecode(Vm),
+}
+
+/// Checks and runs Flavor programs on behalf of a host.
+///
+/// Declarations are kept from one program to the next, so sources run through the same
+/// engine build on each other the way REPL inputs do. A program that fails to type check or
+/// to run leaves the type checker as it was before it.
+///
+/// ```
+/// let mut engine = flavor::Engine::new();
+/// engine.run_source("fn double(x: int) -> int { return x * 2; }").unwrap();
+/// let value = engine.run_source("double(21);").unwrap();
+/// assert_eq!(value.to_string(), "42");
+/// ```
+pub struct Engine {
+    checker: TypeChecker,
+    runtime: Runtime,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::with_backend(Backend::default())
+    }
+
+    /// An engine running its programs with `backend`.
+    pub fn with_backend(backend: Backend) -> Self {
+        Self {
+            checker: TypeChecker::new(),
+            runtime: match backend {
+                Backend::Interpreter => Runtime::Interpreter(Interpreter::new()),
+                Backend::Bytecode => Runtime::Bytecode(Vm::new()),
+            },
+        }
+    }
+
+    /// Sends the output of `print` to `output` instead of standard output.
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        *self.output() = Box::new(output);
+        self
+    }
+
+    /// Makes `read_line` read from `input` instead of standard input.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        let input: Box<dyn BufRead> = Box::new(input);
+        match &mut self.runtime {
+            Runtime::Interpreter(interpreter) => interpreter.input = Some(input),
+            Runtime::Bytecode(vm) => vm.input = Some(input),
+        }
+        self
+    }
+
+    fn output(&mut self) -> &mut Box<dyn Write> {
+        match &mut self.runtime {
+            Runtime::Interpreter(interpreter) => &mut interpreter.output,
+            Runtime::Bytecode(vm) => &mut vm.output,
+        }
+    }
+
+    /// Defines a variable of type `ty` visible to every program run afterwards.
+    pub fn define_global(&mut self, name: &str, ty: Type, value: EvaluationType) {
+        self.checker.insert(name.to_string(), ty);
+        match &mut self.runtime {
+            Runtime::Interpreter(interpreter) => interpreter.define_global(name, value),
+            Runtime::Bytecode(vm) => vm.define_global(name, value),
+        }
+    }
+
+    /// Makes `function` callable as `name` from every program run afterwards.
+    ///
+    /// `signature` is the Flavor type of the function and is what the type checker sees, so
+    /// the arguments `function` receives already match its parameter types. An `Err` message
+    /// is reported as a runtime error at the call site, and so is a result that does not
+    /// match the declared return type.
+    ///
+    /// # Panics
+    ///
+    /// If `signature` is not a `Type::Function`.
+    pub fn register_function<F>(&mut self, name: &str, signature: Type, function: F)
+    where
+        F: Fn(&[EvaluationType]) -> Result<EvaluationType, String> + 'static,
+    {
+        let Type::Function {
+            param_types,
+            return_type,
+        } = &signature
+        else {
+            panic!("host function '{name}' must have a function type, found {signature}");
+        };
+        let native = NativeFunction {
+            name: name.to_string(),
+            param_types: param_types.clone(),
+            return_type: return_type.as_ref().clone(),
+            call: Box::new(function),
+        };
+        self.define_global(name, signature, EvaluationType::Native(Rc::new(native)));
+    }
+
+    /// Lexes and parses `source`.
+    /// Lexing stops at the first error, while every syntax error is reported.
+    pub fn parse(source: &str) -> Result<Vec<ASTNode>, Vec<FlavorError>> {
+        let mut lexer = Lexer::new(source);
+        lexer.lexe().map_err(|err| vec![err])?;
+        let (nodes, errors) = Parser::new(lexer.tokens).parse_program();
+        if errors.is_empty() {
+            Ok(nodes)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Type checks `nodes` against the declarations made so far and keeps theirs on success.
+    pub fn check(&mut self, nodes: &[ASTNode]) -> Result<(), Vec<FlavorError>> {
+        let snapshot = self.checker.clone();
+        self.checker.check_program(nodes).inspect_err(|_| {
+            self.checker = snapshot;
+        })
+    }
+
+    /// Type checks a lone expression without running it or keeping anything it declares.
+    pub fn type_of(&self, expr: &ASTNode) -> Result<Type, Vec<FlavorError>> {
+        self.checker.clone().check_expression(expr)
+    }
+
+    /// Runs nodes that went through `check` and returns the value of the last statement.
+    pub fn run(&mut self, nodes: &[ASTNode]) -> Result<EvaluationType, FlavorError> {
+        let interpreter = match &mut self.runtime {
+            Runtime::Interpreter(interpreter) => interpreter,
+            Runtime::Bytecode(vm) => return vm.run(nodes),
+        };
+        Ok(match interpreter.eval_program(nodes)? {
+            EvalOutcome::Value(value) | EvalOutcome::Return(value) => value,
+            EvalOutcome::Break(_) | EvalOutcome::Continue(_) => EvaluationType::Unit,
+        })
+    }
+
+    /// Type checks and runs `nodes`. If running fails, the type checker forgets their
+    /// declarations again, while the effects of the statements that did run are kept.
+    pub fn execute(&mut self, nodes: &[ASTNode]) -> Result<EvaluationType, Vec<FlavorError>> {
+        let snapshot = self.checker.clone();
+        self.check(nodes)?;
+        self.run(nodes).map_err(|err| {
+            self.checker = snapshot;
+            vec![err]
+        })
+    }
+
+    /// Parses, type checks and runs `source`, returning the value of its last statement.
+    pub fn run_source(&mut self, source: &str) -> Result<EvaluationType, Vec<FlavorError>> {
+        let nodes = Self::parse(source)?;
+        self.execute(&nodes)
+    }
+
+    /// Like `run_source`, but returns what the program printed instead of writing it to the
+    /// output of the engine. Output printed before a runtime error is dropped with it.
+    pub fn run_to_string(&mut self, source: &str) -> Result<String, Vec<FlavorError>> {
+        let buffer = SharedBuffer::default();
+        let output = std::mem::replace(self.output(), Box::new(buffer.clone()));
+        let result = self.run_source(source);
+        *self.output() = output;
+        result.map(|_| String::from_utf8_lossy(&buffer.0.borrow()).into_owned())
+    }
+}
+
+/// Bytes written by a program that stay readable by the engine.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,41 +1,96 @@
 use colored::*;
+use std::collections::BTreeSet;
 use std::fmt::Display;
 
+use crate::error_codes::ErrorCode;
+use crate::json::Json;
 use crate::types::Span;
-use std::cmp::max;
 
 #[derive(Debug, Clone)]
 pub enum ErrorPhase {
     Lexing,
     Parsing,
     TypeChecking,
+    Linting,
     Runtime,
 }
 
+/// How serious a diagnostic is. Only errors stop the program from running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
 impl Display for ErrorPhase {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ErrorPhase::Lexing => write!(f, "Lexing"),
             ErrorPhase::Parsing => write!(f, "Parsing"),
             ErrorPhase::TypeChecking => write!(f, "TypeChecking"),
+            ErrorPhase::Linting => write!(f, "Linting"),
             ErrorPhase::Runtime => write!(f, "Runtime"),
         }
     }
 }
 
+/// A secondary span of a diagnostic, e.g. the declaration involved in a type mismatch.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Kind of a line printed below the source snippet of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteKind {
+    /// Extra context about the problem
+    Note,
+    /// A suggestion on how to fix it
+    Help,
+}
+
+impl Display for NoteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoteKind::Note => write!(f, "note"),
+            NoteKind::Help => write!(f, "help"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FlavorError {
     pub phase: ErrorPhase,
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
     pub message: String,
     pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<(NoteKind, String)>,
 }
 
 impl FlavorError {
     pub fn new(phase: ErrorPhase, message: impl Into<String>, span: Option<Span>) -> Self {
         Self {
             phase,
+            severity: Severity::Error,
+            code: None,
             message: message.into(),
             span,
+            labels: vec![],
+            notes: vec![],
         }
     }
 
@@ -43,65 +98,431 @@ impl FlavorError {
         Self::new(phase, message, Some(span))
     }
 
+    pub fn warning(phase: ErrorPhase, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::with_span(phase, message, span)
+        }
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>) -> Self {
+        self.notes.push((NoteKind::Note, message.into()));
+        self
+    }
+
+    pub fn with_help(mut self, message: impl Into<String>) -> Self {
+        self.notes.push((NoteKind::Help, message.into()));
+        self
+    }
+
+    /// Describes the diagnostic as a JSON object for editors and CI. The schema is stable:
+    /// fields may be added in later releases but are never renamed or removed.
+    ///
+    /// ```json
+    /// {"severity": "error", "phase": "TypeChecking", "code": "F0203", "message": "...",
+    ///  "file": "main.flv",
+    ///  "span": {"start_line": 1, "start_column": 1, "end_line": 1, "end_column": 5},
+    ///  "labels": [{"message": "...", "span": {...}}],
+    ///  "notes": [{"kind": "help", "message": "..."}]}
+    /// ```
+    ///
+    /// `code` and `span` are `null` when the diagnostic has none. Columns count characters,
+    /// start at 1 and `end_column` is inclusive.
+    pub fn to_json(&self, file: &str) -> Json {
+        Json::object([
+            ("severity", Json::string(self.severity.to_string())),
+            ("phase", Json::string(self.phase.to_string())),
+            ("code", self.code.map(ErrorCode::as_str).into()),
+            ("message", Json::string(&self.message)),
+            ("file", file.into()),
+            ("span", self.span.into()),
+            (
+                "labels",
+                Json::Array(
+                    self.labels
+                        .iter()
+                        .map(|label| {
+                            Json::object([
+                                ("message", Json::string(&label.message)),
+                                ("span", label.span.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "notes",
+                Json::Array(
+                    self.notes
+                        .iter()
+                        .map(|(kind, message)| {
+                            Json::object([
+                                ("kind", Json::string(kind.to_string())),
+                                ("message", Json::string(message)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    /// Renders the diagnostic the way rustc does: the source lines of every span with the
+    /// primary span underlined by `^`, labels underlined by `-`, multi-line spans drawn in
+    /// the gutter and notes and help lines at the bottom.
     pub fn render(&self, source: &str) -> String {
-        let phase = format!("[{}]", self.phase);
-        match &self.span {
-            Some(span) => {
-                let line_index = span.start_line.saturating_sub(1);
-                let lines: Vec<&str> = source.lines().collect();
-                let line_text = lines.get(line_index).copied().unwrap_or("");
-                let line_length = line_text.chars().count();
-
-                let mut pointer_offset = span.start_column.saturating_sub(1);
-                if pointer_offset > line_length {
-                    pointer_offset = line_length;
+        let phase = match (self.severity, self.code) {
+            (Severity::Error, None) => format!("[{}]", self.phase),
+            (severity, None) => format!("[{}] {severity}:", self.phase),
+            (severity, Some(code)) => format!("[{}] {severity}[{code}]:", self.phase),
+        };
+        let mut out = format!("\n\n{} {}\n", phase.yellow().bold(), self.message.yellow());
+
+        if let Some(span) = self.span {
+            let annotations: Vec<Annotation> = std::iter::once(Annotation {
+                span,
+                message: "",
+                primary: true,
+            })
+            .chain(self.labels.iter().map(|label| Annotation {
+                span: label.span,
+                message: &label.message,
+                primary: false,
+            }))
+            .collect();
+            let lines: Vec<&str> = source.lines().collect();
+            let shown = shown_lines(&annotations);
+            let gutter = shown.last().copied().unwrap_or(1).to_string().len();
+
+            out.push_str(&format!(
+                "{:gutter$}--> {}:{}\n{:gutter$} |\n",
+                "", span.start_line, span.start_column, ""
+            ));
+
+            let mut previous: Option<usize> = None;
+            for line in shown {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    out.push_str("...\n");
+                }
+                previous = Some(line);
+
+                let text = lines.get(line - 1).copied().unwrap_or("");
+                let mut source_row = Row::gutter(&annotations, line);
+                let multiline = annotations.iter().filter(|a| a.is_multiline());
+                for (column, annotation) in multiline.enumerate() {
+                    if annotation.opens_line(line, text) {
+                        source_row.put(column, '/', annotation.style());
+                    }
                 }
+                source_row.put_str(source_row.margin, &expand_tabs(text), Style::Plain);
+                out.push_str(&format!("{line:>gutter$} | {}\n", source_row.render()));
+                for row in annotation_rows(&annotations, line, text) {
+                    out.push_str(&format!("{:gutter$} | {}\n", "", row.render()));
+                }
+            }
+
+            for (kind, note) in &self.notes {
+                let kind = format!("{kind}:");
+                out.push_str(&format!("{:gutter$} = {} {note}\n", "", kind.bold()));
+            }
+        } else {
+            for (kind, note) in &self.notes {
+                let kind = format!("{kind}:");
+                out.push_str(&format!("= {} {note}\n", kind.bold()));
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Spans longer than this many lines only show their first and last two lines.
+const MAX_SPAN_LINES: usize = 6;
+const TAB_WIDTH: usize = 4;
+
+struct Annotation<'a> {
+    span: Span,
+    message: &'a str,
+    primary: bool,
+}
+
+impl Annotation<'_> {
+    fn is_multiline(&self) -> bool {
+        self.span.start_line < self.span.end_line
+    }
+
+    /// Whether this multi-line span starts at the first character of `line`, in which case
+    /// it is drawn as `/` in the margin instead of with a start marker.
+    fn opens_line(&self, line: usize, text: &str) -> bool {
+        let indent = text.chars().take_while(|ch| ch.is_whitespace()).count();
+        self.is_multiline() && self.span.start_line == line && self.span.start_column == indent + 1
+    }
+
+    fn style(&self) -> Style {
+        if self.primary {
+            Style::Primary
+        } else {
+            Style::Secondary
+        }
+    }
+
+    fn marker(&self) -> char {
+        if self.primary { '^' } else { '-' }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    Plain,
+    Primary,
+    Secondary,
+}
+
+impl Style {
+    fn paint(self, text: &str) -> String {
+        match self {
+            Style::Plain => text.to_string(),
+            Style::Primary => text.red().bold().to_string(),
+            Style::Secondary => text.blue().bold().to_string(),
+        }
+    }
+}
+
+/// One line of output below the gutter, built cell by cell so markers of several spans can
+/// be drawn on it. Each multi-line span owns one column of the left margin.
+struct Row {
+    cells: Vec<(char, Style)>,
+    margin: usize,
+}
+
+impl Row {
+    /// Starts a row with the vertical bars of the multi-line spans that started before `line`
+    /// and have not ended yet.
+    fn gutter(annotations: &[Annotation], line: usize) -> Self {
+        let multiline: Vec<&Annotation> = annotations.iter().filter(|a| a.is_multiline()).collect();
+        let mut row = Row {
+            cells: vec![],
+            margin: if multiline.is_empty() {
+                0
+            } else {
+                multiline.len() + 1
+            },
+        };
+        for (column, annotation) in multiline.iter().enumerate() {
+            if (annotation.span.start_line + 1..=annotation.span.end_line).contains(&line) {
+                row.put(column, '|', annotation.style());
+            }
+        }
+        row
+    }
+
+    fn put(&mut self, column: usize, ch: char, style: Style) {
+        if self.cells.len() <= column {
+            self.cells.resize(column + 1, (' ', Style::Plain));
+        }
+        self.cells[column] = (ch, style);
+    }
+
+    fn put_str(&mut self, column: usize, text: &str, style: Style) {
+        for (offset, ch) in text.chars().enumerate() {
+            self.put(column + offset, ch, style);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut chunk = String::new();
+        let mut chunk_style = Style::Plain;
+        for &(ch, style) in &self.cells {
+            if style != chunk_style && !chunk.is_empty() {
+                out.push_str(&chunk_style.paint(&chunk));
+                chunk.clear();
+            }
+            chunk_style = style;
+            chunk.push(ch);
+        }
+        out.push_str(&chunk_style.paint(&chunk));
+        out.trim_end().to_string()
+    }
+}
+
+/// Line numbers to print: every line holding a single-line span and the lines of multi-line
+/// spans, skipping the middle of long ones.
+fn shown_lines(annotations: &[Annotation]) -> BTreeSet<usize> {
+    let mut lines = BTreeSet::new();
+    for annotation in annotations {
+        let Span {
+            start_line,
+            end_line,
+            ..
+        } = annotation.span;
+        let start_line = start_line.max(1);
+        let end_line = end_line.max(start_line);
+        if end_line - start_line < MAX_SPAN_LINES {
+            lines.extend(start_line..=end_line);
+        } else {
+            lines.extend([start_line, start_line + 1, end_line - 1, end_line]);
+        }
+    }
+    lines
+}
+
+/// The rows drawn below source `line`: underlines of the single-line spans on it with their
+/// messages, then the start and end markers of multi-line spans.
+fn annotation_rows(annotations: &[Annotation], line: usize, text: &str) -> Vec<Row> {
+    let mut rows = vec![];
 
-                let raw_width = if span.start_line == span.end_line {
-                    span.end_column.saturating_sub(span.start_column) + 1
-                } else {
-                    1
-                };
-                let available = line_length.saturating_sub(pointer_offset);
-                let highlight_width = max(1, raw_width.min(available.max(1)));
-
-                let pointer_line = format!(
-                    "{space}{marker}",
-                    space = " ".repeat(pointer_offset),
-                    marker = "^".repeat(highlight_width)
-                );
-
-                let line_pre_error = if span.start_column >= 2 {
-                    &line_text[0..(span.start_column - 1).min(line_text.len())]
-                } else {
-                    ""
-                };
-                let line_post_error = if span.end_column < line_text.len() {
-                    &line_text[(span.end_column).min(line_text.len())..]
-                } else {
-                    ""
-                };
-
-                format!(
-                    "\n\n{} {}\n--> {}:{}\n{:>4} | {}{}{}\n     | {}\n\n",
-                    phase.yellow().bold(),
-                    self.message.yellow(),
-                    span.start_line,
-                    span.start_column,
-                    span.start_line,
-                    line_pre_error,
-                    line_text
-                        .chars()
-                        .skip(pointer_offset)
-                        .take(highlight_width)
-                        .collect::<String>()
-                        .red(),
-                    line_post_error,
-                    pointer_line.red()
-                )
+    let mut single: Vec<(&Annotation, usize, usize)> = annotations
+        .iter()
+        .filter(|a| !a.is_multiline() && a.span.start_line == line)
+        .map(|a| {
+            let (start, width) = underline(text, a.span.start_column, a.span.end_column);
+            (a, start, width)
+        })
+        .collect();
+    if !single.is_empty() {
+        single.sort_by_key(|(_, start, _)| *start);
+        let mut row = Row::gutter(annotations, line);
+        let margin = row.margin;
+        // The primary span is drawn last so it stays visible where spans overlap
+        for (annotation, start, width) in single.iter().filter(|(a, ..)| !a.primary) {
+            for column in *start..start + width {
+                row.put(margin + column, annotation.marker(), annotation.style());
             }
-            None => format!("\n\n{} {}\n\n", phase.yellow().bold(), self.message),
         }
+        for (annotation, start, width) in single.iter().filter(|(a, ..)| a.primary) {
+            for column in *start..start + width {
+                row.put(margin + column, annotation.marker(), annotation.style());
+            }
+        }
+
+        // The rightmost message goes on the underline row, the others hang below it
+        let mut messages: Vec<&(&Annotation, usize, usize)> = single
+            .iter()
+            .filter(|(a, ..)| !a.message.is_empty())
+            .collect();
+        if let Some((annotation, start, width)) = messages.last()
+            && single.iter().all(|(_, other, _)| other <= start)
+        {
+            let column = row.cells.len().max(margin + start + width) + 1;
+            row.put_str(column, annotation.message, annotation.style());
+            messages.pop();
+        }
+        rows.push(row);
+
+        if !messages.is_empty() {
+            let mut connectors = Row::gutter(annotations, line);
+            for (annotation, start, _) in &messages {
+                connectors.put(margin + start, '|', annotation.style());
+            }
+            rows.push(connectors);
+        }
+        while let Some((annotation, start, _)) = messages.pop() {
+            let mut row = Row::gutter(annotations, line);
+            for (other, other_start, _) in &messages {
+                row.put(margin + other_start, '|', other.style());
+            }
+            row.put_str(margin + start, annotation.message, annotation.style());
+            rows.push(row);
+        }
+    }
+
+    let multiline = annotations.iter().filter(|a| a.is_multiline());
+    for (column, annotation) in multiline.clone().enumerate() {
+        if annotation.span.start_line.max(1) != line || annotation.opens_line(line, text) {
+            continue;
+        }
+        let mut row = Row::gutter(annotations, line);
+        let (start, _) = underline(
+            text,
+            annotation.span.start_column,
+            annotation.span.start_column,
+        );
+        for cell in column + 1..row.margin + start {
+            row.put(cell, '_', annotation.style());
+        }
+        row.put(row.margin + start, annotation.marker(), annotation.style());
+        rows.push(row);
+    }
+    for (column, annotation) in multiline.enumerate() {
+        if annotation.span.end_line != line {
+            continue;
+        }
+        let mut row = Row::gutter(annotations, line);
+        let (end, _) = underline(text, annotation.span.end_column, annotation.span.end_column);
+        for cell in column + 1..row.margin + end {
+            row.put(cell, '_', annotation.style());
+        }
+        row.put(row.margin + end, annotation.marker(), annotation.style());
+        if !annotation.message.is_empty() {
+            row.put_str(row.margin + end + 2, annotation.message, annotation.style());
+        }
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// Display column and width of the characters `start_column..=end_column` (1-based) of
+/// `text`. Spans past the end of the line point just after its last character.
+fn underline(text: &str, start_column: usize, end_column: usize) -> (usize, usize) {
+    let start_index = start_column.saturating_sub(1);
+    let start: usize = text.chars().take(start_index).map(char_width).sum();
+    let width: usize = text
+        .chars()
+        .skip(start_index)
+        .take((end_column + 1).saturating_sub(start_column.max(1)))
+        .map(char_width)
+        .sum();
+    (start, width.max(1))
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+/// Number of terminal columns `ch` takes up: zero for combining marks and control characters,
+/// two for East Asian wide characters and emoji.
+fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0x09 => TAB_WIDTH,
+        0x00..=0x1F
+        | 0x7F..=0x9F
+        | 0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
     }
 }
 
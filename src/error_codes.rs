@@ -0,0 +1,1139 @@
+use std::fmt::Display;
+
+/// Stable identifier of a kind of diagnostic, printed as e.g. `F0203`.
+///
+/// Codes are grouped by phase: `F00xx` lexing, `F01xx` parsing, `F02xx` type checking,
+/// `F03xx` runtime and `F04xx` lint warnings. A code is never reused for a different
+/// problem once released, so documentation can link to it. Run `flavor --explain <code>`
+/// for a long-form explanation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnknownToken,
+    UnterminatedString,
+    InvalidEscape,
+    UnterminatedComment,
+
+    UnexpectedToken,
+    LabelWithoutLoop,
+    ExpectedExpression,
+    ExpectedType,
+    IntegerLiteralTooLarge,
+
+    UndeclaredIdentifier,
+    UnknownType,
+    LetTypeMismatch,
+    AssignmentTypeMismatch,
+    ArgumentCountMismatch,
+    ArgumentTypeMismatch,
+    ReturnTypeMismatch,
+    MissingReturn,
+    NotCallable,
+    BuiltinNotCalled,
+    InvalidOperandTypes,
+    UnknownOperator,
+    NotAssignable,
+    JumpOutsideLoop,
+    UndeclaredLabel,
+    ShadowedLabel,
+    DuplicateField,
+    DuplicateVariant,
+    DuplicateType,
+    RecursiveType,
+    ArrayElementMismatch,
+    NonIntegerIndex,
+    NotIndexable,
+    FunctionExpressionMismatch,
+    NotAStruct,
+    FieldInitializedTwice,
+    UnknownField,
+    FieldTypeMismatch,
+    MissingFields,
+    VariantArityMismatch,
+    VariantPayloadMismatch,
+    FieldAccessOnNonStruct,
+    NonBooleanGuard,
+    NonIntegerRange,
+    NotIterable,
+    RangeOutsideFor,
+    NotAnEnum,
+    UnknownVariant,
+    MatchArmTypeMismatch,
+    NonExhaustiveMatch,
+    PatternTypeMismatch,
+    PatternArityMismatch,
+    BuiltinArgumentMismatch,
+
+    RuntimeTypeError,
+    DivisionByZero,
+    IndexOutOfBounds,
+    NegativeIndex,
+    EmptyArray,
+    NoMatchingArm,
+    JumpOutOfFunction,
+    HostFunctionFailed,
+    IoFailed,
+    IntegerOverflow,
+
+    UnusedBinding,
+    UnreachableCode,
+    ShadowedBinding,
+    ConstantGuard,
+}
+
+impl ErrorCode {
+    pub const ALL: &[ErrorCode] = &[
+        ErrorCode::UnknownToken,
+        ErrorCode::UnterminatedString,
+        ErrorCode::InvalidEscape,
+        ErrorCode::UnterminatedComment,
+        ErrorCode::UnexpectedToken,
+        ErrorCode::LabelWithoutLoop,
+        ErrorCode::ExpectedExpression,
+        ErrorCode::ExpectedType,
+        ErrorCode::IntegerLiteralTooLarge,
+        ErrorCode::UndeclaredIdentifier,
+        ErrorCode::UnknownType,
+        ErrorCode::LetTypeMismatch,
+        ErrorCode::AssignmentTypeMismatch,
+        ErrorCode::ArgumentCountMismatch,
+        ErrorCode::ArgumentTypeMismatch,
+        ErrorCode::ReturnTypeMismatch,
+        ErrorCode::MissingReturn,
+        ErrorCode::NotCallable,
+        ErrorCode::BuiltinNotCalled,
+        ErrorCode::InvalidOperandTypes,
+        ErrorCode::UnknownOperator,
+        ErrorCode::NotAssignable,
+        ErrorCode::JumpOutsideLoop,
+        ErrorCode::UndeclaredLabel,
+        ErrorCode::ShadowedLabel,
+        ErrorCode::DuplicateField,
+        ErrorCode::DuplicateVariant,
+        ErrorCode::DuplicateType,
+        ErrorCode::RecursiveType,
+        ErrorCode::ArrayElementMismatch,
+        ErrorCode::NonIntegerIndex,
+        ErrorCode::NotIndexable,
+        ErrorCode::FunctionExpressionMismatch,
+        ErrorCode::NotAStruct,
+        ErrorCode::FieldInitializedTwice,
+        ErrorCode::UnknownField,
+        ErrorCode::FieldTypeMismatch,
+        ErrorCode::MissingFields,
+        ErrorCode::VariantArityMismatch,
+        ErrorCode::VariantPayloadMismatch,
+        ErrorCode::FieldAccessOnNonStruct,
+        ErrorCode::NonBooleanGuard,
+        ErrorCode::NonIntegerRange,
+        ErrorCode::NotIterable,
+        ErrorCode::RangeOutsideFor,
+        ErrorCode::NotAnEnum,
+        ErrorCode::UnknownVariant,
+        ErrorCode::MatchArmTypeMismatch,
+        ErrorCode::NonExhaustiveMatch,
+        ErrorCode::PatternTypeMismatch,
+        ErrorCode::PatternArityMismatch,
+        ErrorCode::BuiltinArgumentMismatch,
+        ErrorCode::RuntimeTypeError,
+        ErrorCode::DivisionByZero,
+        ErrorCode::IndexOutOfBounds,
+        ErrorCode::NegativeIndex,
+        ErrorCode::EmptyArray,
+        ErrorCode::NoMatchingArm,
+        ErrorCode::JumpOutOfFunction,
+        ErrorCode::HostFunctionFailed,
+        ErrorCode::IoFailed,
+        ErrorCode::IntegerOverflow,
+        ErrorCode::UnusedBinding,
+        ErrorCode::UnreachableCode,
+        ErrorCode::ShadowedBinding,
+        ErrorCode::ConstantGuard,
+    ];
+
+    /// Finds the code printed as `code`, ignoring case.
+    pub fn parse(code: &str) -> Option<ErrorCode> {
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|known| known.as_str().eq_ignore_ascii_case(code))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownToken => "F0001",
+            ErrorCode::UnterminatedString => "F0002",
+            ErrorCode::InvalidEscape => "F0003",
+            ErrorCode::UnterminatedComment => "F0004",
+
+            ErrorCode::UnexpectedToken => "F0101",
+            ErrorCode::LabelWithoutLoop => "F0102",
+            ErrorCode::ExpectedExpression => "F0103",
+            ErrorCode::ExpectedType => "F0104",
+            ErrorCode::IntegerLiteralTooLarge => "F0105",
+
+            ErrorCode::UndeclaredIdentifier => "F0201",
+            ErrorCode::UnknownType => "F0202",
+            ErrorCode::LetTypeMismatch => "F0203",
+            ErrorCode::AssignmentTypeMismatch => "F0204",
+            ErrorCode::ArgumentCountMismatch => "F0205",
+            ErrorCode::ArgumentTypeMismatch => "F0206",
+            ErrorCode::ReturnTypeMismatch => "F0207",
+            ErrorCode::MissingReturn => "F0208",
+            ErrorCode::NotCallable => "F0209",
+            ErrorCode::BuiltinNotCalled => "F0210",
+            ErrorCode::InvalidOperandTypes => "F0211",
+            ErrorCode::UnknownOperator => "F0212",
+            ErrorCode::NotAssignable => "F0213",
+            ErrorCode::JumpOutsideLoop => "F0214",
+            ErrorCode::UndeclaredLabel => "F0215",
+            ErrorCode::ShadowedLabel => "F0216",
+            ErrorCode::DuplicateField => "F0217",
+            ErrorCode::DuplicateVariant => "F0218",
+            ErrorCode::DuplicateType => "F0219",
+            ErrorCode::RecursiveType => "F0220",
+            ErrorCode::ArrayElementMismatch => "F0221",
+            ErrorCode::NonIntegerIndex => "F0222",
+            ErrorCode::NotIndexable => "F0223",
+            ErrorCode::FunctionExpressionMismatch => "F0224",
+            ErrorCode::NotAStruct => "F0225",
+            ErrorCode::FieldInitializedTwice => "F0226",
+            ErrorCode::UnknownField => "F0227",
+            ErrorCode::FieldTypeMismatch => "F0228",
+            ErrorCode::MissingFields => "F0229",
+            ErrorCode::VariantArityMismatch => "F0230",
+            ErrorCode::VariantPayloadMismatch => "F0231",
+            ErrorCode::FieldAccessOnNonStruct => "F0232",
+            ErrorCode::NonBooleanGuard => "F0233",
+            ErrorCode::NonIntegerRange => "F0234",
+            ErrorCode::NotIterable => "F0235",
+            ErrorCode::RangeOutsideFor => "F0236",
+            ErrorCode::NotAnEnum => "F0237",
+            ErrorCode::UnknownVariant => "F0238",
+            ErrorCode::MatchArmTypeMismatch => "F0239",
+            ErrorCode::NonExhaustiveMatch => "F0240",
+            ErrorCode::PatternTypeMismatch => "F0241",
+            ErrorCode::PatternArityMismatch => "F0242",
+            ErrorCode::BuiltinArgumentMismatch => "F0243",
+
+            ErrorCode::RuntimeTypeError => "F0300",
+            ErrorCode::DivisionByZero => "F0301",
+            ErrorCode::IndexOutOfBounds => "F0302",
+            ErrorCode::NegativeIndex => "F0303",
+            ErrorCode::EmptyArray => "F0304",
+            ErrorCode::NoMatchingArm => "F0305",
+            ErrorCode::JumpOutOfFunction => "F0306",
+            ErrorCode::HostFunctionFailed => "F0307",
+            ErrorCode::IoFailed => "F0308",
+            ErrorCode::IntegerOverflow => "F0309",
+
+            ErrorCode::UnusedBinding => "F0401",
+            ErrorCode::UnreachableCode => "F0402",
+            ErrorCode::ShadowedBinding => "F0403",
+            ErrorCode::ConstantGuard => "F0404",
+        }
+    }
+
+    /// Long-form explanation with a minimal example, printed by `flavor --explain`.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownToken => {
+                "\
+The source contains a character that does not start any Flavor token.
+
+Erroneous code example:
+
+    let price: int = 5$;
+
+Identifiers may only use ASCII letters, digits and `_`. Remove the character or
+move it inside a string literal:
+
+    let price: int = 5;
+    print \"5$\";
+"
+            }
+            ErrorCode::UnterminatedString => {
+                "\
+A string literal was opened but never closed.
+
+Erroneous code example:
+
+    print \"hello;
+
+Single-line strings must be closed on the line they start. Use a multi-line
+string (`\"\"\"...\"\"\"`) to span several lines:
+
+    print \"hello\";
+"
+            }
+            ErrorCode::InvalidEscape => {
+                "\
+A string literal contains an escape sequence Flavor does not know.
+
+Erroneous code example:
+
+    print \"tab\\qhere\";
+
+The supported escapes are `\\n`, `\\t`, `\\\\`, `\\\"` and `\\u{...}` with 1 to 6
+hexadecimal digits naming a valid code point. Use a raw string (`r\"...\"`) to
+keep backslashes as they are:
+
+    print r\"tab\\qhere\";
+"
+            }
+            ErrorCode::UnterminatedComment => {
+                "\
+A block comment was opened with `/*` but never closed.
+
+Erroneous code example:
+
+    /* outer /* inner */
+    print 1;
+
+Block comments nest, so every `/*` needs its own `*/`:
+
+    /* outer /* inner */ */
+    print 1;
+"
+            }
+            ErrorCode::UnexpectedToken => {
+                "\
+The parser expected a specific token, such as a `;` or a closing bracket, and
+found something else.
+
+Erroneous code example:
+
+    let x: int = 1
+    print x;
+
+Add the missing token:
+
+    let x: int = 1;
+    print x;
+"
+            }
+            ErrorCode::LabelWithoutLoop => {
+                "\
+A loop label was not followed by a loop. Labels can only name `while` and
+`for` loops.
+
+Erroneous code example:
+
+    'outer: print 1;
+
+Put the label right before the loop it names:
+
+    'outer: while true { break 'outer; }
+"
+            }
+            ErrorCode::ExpectedExpression => {
+                "\
+The parser expected an expression, such as a literal, a variable or a call,
+and found a token that cannot start one.
+
+Erroneous code example:
+
+    let x: int = ;
+
+Provide a value:
+
+    let x: int = 0;
+"
+            }
+            ErrorCode::ExpectedType => {
+                "\
+The parser expected a type and found a token that cannot start one.
+
+Erroneous code example:
+
+    let x: 5 = 5;
+
+Types are `int`, `float`, `bool`, `string`, `nothing`, arrays like `[int]`,
+function types and the names of aliases, structs and enums:
+
+    let x: int = 5;
+"
+            }
+            ErrorCode::IntegerLiteralTooLarge => {
+                "\
+An integer literal is larger than the largest `int`, 9223372036854775807.
+Integers are 64 bits wide.
+
+Erroneous code example:
+
+    let x = 9223372036854775808;
+
+Use a smaller value, or a float if an approximation is enough. The smallest
+`int` has to be written `-9223372036854775807 - 1`, since `-` applies to a
+literal that must fit on its own:
+
+    let x = 9223372036854775807;
+"
+            }
+            ErrorCode::UndeclaredIdentifier => {
+                "\
+A name was used that is not declared in the current scope or any enclosing
+one.
+
+Erroneous code example:
+
+    print total;
+
+Declare the variable before using it:
+
+    let total: int = 0;
+    print total;
+"
+            }
+            ErrorCode::UnknownType => {
+                "\
+A type annotation names a type that is not declared.
+
+Erroneous code example:
+
+    let p: Point = 1;
+
+Declare the type with `alias`, `struct` or `enum` in the same or an enclosing
+scope:
+
+    struct Point { x: int, y: int }
+    let p: Point = Point { x: 1, y: 2 };
+"
+            }
+            ErrorCode::LetTypeMismatch => {
+                "\
+The value of a `let` declaration does not have the type the declaration is
+annotated with.
+
+Erroneous code example:
+
+    let count: int = \"three\";
+
+Either change the annotation or the value so that they agree, or drop the
+annotation to let Flavor infer it:
+
+    let count: int = 3;
+"
+            }
+            ErrorCode::AssignmentTypeMismatch => {
+                "\
+A value of one type was assigned to a variable, element or field of another
+type. A variable keeps the type it was declared with.
+
+Erroneous code example:
+
+    let count: int = 3;
+    count = \"four\";
+
+Assign a value of the declared type:
+
+    let count: int = 3;
+    count = 4;
+"
+            }
+            ErrorCode::ArgumentCountMismatch => {
+                "\
+A function was called with a different number of arguments than it has
+parameters.
+
+Erroneous code example:
+
+    fn add(a: int, b: int) -> int { return a + b; }
+    print add(1);
+
+Pass one argument per parameter:
+
+    print add(1, 2);
+"
+            }
+            ErrorCode::ArgumentTypeMismatch => {
+                "\
+An argument does not have the type of the parameter it is passed to.
+
+Erroneous code example:
+
+    fn double(x: int) -> int { return x * 2; }
+    print double(\"2\");
+
+Pass a value of the parameter type:
+
+    print double(2);
+"
+            }
+            ErrorCode::ReturnTypeMismatch => {
+                "\
+A `return` statement returns a value whose type differs from the return type
+of the function.
+
+Erroneous code example:
+
+    fn name() -> string { return 1; }
+
+Return a value of the declared type:
+
+    fn name() -> string { return \"one\"; }
+"
+            }
+            ErrorCode::MissingReturn => {
+                "\
+A function with a return type other than `nothing` has a path through its
+body that ends without a `return`.
+
+Erroneous code example:
+
+    fn sign(x: int) -> int {
+        if x > 0 { return 1; }
+    }
+
+Make every path end in a return, for example with an `else` branch or a final
+return statement:
+
+    fn sign(x: int) -> int {
+        if x > 0 { return 1; }
+        return 0;
+    }
+"
+            }
+            ErrorCode::NotCallable => {
+                "\
+A value that is not a function was called.
+
+Erroneous code example:
+
+    let x: int = 1;
+    print x(2);
+
+Only functions, function expressions and builtins can be called.
+"
+            }
+            ErrorCode::BuiltinNotCalled => {
+                "\
+A builtin function was used as a value. Builtins such as `len` and `push` are
+polymorphic and have no single function type, so they can only be called
+directly.
+
+Erroneous code example:
+
+    let f: ([int]) -> int = len;
+
+Wrap the call in a function expression instead:
+
+    let f: ([int]) -> int = <values: [int]> -> int { return len(values); };
+"
+            }
+            ErrorCode::InvalidOperandTypes => {
+                "\
+An operator was applied to operands of types it does not support. Arithmetic
+and comparisons need two numbers of the same type, `&&`, `||` and `!` need
+booleans, and `==`/`!=` need operands of the same type.
+
+Erroneous code example:
+
+    print 1 + true;
+
+Flavor never converts between types implicitly:
+
+    print 1 + 1;
+"
+            }
+            ErrorCode::UnknownOperator => {
+                "\
+The type checker met an operator it does not know. This usually points to a
+bug in the parser rather than in the program.
+"
+            }
+            ErrorCode::NotAssignable => {
+                "\
+Something that is not a place in memory was used where one is required: on
+the left of `=`, as the operand of `++`/`--` or as the first argument of a
+builtin that updates it in place.
+
+Erroneous code example:
+
+    1 = 2;
+    push([1, 2], 3);
+
+Use a variable, an array element or a struct field:
+
+    let values: [int] = [1, 2];
+    push(values, 3);
+"
+            }
+            ErrorCode::JumpOutsideLoop => {
+                "\
+A `break` or `continue` statement was used outside of any loop.
+
+Erroneous code example:
+
+    fn f() -> nothing { break; }
+
+Only use `break` and `continue` inside the body of a `while` or `for` loop.
+Use `return` to leave a function early.
+"
+            }
+            ErrorCode::UndeclaredLabel => {
+                "\
+A `break` or `continue` names a label that no enclosing loop has.
+
+Erroneous code example:
+
+    while true { break 'outer; }
+
+Label the loop you want to jump out of:
+
+    'outer: while true { break 'outer; }
+"
+            }
+            ErrorCode::ShadowedLabel => {
+                "\
+A loop has the same label as a loop enclosing it, which would make jumps to
+that label ambiguous.
+
+Erroneous code example:
+
+    'outer: while true {
+        'outer: while true { break 'outer; }
+    }
+
+Give each nested loop its own label.
+"
+            }
+            ErrorCode::DuplicateField => {
+                "\
+A struct declares the same field twice.
+
+Erroneous code example:
+
+    struct Point { x: int, x: int }
+
+Give every field a different name:
+
+    struct Point { x: int, y: int }
+"
+            }
+            ErrorCode::DuplicateVariant => {
+                "\
+An enum declares the same variant twice.
+
+Erroneous code example:
+
+    enum Light { Red, Red }
+
+Give every variant a different name:
+
+    enum Light { Red, Green }
+"
+            }
+            ErrorCode::DuplicateType => {
+                "\
+Two aliases, structs or enums with the same name were declared in the same
+scope.
+
+Erroneous code example:
+
+    alias Id = int;
+    struct Id { value: int }
+
+Rename one of them. A type in a nested block may reuse the name of a type from
+an enclosing scope.
+"
+            }
+            ErrorCode::RecursiveType => {
+                "\
+An alias refers to itself, directly or through other aliases, so it never
+resolves to a concrete type.
+
+Erroneous code example:
+
+    alias A = [B];
+    alias B = A;
+
+Break the cycle by making one of the aliases a concrete type.
+
+Structs and enums are not aliases and may refer to themselves, as in
+`struct Node { value: int, children: [Node] }`.
+"
+            }
+            ErrorCode::ArrayElementMismatch => {
+                "\
+The elements of an array literal have different types, or do not match the
+element type the context expects. All elements of an array share one type.
+
+Erroneous code example:
+
+    let values: [int] = [1, \"two\"];
+
+Use elements of a single type:
+
+    let values: [int] = [1, 2];
+"
+            }
+            ErrorCode::NonIntegerIndex => {
+                "\
+An array was indexed with a value that is not an `int`.
+
+Erroneous code example:
+
+    let values: [int] = [1, 2];
+    print values[\"0\"];
+
+Index with an integer:
+
+    print values[0];
+"
+            }
+            ErrorCode::NotIndexable => {
+                "\
+A value that is not an array was indexed with `[...]`.
+
+Erroneous code example:
+
+    let x: int = 1;
+    print x[0];
+
+Only arrays can be indexed.
+"
+            }
+            ErrorCode::FunctionExpressionMismatch => {
+                "\
+A function expression passed where a function type is expected does not match
+that type: its parameter count, a parameter type or its return type differs.
+
+Erroneous code example:
+
+    fn apply(f: (int) -> int) -> int { return f(1); }
+    print apply(<x: string> -> int { return 1; });
+
+Make the function expression match the expected type:
+
+    print apply(<x: int> -> int { return x; });
+"
+            }
+            ErrorCode::NotAStruct => {
+                "\
+A struct literal was written for a type that is not a struct.
+
+Erroneous code example:
+
+    alias Id = int;
+    let id: Id = Id { value: 1 };
+
+Only types declared with `struct` can be built with `Name { field: value }`.
+"
+            }
+            ErrorCode::FieldInitializedTwice => {
+                "\
+A struct literal sets the same field twice.
+
+Erroneous code example:
+
+    struct Point { x: int, y: int }
+    let p: Point = Point { x: 1, x: 2, y: 3 };
+
+Set every field exactly once.
+"
+            }
+            ErrorCode::UnknownField => {
+                "\
+A struct literal or a field access names a field the struct does not have.
+
+Erroneous code example:
+
+    struct Point { x: int, y: int }
+    let p: Point = Point { x: 1, y: 2 };
+    print p.z;
+
+Use one of the fields declared by the struct.
+"
+            }
+            ErrorCode::FieldTypeMismatch => {
+                "\
+A struct literal sets a field to a value of the wrong type.
+
+Erroneous code example:
+
+    struct Point { x: int, y: int }
+    let p: Point = Point { x: 1, y: \"2\" };
+
+Use values of the declared field types:
+
+    let p: Point = Point { x: 1, y: 2 };
+"
+            }
+            ErrorCode::MissingFields => {
+                "\
+A struct literal does not set every field of the struct. Flavor has no
+default values, so all fields must be given.
+
+Erroneous code example:
+
+    struct Point { x: int, y: int }
+    let p: Point = Point { x: 1 };
+
+Set the missing fields:
+
+    let p: Point = Point { x: 1, y: 0 };
+"
+            }
+            ErrorCode::VariantArityMismatch => {
+                "\
+An enum variant was built with a different number of values than it carries.
+
+Erroneous code example:
+
+    enum Shape { Rect(float, float) }
+    let s: Shape = Shape::Rect(1.0);
+
+Pass one value per payload type:
+
+    let s: Shape = Shape::Rect(1.0, 2.0);
+"
+            }
+            ErrorCode::VariantPayloadMismatch => {
+                "\
+A value passed to an enum variant does not have the payload type the variant
+declares.
+
+Erroneous code example:
+
+    enum Shape { Circle(float) }
+    let s: Shape = Shape::Circle(1);
+
+Pass a value of the declared type:
+
+    let s: Shape = Shape::Circle(1.0);
+"
+            }
+            ErrorCode::FieldAccessOnNonStruct => {
+                "\
+A field was read with `.` on a value that is not a struct.
+
+Erroneous code example:
+
+    let x: int = 1;
+    print x.value;
+
+Only struct values have fields.
+"
+            }
+            ErrorCode::NonBooleanGuard => {
+                "\
+The condition of an `if` or `while` is not a `bool`. Flavor has no truthy
+values.
+
+Erroneous code example:
+
+    let n: int = 3;
+    if n { print n; }
+
+Compare explicitly:
+
+    if n != 0 { print n; }
+"
+            }
+            ErrorCode::NonIntegerRange => {
+                "\
+A range bound is not an `int`. Ranges count through integers.
+
+Erroneous code example:
+
+    for i in 0..2.5 { print i; }
+
+Use integer bounds:
+
+    for i in 0..3 { print i; }
+"
+            }
+            ErrorCode::NotIterable => {
+                "\
+A `for` loop iterates over a value that is neither an array nor a range.
+
+Erroneous code example:
+
+    for c in \"abc\" { print c; }
+
+Loop over an array or a range instead:
+
+    for i in 0..3 { print i; }
+"
+            }
+            ErrorCode::RangeOutsideFor => {
+                "\
+A range such as `0..10` was found outside of the iterable of a `for` loop.
+Ranges are not values in Flavor and the parser only accepts them there, so
+this error points to a bug in the parser rather than in the program:
+
+    for i in 0..10 { print i; }
+"
+            }
+            ErrorCode::NotAnEnum => {
+                "\
+A `Name::Variant` expression or pattern names a type that is not an enum.
+
+Erroneous code example:
+
+    struct Point { x: int, y: int }
+    let p: Point = Point::Origin;
+
+Only types declared with `enum` have variants.
+"
+            }
+            ErrorCode::UnknownVariant => {
+                "\
+An expression or pattern names a variant the enum does not declare.
+
+Erroneous code example:
+
+    enum Light { Red, Green }
+    let l: Light = Light::Blue;
+
+Use one of the declared variants.
+"
+            }
+            ErrorCode::MatchArmTypeMismatch => {
+                "\
+The arms of a `match` used as a value evaluate to different types.
+
+Erroneous code example:
+
+    enum Light { Red, Green }
+    let l: Light = Light::Red;
+    let x: int = match l { Light::Red => 1, Light::Green => \"two\" };
+
+Make every arm produce a value of the same type.
+"
+            }
+            ErrorCode::NonExhaustiveMatch => {
+                "\
+A `match` does not cover every possible value of its scrutinee.
+
+Erroneous code example:
+
+    enum Light { Red, Green }
+    let l: Light = Light::Red;
+    match l { Light::Red => { print \"stop\"; } }
+
+Add an arm for each missing variant, or a `_` arm to catch the rest:
+
+    match l { Light::Red => { print \"stop\"; } _ => { print \"go\"; } }
+"
+            }
+            ErrorCode::PatternTypeMismatch => {
+                "\
+A `match` pattern names a variant of an enum other than the type of the value
+being matched.
+
+Erroneous code example:
+
+    enum A { One }
+    enum B { Two }
+    let a: A = A::One;
+    match a { B::Two => { print 2; } _ => { print 0; } }
+
+Match on variants of the scrutinee's own enum.
+"
+            }
+            ErrorCode::PatternArityMismatch => {
+                "\
+A variant pattern binds a different number of values than the variant
+carries.
+
+Erroneous code example:
+
+    enum Shape { Rect(float, float) }
+    let s: Shape = Shape::Rect(1.0, 2.0);
+    match s { Shape::Rect(w) => { print w; } }
+
+Bind one name per payload value, using `_` for those you do not need:
+
+    match s { Shape::Rect(w, _) => { print w; } }
+"
+            }
+            ErrorCode::BuiltinArgumentMismatch => {
+                "\
+A builtin function was called with the wrong number or types of arguments.
+
+Erroneous code example:
+
+    let values: [int] = [1, 2];
+    push(values, \"3\");
+
+Check the signature of the builtin: `len(array or string)`, `push(array,
+item)`, `pop(array)`, `insert(array, index, item)` and `remove(array, index)`.
+"
+            }
+            ErrorCode::RuntimeTypeError => {
+                "\
+An operation received a value of the wrong type while the program was running.
+
+Programs that pass type checking should never hit this error. If you see it,
+the type checker missed a case; please report it with the program that caused
+it.
+"
+            }
+            ErrorCode::DivisionByZero => {
+                "\
+An integer was divided by zero, or taken modulo zero.
+
+Erroneous code example:
+
+    let d: int = 0;
+    print 10 / d;
+
+Check the divisor before dividing:
+
+    if d != 0 { print 10 / d; }
+"
+            }
+            ErrorCode::IndexOutOfBounds => {
+                "\
+An array was accessed at an index past its end. Valid indices go from `0` to
+`len(array) - 1`, and `insert` also accepts `len(array)`.
+
+Erroneous code example:
+
+    let values: [int] = [1, 2];
+    print values[2];
+
+Check the index against `len` first:
+
+    if 2 < len(values) { print values[2]; }
+"
+            }
+            ErrorCode::NegativeIndex => {
+                "\
+An array was accessed at a negative index. Flavor does not count from the end
+of arrays.
+
+Erroneous code example:
+
+    let values: [int] = [1, 2];
+    print values[-1];
+
+Use `len(values) - 1` to reach the last element:
+
+    print values[len(values) - 1];
+"
+            }
+            ErrorCode::EmptyArray => {
+                "\
+`pop` was called on an empty array.
+
+Erroneous code example:
+
+    let values: [int] = [];
+    print pop(values);
+
+Check the length first:
+
+    if len(values) > 0 { print pop(values); }
+"
+            }
+            ErrorCode::NoMatchingArm => {
+                "\
+No arm of a `match` matched the value at runtime. Exhaustiveness checking
+should make this impossible; please report it with the program that caused it.
+"
+            }
+            ErrorCode::JumpOutOfFunction => {
+                "\
+A `break` or `continue` escaped the function it was written in. The type
+checker rejects this, so it should never be seen when running a checked
+program.
+"
+            }
+            ErrorCode::HostFunctionFailed => {
+                "\
+A function provided by the program embedding Flavor reported an error, or
+returned a value that does not match the signature it was registered with.
+The message comes from the host; see the documentation of the function for
+what its arguments must be.
+"
+            }
+            ErrorCode::IoFailed => {
+                "\
+Writing the output of `print` or reading input with `read_line` or `eof` failed,
+for example because standard output was closed. The message carries the error
+reported by the operating system or by the writer given to the interpreter.
+"
+            }
+            ErrorCode::IntegerOverflow => {
+                "\
+The result of integer arithmetic does not fit in an `int`, which holds values
+from -9223372036854775808 to 9223372036854775807. This covers `+`, `-`, `*`,
+`/` and `%`, the `++` and `--` operators and negation.
+
+Erroneous code example:
+
+    let big: int = 9223372036854775807;
+    print big + 1;
+
+Use `wrapping_add`, `wrapping_sub` or `wrapping_mul` when the result should
+wrap around, or their `saturating_` versions to stop at the limit:
+
+    print wrapping_add(big, 1);
+    print saturating_add(big, 1);
+"
+            }
+            ErrorCode::UnusedBinding => {
+                "\
+Warning: a variable or parameter is declared but never read.
+
+Example:
+
+    let unused: int = 1;
+
+Remove the binding, or prefix its name with `_` if keeping it is intentional:
+
+    let _unused: int = 1;
+"
+            }
+            ErrorCode::UnreachableCode => {
+                "\
+Warning: statements follow a `return`, `break` or `continue` in the same block,
+so they can never run.
+
+Example:
+
+    fn f() -> int {
+        return 1;
+        print \"never\";
+    }
+
+Remove the statements or move them before the jump.
+"
+            }
+            ErrorCode::ShadowedBinding => {
+                "\
+Warning: a variable or parameter has the same name as a binding of an
+enclosing scope, hiding it for the rest of the block.
+
+Example:
+
+    let x: int = 1;
+    fn f(x: int) -> int { return x; }
+    print f(x);
+
+This is allowed but easy to misread. Pick a different name.
+"
+            }
+            ErrorCode::ConstantGuard => {
+                "\
+Warning: an `if` guard is the literal `true` or `false`, so one branch always
+runs and the other never does.
+
+Example:
+
+    if false { print \"debug\"; }
+
+Remove the `if`, or the dead branch.
+"
+            }
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
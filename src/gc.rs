@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::bytecode::{Closure, Upvalue};
+use crate::interpreter::{EnvFrame, EvaluationType};
+
+/// Number of objects created before the first collection.
+const INITIAL_THRESHOLD: usize = 1024;
+
+/// A value shared through an `Rc` that can lead to scopes or upvalues.
+#[derive(Clone)]
+pub(crate) enum Node {
+    Scope(Rc<RefCell<EnvFrame>>),
+    Upvalue(Rc<RefCell<Upvalue>>),
+    Closure(Rc<Closure>),
+    /// An array, shared by the variables it was copied to until one of them changes it
+    Array(Rc<Vec<EvaluationType>>),
+}
+
+impl Node {
+    fn address(&self) -> *const () {
+        match self {
+            Node::Scope(scope) => Rc::as_ptr(scope).cast(),
+            Node::Upvalue(upvalue) => Rc::as_ptr(upvalue).cast(),
+            Node::Closure(closure) => Rc::as_ptr(closure).cast(),
+            Node::Array(values) => Rc::as_ptr(values).cast(),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Scope(scope) => Rc::strong_count(scope),
+            Node::Upvalue(upvalue) => Rc::strong_count(upvalue),
+            Node::Closure(closure) => Rc::strong_count(closure),
+            Node::Array(values) => Rc::strong_count(values),
+        }
+    }
+
+    fn for_each_reference(&self, f: &mut impl FnMut(Node)) {
+        match self {
+            Node::Scope(scope) => scope.borrow().for_each_reference(f),
+            Node::Upvalue(upvalue) => {
+                if let Upvalue::Closed(value) = &*upvalue.borrow() {
+                    value.for_each_reference(f);
+                }
+            }
+            Node::Closure(closure) => {
+                for upvalue in &closure.upvalues {
+                    f(Node::Upvalue(Rc::clone(upvalue)));
+                }
+            }
+            Node::Array(values) => {
+                for value in values.iter() {
+                    value.for_each_reference(f);
+                }
+            }
+        }
+    }
+
+    /// Drops what the object holds, breaking the cycles going through it.
+    fn clear(&self) {
+        match self {
+            Node::Scope(scope) => scope.borrow_mut().clear(),
+            Node::Upvalue(upvalue) => *upvalue.borrow_mut() = Upvalue::Closed(EvaluationType::Unit),
+            Node::Closure(_) | Node::Array(_) => {}
+        }
+    }
+}
+
+/// An object given to the collector, which does not keep it alive.
+pub(crate) enum Tracked {
+    Scope(Weak<RefCell<EnvFrame>>),
+    Upvalue(Weak<RefCell<Upvalue>>),
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Node> {
+        match self {
+            Tracked::Scope(scope) => scope.upgrade().map(Node::Scope),
+            Tracked::Upvalue(upvalue) => upvalue.upgrade().map(Node::Upvalue),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            Tracked::Scope(scope) => scope.strong_count() > 0,
+            Tracked::Upvalue(upvalue) => upvalue.strong_count() > 0,
+        }
+    }
+}
+
+/// Frees the scopes of an interpreter, and the upvalues of a virtual machine, that only
+/// reference cycles keep alive.
+///
+/// A function value holds the scope it was created in, so a function stored in that scope, or
+/// in one of its children, forms a cycle that reference counting never frees. Every named
+/// function declared inside a block and every closure kept in a local variable does so. On the
+/// virtual machine a closure holds the upvalues it captured instead, and a local function
+/// calling itself captures the upvalue that ends up holding it.
+///
+/// The collector remembers every scope or upvalue it is given. A collection follows the
+/// references between them, including those through the closures and arrays they hold, and
+/// counts how many each object receives. One with more references than that is also held by
+/// something else, such as the interpreter, the stack, a global or a value being evaluated, and
+/// is kept along with everything it reaches. The other scopes and upvalues are garbage and are
+/// cleared, which breaks the cycles so they are freed.
+pub(crate) struct Collector {
+    objects: Vec<Tracked>,
+    /// Number of remembered objects that triggers the next collection
+    threshold: usize,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Collector {
+    pub(crate) fn new() -> Self {
+        Self {
+            objects: vec![],
+            threshold: INITIAL_THRESHOLD,
+        }
+    }
+
+    /// Remembers `object`, collecting first if enough objects were created since the last time.
+    pub(crate) fn register(&mut self, object: Tracked) {
+        if self.objects.len() >= self.threshold {
+            self.collect();
+        }
+        self.objects.push(object);
+    }
+
+    /// Number of objects still allocated, including garbage that was not collected yet.
+    pub(crate) fn live(&self) -> usize {
+        self.objects
+            .iter()
+            .filter(|object| object.is_alive())
+            .count()
+    }
+
+    /// Frees the unreachable objects and returns how many there were.
+    pub(crate) fn collect(&mut self) -> usize {
+        let mut nodes: Vec<Node> = self.objects.iter().filter_map(Tracked::upgrade).collect();
+        let tracked = nodes.len();
+        let mut indices: HashMap<*const (), usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.address(), index))
+            .collect();
+
+        // Closures and arrays are added to the nodes as they are found.
+        let mut edges: Vec<Vec<usize>> = vec![];
+        while edges.len() < nodes.len() {
+            let node = nodes[edges.len()].clone();
+            let mut targets = vec![];
+            node.for_each_reference(&mut |target| {
+                let index = match (indices.get(&target.address()), &target) {
+                    (Some(&index), _) => index,
+                    (None, Node::Closure(_) | Node::Array(_)) => {
+                        indices.insert(target.address(), nodes.len());
+                        nodes.push(target);
+                        nodes.len() - 1
+                    }
+                    // Scopes the collector was not given, such as the global one, stay alive.
+                    (None, Node::Scope(_) | Node::Upvalue(_)) => return,
+                };
+                targets.push(index);
+            });
+            edges.push(targets);
+        }
+
+        let mut internal = vec![0; nodes.len()];
+        for &target in edges.iter().flatten() {
+            internal[target] += 1;
+        }
+
+        // `nodes` holds one reference to each of them itself.
+        let mut reachable = vec![false; nodes.len()];
+        let mut pending: Vec<usize> = (0..nodes.len())
+            .filter(|&index| nodes[index].strong_count() - 1 > internal[index])
+            .collect();
+        while let Some(index) = pending.pop() {
+            if !std::mem::replace(&mut reachable[index], true) {
+                pending.extend(&edges[index]);
+            }
+        }
+
+        let mut freed = 0;
+        for (node, reachable) in nodes.iter().zip(reachable).take(tracked) {
+            if !reachable {
+                node.clear();
+                freed += 1;
+            }
+        }
+        // Releasing the last references frees the garbage.
+        drop(nodes);
+
+        self.objects.retain(Tracked::is_alive);
+        self.threshold = INITIAL_THRESHOLD.max(self.objects.len() * 2);
+        freed
+    }
+}
@@ -1,46 +1,106 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt;
+use std::io::{BufRead, Write};
 use std::rc::Rc;
 
+use crate::builtins::{self, Builtin, BuiltinCall};
+use crate::bytecode::Closure;
+use crate::compiler::Globals;
 use crate::error::{ErrorPhase, FlavorError};
-use crate::types::{ASTNode as AST, Type};
+use crate::error_codes::ErrorCode;
+use crate::gc::{Collector, Node, Tracked};
+use crate::operations::{self, BinaryOperator, PlaceStep};
+use crate::resolver::{self, Resolver};
+use crate::types::{ASTNode as AST, Location, MatchArm, Parameter, Pattern, Span, Type};
 
 #[derive(Debug, Clone)]
-pub(crate) enum EvaluationType {
+pub enum EvaluationType {
     Int(i64),
     Float(f64),
     Bool(bool),
     #[allow(dead_code)]
     String(String),
     Unit,
-    Array(Vec<EvaluationType>),
+    /// Arrays are values. Copies share their elements until one of them is changed, which
+    /// then takes a copy of its own with `Rc::make_mut`.
+    Array(Rc<Vec<EvaluationType>>),
+    Struct {
+        name: String,
+        fields: Vec<(String, EvaluationType)>,
+    },
+    Variant {
+        enum_name: String,
+        variant: String,
+        payload: Vec<EvaluationType>,
+    },
+    /// Function declared in the program. Its parameters and body are shared by every copy
+    /// of the value, so looking it up to call it does not copy the syntax tree.
     Function {
-        parameters: Vec<String>,
-        body: Box<AST>,
+        parameters: Rc<[String]>,
+        body: Rc<AST>,
         env: Rc<RefCell<EnvFrame>>,
     },
+    Builtin(&'static Builtin),
+    Native(Rc<NativeFunction>),
+    /// Function created by the bytecode virtual machine
+    Closure(Rc<Closure>),
 }
 
-type ET = EvaluationType;
-
 impl EvaluationType {
-    fn matches_type(&self, expected: &Type) -> bool {
+    /// Checks the runtime value against a declared type.
+    /// Aliases are looked up starting from `env`.
+    pub(crate) fn matches_type(&self, expected: &Type, env: &Rc<RefCell<EnvFrame>>) -> bool {
         match (self, expected) {
             (EvaluationType::Int(_), Type::Int) => true,
+            (EvaluationType::Float(_), Type::Float) => true,
             (EvaluationType::Bool(_), Type::Bool) => true,
             (EvaluationType::String(_), Type::String) => true,
             (EvaluationType::Unit, Type::Unit) => true,
             (EvaluationType::Array(values), Type::Array(inner)) => {
-                values.iter().all(|value| value.matches_type(inner))
+                values.iter().all(|value| value.matches_type(inner, env))
+            }
+            (EvaluationType::Struct { name, .. }, Type::Struct { name: expected, .. }) => {
+                name == expected
+            }
+            (EvaluationType::Variant { enum_name, .. }, Type::Enum { name, .. }) => {
+                enum_name == name
+            }
+            (
+                EvaluationType::Function { .. }
+                | EvaluationType::Native(_)
+                | EvaluationType::Closure(_),
+                Type::Function { .. },
+            ) => true,
+            (value, Type::Custom(name)) => {
+                EnvFrame::get_type(env, name).is_some_and(|named| value.matches_type(&named, env))
             }
-            (EvaluationType::Function { .. }, Type::Function { .. }) => true,
-            (_, Type::Custom(_)) => true,
             _ => false,
         }
     }
 
-    fn type_name(&self) -> &'static str {
+    /// Calls `f` with the scopes of the interpreted functions, the compiled closures and the
+    /// arrays held by the value, without looking inside of them.
+    pub(crate) fn for_each_reference(&self, f: &mut impl FnMut(Node)) {
+        match self {
+            EvaluationType::Function { env, .. } => f(Node::Scope(Rc::clone(env))),
+            EvaluationType::Closure(closure) => f(Node::Closure(Rc::clone(closure))),
+            EvaluationType::Array(values) => f(Node::Array(Rc::clone(values))),
+            EvaluationType::Variant { payload, .. } => {
+                for value in payload {
+                    value.for_each_reference(f);
+                }
+            }
+            EvaluationType::Struct { fields, .. } => {
+                for (_, value) in fields {
+                    value.for_each_reference(f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
         match self {
             EvaluationType::Int(_) => "int",
             EvaluationType::Float(_) => "float",
@@ -48,7 +108,12 @@ impl EvaluationType {
             EvaluationType::String(_) => "string",
             EvaluationType::Unit => "unit",
             EvaluationType::Array(_) => "array",
-            EvaluationType::Function { .. } => "function",
+            EvaluationType::Struct { .. } => "struct",
+            EvaluationType::Variant { .. } => "enum",
+            EvaluationType::Function { .. }
+            | EvaluationType::Builtin(_)
+            | EvaluationType::Native(_)
+            | EvaluationType::Closure(_) => "function",
         }
     }
 }
@@ -59,13 +124,7 @@ impl fmt::Display for EvaluationType {
             EvaluationType::Int(value) => write!(f, "{value}"),
             EvaluationType::Float(value) => write!(f, "{value}"),
             EvaluationType::Bool(value) => write!(f, "{value}"),
-            EvaluationType::String(value) => {
-                if let Some(stripped) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
-                    write!(f, "{stripped}")
-                } else {
-                    write!(f, "{value}")
-                }
-            }
+            EvaluationType::String(value) => write!(f, "{value}"),
             EvaluationType::Unit => write!(f, "<unit>"),
             EvaluationType::Array(values) => {
                 let formatted = values
@@ -75,79 +134,352 @@ impl fmt::Display for EvaluationType {
                     .join(", ");
                 write!(f, "[{formatted}]")
             }
-            EvaluationType::Function { .. } => write!(f, "<function>"),
+            EvaluationType::Struct { name, fields } => {
+                let formatted = fields
+                    .iter()
+                    .map(|(field, value)| format!("{field}: {value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{name} {{ {formatted} }}")
+            }
+            EvaluationType::Variant {
+                enum_name,
+                variant,
+                payload,
+            } => {
+                write!(f, "{enum_name}::{variant}")?;
+                if !payload.is_empty() {
+                    let formatted = payload
+                        .iter()
+                        .map(|value| value.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    write!(f, "({formatted})")?;
+                }
+                Ok(())
+            }
+            EvaluationType::Function { .. } | EvaluationType::Closure(_) => write!(f, "<function>"),
+            EvaluationType::Builtin(builtin) => write!(f, "<builtin {}>", builtin.name),
+            EvaluationType::Native(native) => write!(f, "<native {}>", native.name),
         }
     }
 }
 
+/// Rust implementation of a host function: receives the evaluated arguments and returns
+/// the result, or a message describing why it failed.
+pub type NativeCall = dyn Fn(&[EvaluationType]) -> Result<EvaluationType, String>;
+
+/// A function provided by the program embedding Flavor, see `Engine::register_function`.
+pub struct NativeFunction {
+    pub(crate) name: String,
+    pub(crate) param_types: Vec<Type>,
+    pub(crate) return_type: Type,
+    pub(crate) call: Box<NativeCall>,
+}
+
+impl NativeFunction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl NativeFunction {
+    /// Calls the function with arguments already checked against its parameters. Failures and
+    /// results that do not match the declared return type are reported at `span`, the call.
+    /// Type names in the return type are looked up starting from `env`.
+    pub(crate) fn invoke(
+        &self,
+        arguments: &[EvaluationType],
+        span: Span,
+        env: &Rc<RefCell<EnvFrame>>,
+    ) -> Result<EvaluationType, FlavorError> {
+        let failed = |message: String| {
+            FlavorError::with_span(ErrorPhase::Runtime, message, span)
+                .with_code(ErrorCode::HostFunctionFailed)
+        };
+        let value = (self.call)(arguments)
+            .map_err(|message| failed(format!("'{}' failed: {message}", self.name)))?;
+        if !value.matches_type(&self.return_type, env) {
+            return Err(failed(format!(
+                "'{}' returned a {} but is declared to return {}",
+                self.name,
+                value.type_name(),
+                self.return_type
+            )));
+        }
+        Ok(value)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("param_types", &self.param_types)
+            .field("return_type", &self.return_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The aliases, structs and enums declared directly in `nodes`, with the types they name.
+pub(crate) fn type_declarations(nodes: &[AST]) -> impl Iterator<Item = (String, Type)> + '_ {
+    nodes.iter().filter_map(|node| match node {
+        AST::AliasDeclaration {
+            name, aliased_type, ..
+        } => Some((name.clone(), aliased_type.clone())),
+        AST::StructDeclaration { name, fields, .. } => Some((
+            name.clone(),
+            Type::Struct {
+                name: name.clone(),
+                fields: fields.clone(),
+            },
+        )),
+        AST::EnumDeclaration { name, variants, .. } => Some((
+            name.clone(),
+            Type::Enum {
+                name: name.clone(),
+                variants: variants.clone(),
+            },
+        )),
+        _ => None,
+    })
+}
+
+/// A scope of the interpreter, holding its variables in the slots the resolver gave them.
 #[derive(Debug)]
-pub(crate) struct EnvFrame {
-    values: HashMap<String, EvaluationType>,
+pub struct EnvFrame {
+    values: Vec<EvaluationType>,
+    types: HashMap<String, Type>,
     parent: Option<Rc<RefCell<EnvFrame>>>,
+    /// Whether the scope was given to the collector
+    tracked: bool,
 }
 
 impl EnvFrame {
-    fn new(parent: Option<Rc<RefCell<EnvFrame>>>) -> Self {
+    pub(crate) fn new(parent: Option<Rc<RefCell<EnvFrame>>>) -> Self {
+        Self::with_values(parent, vec![])
+    }
+
+    fn with_values(parent: Option<Rc<RefCell<EnvFrame>>>, values: Vec<EvaluationType>) -> Self {
         Self {
-            values: HashMap::new(),
+            values,
+            types: HashMap::new(),
             parent,
+            tracked: false,
         }
     }
 
-    fn get(env: &Rc<RefCell<EnvFrame>>, name: &str) -> Option<EvaluationType> {
-        let (maybe_value, parent) = {
+    /// Registers the aliases, structs and enums declared directly in `nodes` in this scope,
+    /// mirroring the hoisting done by the type checker.
+    pub(crate) fn declare_types(&mut self, nodes: &[AST]) {
+        self.types.extend(type_declarations(nodes));
+    }
+
+    pub(crate) fn get_type(env: &Rc<RefCell<EnvFrame>>, name: &str) -> Option<Type> {
+        let (maybe_type, parent) = {
             let borrowed = env.borrow();
-            let value = borrowed.values.get(name).cloned();
+            let ty = borrowed.types.get(name).cloned();
             let parent = borrowed.parent.clone();
-            (value, parent)
+            (ty, parent)
         };
 
-        if let Some(value) = maybe_value {
-            Some(value)
+        if let Some(ty) = maybe_type {
+            Some(ty)
         } else {
-            parent.and_then(|p| EnvFrame::get(&p, name))
+            parent.and_then(|p| EnvFrame::get_type(&p, name))
         }
     }
 
-    fn find_env(env: &Rc<RefCell<EnvFrame>>, name: &str) -> Option<Rc<RefCell<EnvFrame>>> {
-        let (contains, parent) = {
-            let borrowed = env.borrow();
-            let contains = borrowed.values.contains_key(name);
-            let parent = borrowed.parent.clone();
-            (contains, parent)
-        };
+    /// Calls `f` with its parent and with what its variables reference.
+    pub(crate) fn for_each_reference(&self, f: &mut impl FnMut(Node)) {
+        if let Some(parent) = &self.parent {
+            f(Node::Scope(Rc::clone(parent)));
+        }
+        for value in &self.values {
+            value.for_each_reference(f);
+        }
+    }
 
-        if contains {
-            Some(Rc::clone(env))
-        } else {
-            parent.and_then(|p| EnvFrame::find_env(&p, name))
+    /// Drops the variables and the parent of a scope found to be garbage.
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+        self.parent = None;
+    }
+
+    fn define(&mut self, slot: usize, value: EvaluationType) {
+        if slot >= self.values.len() {
+            self.values.resize(slot + 1, EvaluationType::Unit);
+        }
+        self.values[slot] = value;
+    }
+
+    /// Hands `slot` of the scope `depth` scopes above `env` to `f`.
+    fn with_slot<R>(
+        env: &Rc<RefCell<EnvFrame>>,
+        depth: usize,
+        slot: usize,
+        f: impl FnOnce(&mut EvaluationType) -> R,
+    ) -> R {
+        if depth == 0 {
+            return f(&mut env.borrow_mut().values[slot]);
         }
+        let borrowed = env.borrow();
+        let parent = borrowed
+            .parent
+            .as_ref()
+            .expect("the resolver only counts existing scopes");
+        EnvFrame::with_slot(parent, depth - 1, slot, f)
     }
 }
 
+/// The location the resolver gave to an identifier or a declaration.
+fn resolved(location: &Cell<Option<Location>>) -> Location {
+    location
+        .get()
+        .expect("variables are resolved before the program runs")
+}
+
+fn undefined_variable(name: &str, span: Span) -> FlavorError {
+    FlavorError::with_span(
+        ErrorPhase::Runtime,
+        format!("Undefined variable: {name}"),
+        span,
+    )
+    .with_code(ErrorCode::RuntimeTypeError)
+}
+
+/// Target of an assignment or increment: a variable followed by the array indices and struct
+/// fields leading to the value to overwrite.
+struct Place<'a> {
+    base_name: &'a str,
+    base_span: Span,
+    base_location: Location,
+    steps: Vec<PlaceStep<'a>>,
+}
+
 #[derive(Debug, Clone)]
-pub(crate) enum EvalOutcome {
+pub enum EvalOutcome {
     Value(EvaluationType),
-    Break,
+    /// `break`, with the label of the loop it targets if one was given
+    Break(Option<String>),
+    /// `continue`, with the label of the loop it targets if one was given
+    Continue(Option<String>),
     Return(EvaluationType),
 }
 
+/// Tells whether a `break`/`continue` aimed at `target` stops at the loop labeled `label`.
+/// Unlabeled jumps always stop at the innermost loop.
+fn targets_loop(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
+}
+
+/// Runs checked programs by walking their syntax tree.
+///
+/// Before a program runs, a `Resolver` gives each of its variables a slot in the scope that
+/// declares it, so lookups index into vectors instead of searching scopes by name. Globals
+/// keep their slots from one program to the next.
 pub struct Interpreter {
+    /// Innermost scope, whose outermost ancestor holds the types declared at the top level
     current_env: Rc<RefCell<EnvFrame>>,
+    globals: Vec<Option<EvaluationType>>,
+    global_names: Globals,
+    collector: Collector,
+    /// Where `print` writes, standard output unless replaced with `with_output`
+    pub(crate) output: Box<dyn Write>,
+    /// Where `read_line` reads from, set with `with_input`. Standard input is locked for each
+    /// read only, so the REPL can keep reading its own lines from it.
+    pub(crate) input: Option<Box<dyn BufRead>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Interpreter {
             current_env: Rc::new(RefCell::new(EnvFrame::new(None))),
+            globals: vec![],
+            global_names: Globals::default(),
+            collector: Collector::new(),
+            output: Box::new(std::io::stdout()),
+            input: None,
+        }
+    }
+
+    /// Sends the output of `print` to `output` instead of standard output.
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Makes `read_line` read from `input` instead of standard input.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Number of scopes captured by a function that are still allocated, counting those the
+    /// next collection will free.
+    pub fn live_scopes(&self) -> usize {
+        self.collector.live()
+    }
+
+    /// Frees the scopes that only reference cycles between closures and the scopes they
+    /// capture keep alive, and returns how many there were. Collections also happen on their
+    /// own as the program creates scopes.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.collector.collect()
+    }
+
+    /// Creates a scope inside `parent` whose first slots hold `values`.
+    fn new_scope(
+        &self,
+        parent: Rc<RefCell<EnvFrame>>,
+        values: Vec<EvaluationType>,
+    ) -> Rc<RefCell<EnvFrame>> {
+        Rc::new(RefCell::new(EnvFrame::with_values(Some(parent), values)))
+    }
+
+    /// Gives the current scope and the scopes around it to the collector, before a function
+    /// captures them. A cycle always goes through the scope a function captured and the
+    /// scopes around it, so the scopes of calls and blocks that create no functions are
+    /// simply dropped when they end. The global scope lives as long as the interpreter.
+    fn track_captured_scopes(&mut self) {
+        let mut scope = Rc::clone(&self.current_env);
+        loop {
+            let parent = {
+                let mut borrowed = scope.borrow_mut();
+                if borrowed.tracked {
+                    // Its enclosing scopes were tracked along with it
+                    return;
+                }
+                let Some(parent) = borrowed.parent.clone() else {
+                    return;
+                };
+                borrowed.tracked = true;
+                parent
+            };
+            self.collector
+                .register(Tracked::Scope(Rc::downgrade(&scope)));
+            scope = parent;
+        }
+    }
+
+    /// Creates the value of a function declared in the current scope.
+    fn function(&mut self, parameters: &[Parameter], body: &Rc<AST>) -> EvaluationType {
+        self.track_captured_scopes();
+        EvaluationType::Function {
+            parameters: parameters.iter().map(|param| param.name.clone()).collect(),
+            body: Rc::clone(body),
+            env: Rc::clone(&self.current_env),
         }
     }
 
     fn push_scope(&mut self) {
-        let child = Rc::new(RefCell::new(EnvFrame::new(Some(Rc::clone(
-            &self.current_env,
-        )))));
-        self.current_env = child;
+        self.current_env = self.new_scope(Rc::clone(&self.current_env), vec![]);
     }
 
     fn pop_scope(&mut self) {
@@ -164,28 +496,287 @@ impl Interpreter {
         }
     }
 
-    fn define(&mut self, name: String, value: EvaluationType) {
-        self.current_env.borrow_mut().values.insert(name, value);
+    /// Defines a variable visible to the whole program, such as the script arguments.
+    pub(crate) fn define_global(&mut self, name: &str, value: EvaluationType) {
+        let slot = self.global_names.declare(name) as usize;
+        self.define(Location::Global(slot), value);
+    }
+
+    fn define(&mut self, location: Location, value: EvaluationType) {
+        match location {
+            Location::Local { slot, .. } => self.current_env.borrow_mut().define(slot, value),
+            Location::Global(slot) => {
+                if slot >= self.globals.len() {
+                    self.globals.resize(slot + 1, None);
+                }
+                self.globals[slot] = Some(value);
+            }
+        }
     }
 
-    fn lookup(&self, name: &str) -> Option<EvaluationType> {
-        EnvFrame::get(&self.current_env, name)
+    /// Hands the variable at `location` to `f`, or fails if it is an undefined global.
+    fn with_variable<R>(
+        &mut self,
+        location: Location,
+        name: &str,
+        span: Span,
+        f: impl FnOnce(&mut EvaluationType) -> R,
+    ) -> Result<R, FlavorError> {
+        match location {
+            Location::Local { depth, slot } => {
+                Ok(EnvFrame::with_slot(&self.current_env, depth, slot, f))
+            }
+            Location::Global(slot) => self
+                .globals
+                .get_mut(slot)
+                .and_then(Option::as_mut)
+                .map(f)
+                .ok_or_else(|| undefined_variable(name, span)),
+        }
     }
 
-    fn find_env_for(&self, name: &str) -> Option<Rc<RefCell<EnvFrame>>> {
-        EnvFrame::find_env(&self.current_env, name)
+    fn lookup(
+        &mut self,
+        location: Location,
+        name: &str,
+        span: Span,
+    ) -> Result<EvaluationType, FlavorError> {
+        self.with_variable(location, name, span, |value| value.clone())
+            .or_else(|err| {
+                builtins::lookup(name)
+                    .map(EvaluationType::Builtin)
+                    .ok_or(err)
+            })
     }
 
-    fn assign_existing(&mut self, name: &str, value: EvaluationType) -> bool {
-        if let Some(env) = self.find_env_for(name) {
-            env.borrow_mut().values.insert(name.to_string(), value);
-            true
+    fn declare_types(&mut self, nodes: &[AST]) {
+        self.current_env.borrow_mut().declare_types(nodes);
+    }
+
+    /// Tests `value` against `pattern`, returning the values of the names it binds when it
+    /// matches, in the order of `resolver::pattern_bindings`.
+    fn match_pattern(pattern: &Pattern, value: &EvaluationType) -> Option<Vec<EvaluationType>> {
+        match pattern {
+            Pattern::Wildcard { .. } => Some(vec![]),
+            Pattern::Binding { .. } => Some(vec![value.clone()]),
+            Pattern::Variant {
+                variant, bindings, ..
+            } => match value {
+                EvaluationType::Variant {
+                    variant: actual,
+                    payload,
+                    ..
+                } if actual == variant => Some(
+                    bindings
+                        .iter()
+                        .zip(payload.iter())
+                        .filter(|(name, _)| *name != "_")
+                        .map(|(_, value)| value.clone())
+                        .collect(),
+                ),
+                _ => None,
+            },
+        }
+    }
+
+    /// Runs the body of the selected arm in a scope holding the pattern bindings, if it binds
+    /// any. Block arms evaluate to unit, like the type checker expects.
+    fn eval_match_arm(
+        &mut self,
+        arm: &MatchArm,
+        bindings: Vec<EvaluationType>,
+    ) -> Result<EvalOutcome, FlavorError> {
+        let result = if bindings.is_empty() {
+            self.eval(&arm.body)
         } else {
-            false
+            self.eval_in_scope(bindings, &arm.body)
+        };
+
+        match result? {
+            EvalOutcome::Value(_) if matches!(arm.body, AST::Body { .. }) => {
+                Ok(EvalOutcome::Value(EvaluationType::Unit))
+            }
+            outcome => Ok(outcome),
+        }
+    }
+
+    /// Evaluates `node` in a new scope whose first slots hold `values`.
+    fn eval_in_scope(
+        &mut self,
+        values: Vec<EvaluationType>,
+        node: &AST,
+    ) -> Result<EvalOutcome, FlavorError> {
+        let scope = self.new_scope(Rc::clone(&self.current_env), values);
+        let previous_env = std::mem::replace(&mut self.current_env, scope);
+        let result = self.eval(node);
+        self.current_env = previous_env;
+        result
+    }
+
+    /// Evaluates the indices of an assignable expression, producing the `Place` it refers to.
+    /// The inner `Err` carries the control flow raised while evaluating an index, if any.
+    fn eval_place<'a>(
+        &mut self,
+        node: &'a AST,
+    ) -> Result<Result<Place<'a>, EvalOutcome>, FlavorError> {
+        let mut accessors = Vec::new();
+        let mut current = node;
+        let (base_name, base_span, base_location) = loop {
+            match current {
+                AST::Identifier {
+                    name,
+                    span,
+                    location,
+                } => break (name.as_str(), *span, resolved(location)),
+                AST::ArrayAccess { array, .. } => {
+                    accessors.push(current);
+                    current = array.as_ref();
+                }
+                AST::FieldAccess { object, .. } => {
+                    accessors.push(current);
+                    current = object.as_ref();
+                }
+                _ => {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::Runtime,
+                        "Assignment target must be an identifier, array access or field access",
+                        *current.span(),
+                    )
+                    .with_code(ErrorCode::RuntimeTypeError));
+                }
+            }
+        };
+
+        let mut steps = Vec::with_capacity(accessors.len());
+        for accessor in accessors.into_iter().rev() {
+            match accessor {
+                AST::ArrayAccess { array, index, .. } => {
+                    let index_value = match self.eval(index)? {
+                        EvalOutcome::Value(value) => value,
+                        control_flow => return Ok(Err(control_flow)),
+                    };
+                    steps.push(PlaceStep::Index {
+                        index: operations::place_index(&index_value, *index.span())?,
+                        index_span: *index.span(),
+                        array_span: *array.span(),
+                    });
+                }
+                AST::FieldAccess { field, span, .. } => steps.push(PlaceStep::Field {
+                    name: field,
+                    span: *span,
+                }),
+                _ => unreachable!("only accessors are collected"),
+            }
+        }
+
+        Ok(Ok(Place {
+            base_name,
+            base_span,
+            base_location,
+            steps,
+        }))
+    }
+
+    /// Runs a native builtin. Mutating builtins receive their first argument
+    /// as a `Place`, so the update is visible through the caller's variable.
+    fn call_builtin(
+        &mut self,
+        builtin: &'static Builtin,
+        arguments: &[AST],
+        span: Span,
+    ) -> Result<EvalOutcome, FlavorError> {
+        let (place, rest) = match builtin.call {
+            BuiltinCall::Mutating(_) => {
+                let Some((target, rest)) = arguments.split_first() else {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::Runtime,
+                        format!("'{}' expects at least one argument", builtin.name),
+                        span,
+                    )
+                    .with_code(ErrorCode::RuntimeTypeError));
+                };
+                match self.eval_place(target)? {
+                    Ok(place) => (Some(place), rest),
+                    Err(control_flow) => return Ok(control_flow),
+                }
+            }
+            BuiltinCall::Pure(_) | BuiltinCall::Input(_) => (None, arguments),
+        };
+
+        let mut values = Vec::with_capacity(rest.len());
+        for arg in rest {
+            match self.eval(arg)? {
+                EvalOutcome::Value(value) => values.push(value),
+                control_flow => return Ok(control_flow),
+            }
         }
+
+        let to_error = |(code, message)| {
+            FlavorError::with_span(ErrorPhase::Runtime, message, span).with_code(code)
+        };
+        let result = match (builtin.call, place) {
+            (BuiltinCall::Pure(call), _) => call(&values).map_err(to_error)?,
+            (BuiltinCall::Input(call), _) => match &mut self.input {
+                Some(input) => call(input.as_mut(), &values),
+                None => call(&mut std::io::stdin().lock(), &values),
+            }
+            .map_err(to_error)?,
+            (BuiltinCall::Mutating(call), Some(place)) => {
+                self.with_place_mut(place, |target| call(target, &values).map_err(to_error))?
+            }
+            (BuiltinCall::Mutating(_), None) => unreachable!("mutating builtins evaluate a place"),
+        };
+        Ok(EvalOutcome::Value(result))
+    }
+
+    /// Runs a function provided by the host. Its failures, and results that do not match its
+    /// declared return type, are reported at the call site.
+    fn call_native(
+        &mut self,
+        native: &NativeFunction,
+        arguments: &[AST],
+        span: Span,
+    ) -> Result<EvalOutcome, FlavorError> {
+        operations::check_arity(native.param_types.len(), arguments.len(), span)?;
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for arg in arguments {
+            match self.eval(arg)? {
+                EvalOutcome::Value(value) => values.push(value),
+                control_flow => return Ok(control_flow),
+            }
+        }
+        native
+            .invoke(&values, span, &self.current_env)
+            .map(EvalOutcome::Value)
+    }
+
+    /// Walks `place` inside the environment and hands the targeted value to `f`.
+    fn with_place_mut<R>(
+        &mut self,
+        place: Place,
+        f: impl FnOnce(&mut EvaluationType) -> Result<R, FlavorError>,
+    ) -> Result<R, FlavorError> {
+        let Place {
+            base_name,
+            base_span,
+            base_location,
+            steps,
+        } = place;
+
+        self.with_variable(base_location, base_name, base_span, |root| {
+            f(operations::place_mut(root, base_name, base_span, steps)?)
+        })?
     }
 
     pub fn eval_program(&mut self, nodes: &[AST]) -> Result<EvalOutcome, FlavorError> {
+        Resolver::new(&mut self.global_names).resolve_program(nodes);
+        self.declare_types(nodes);
+        self.eval_block(nodes)
+    }
+
+    /// Runs `nodes` in order, evaluating to the value of the last one.
+    fn eval_block(&mut self, nodes: &[AST]) -> Result<EvalOutcome, FlavorError> {
         let mut last_value = EvaluationType::Unit;
         for node in nodes {
             match self.eval(node)? {
@@ -198,39 +789,28 @@ impl Interpreter {
 
     fn eval(&mut self, node: &AST) -> Result<EvalOutcome, FlavorError> {
         match node {
-            AST::Print { expressions, .. } => {
-                let mut outputs = Vec::new();
+            AST::Print { expressions, span } => {
+                let mut values = Vec::with_capacity(expressions.len());
                 for expr in expressions.iter() {
                     match self.eval(expr)? {
-                        EvalOutcome::Value(val) => outputs.push(val.to_string()),
+                        EvalOutcome::Value(val) => values.push(val),
                         control_flow => {
                             return Ok(control_flow);
                         }
                     }
                 }
-                println!("{}", outputs.join(""));
+                operations::print(self.output.as_mut(), &values, *span)?;
                 Ok(EvalOutcome::Value(EvaluationType::Unit))
             }
             AST::Body { nodes, .. } => {
-                self.push_scope();
-                let mut result = EvaluationType::Unit;
-                for n in nodes {
-                    match self.eval(n) {
-                        Ok(EvalOutcome::Value(val)) => {
-                            result = val;
-                        }
-                        Ok(control_flow) => {
-                            self.pop_scope();
-                            return Ok(control_flow);
-                        }
-                        Err(err) => {
-                            self.pop_scope();
-                            return Err(err);
-                        }
-                    }
+                if !resolver::declares_names(nodes) {
+                    return self.eval_block(nodes);
                 }
+                self.push_scope();
+                self.declare_types(nodes);
+                let result = self.eval_block(nodes);
                 self.pop_scope();
-                Ok(EvalOutcome::Value(result))
+                result
             }
             AST::If {
                 guard,
@@ -251,7 +831,9 @@ impl Interpreter {
                     Ok(EvalOutcome::Value(EvaluationType::Unit))
                 }
             }
-            AST::While { guard, body, .. } => {
+            AST::While {
+                label, guard, body, ..
+            } => {
                 let mut result = EvaluationType::Unit;
                 loop {
                     let guard_value = match self.eval(guard)? {
@@ -263,10 +845,13 @@ impl Interpreter {
                             EvalOutcome::Value(value) => {
                                 result = value;
                             }
-                            EvalOutcome::Break => {
+                            EvalOutcome::Break(target) if targets_loop(&target, label) => {
                                 break;
                             }
-                            EvalOutcome::Return(val) => return Ok(EvalOutcome::Return(val)),
+                            EvalOutcome::Continue(target) if targets_loop(&target, label) => {
+                                continue;
+                            }
+                            control_flow => return Ok(control_flow),
                         },
                         EvaluationType::Bool(false) => break,
                         _ => {
@@ -274,60 +859,130 @@ impl Interpreter {
                                 ErrorPhase::Runtime,
                                 "While guard must be evaluated to boolean",
                                 *guard.span(),
-                            ));
+                            )
+                            .with_code(ErrorCode::RuntimeTypeError));
                         }
                     }
                 }
                 Ok(EvalOutcome::Value(result))
             }
+            AST::For {
+                label,
+                iterable,
+                body,
+                ..
+            } => {
+                let values: Box<dyn Iterator<Item = EvaluationType>> = match iterable.as_ref() {
+                    AST::Range {
+                        start,
+                        end,
+                        inclusive,
+                        ..
+                    } => {
+                        let mut bounds = [0i64; 2];
+                        for (bound, node) in bounds.iter_mut().zip([start, end]) {
+                            *bound = match self.eval(node)? {
+                                EvalOutcome::Value(EvaluationType::Int(value)) => value,
+                                EvalOutcome::Value(_) => {
+                                    return Err(FlavorError::with_span(
+                                        ErrorPhase::Runtime,
+                                        "Range bounds must be integers",
+                                        *node.span(),
+                                    )
+                                    .with_code(ErrorCode::RuntimeTypeError));
+                                }
+                                control_flow => return Ok(control_flow),
+                            };
+                        }
+                        let [low, high] = bounds;
+                        if *inclusive {
+                            Box::new((low..=high).map(EvaluationType::Int))
+                        } else {
+                            Box::new((low..high).map(EvaluationType::Int))
+                        }
+                    }
+                    _ => match self.eval(iterable)? {
+                        EvalOutcome::Value(EvaluationType::Array(values)) => {
+                            Box::new((0..values.len()).map(move |index| values[index].clone()))
+                        }
+                        EvalOutcome::Value(other) => {
+                            return Err(FlavorError::with_span(
+                                ErrorPhase::Runtime,
+                                format!("Cannot iterate over {}", other.type_name()),
+                                *iterable.span(),
+                            )
+                            .with_code(ErrorCode::RuntimeTypeError));
+                        }
+                        control_flow => return Ok(control_flow),
+                    },
+                };
+
+                for value in values {
+                    // Every iteration gets its own scope so closures capture the current element.
+                    match self.eval_in_scope(vec![value], body)? {
+                        EvalOutcome::Value(_) => {}
+                        EvalOutcome::Break(target) if targets_loop(&target, label) => break,
+                        EvalOutcome::Continue(target) if targets_loop(&target, label) => continue,
+                        control_flow => return Ok(control_flow),
+                    }
+                }
+                Ok(EvalOutcome::Value(EvaluationType::Unit))
+            }
+            AST::Range { span, .. } => Err(FlavorError::with_span(
+                ErrorPhase::Runtime,
+                "Ranges can only be used as the iterable of a for loop",
+                *span,
+            )
+            .with_code(ErrorCode::RangeOutsideFor)),
             AST::LetDeclaration {
                 identifier,
                 var_type,
                 expr,
                 span,
+                location,
             } => {
                 let value = match self.eval(expr)? {
                     EvalOutcome::Value(value) => value,
                     control_flow => return Ok(control_flow),
                 };
-                if let Some(var_type) = var_type {
-                    if !value.matches_type(var_type) {
-                        return Err(FlavorError::with_span(
-                            ErrorPhase::Runtime,
-                            format!(
-                                "Type mismatch: variable '{}' declared as {:?} but value has runtime type {}",
-                                identifier,
-                                var_type,
-                                value.type_name()
-                            ),
-                            *span,
-                        ));
-                    }
+                if let Some(var_type) = var_type
+                    && !value.matches_type(var_type, &self.current_env)
+                {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::Runtime,
+                        format!(
+                            "Type mismatch: variable '{}' declared as {:?} but value has runtime type {}",
+                            identifier,
+                            var_type,
+                            value.type_name()
+                        ),
+                        *span,
+                    )
+                    .with_code(ErrorCode::RuntimeTypeError));
                 }
 
-                self.define(identifier.clone(), value);
+                self.define(resolved(location), value);
                 Ok(EvalOutcome::Value(EvaluationType::Unit))
             }
             AST::FunctionDeclaration {
-                name,
                 parameters,
-                return_type: _,
                 body,
+                location,
                 ..
             } => {
-                let func = EvaluationType::Function {
-                    parameters: parameters.iter().map(|(param, _)| param.clone()).collect(),
-                    body: body.clone(),
-                    env: Rc::clone(&self.current_env),
-                };
-                self.define(name.clone(), func.clone());
+                let func = self.function(parameters, body);
+                self.define(resolved(location), func);
                 Ok(EvalOutcome::Value(EvaluationType::Unit))
             }
+            AST::AliasDeclaration { .. }
+            | AST::StructDeclaration { .. }
+            | AST::EnumDeclaration { .. } => Ok(EvalOutcome::Value(EvaluationType::Unit)),
             AST::Return { expr, .. } => match self.eval(expr)? {
                 EvalOutcome::Value(value) => Ok(EvalOutcome::Return(value)),
                 contorl_flow => Ok(contorl_flow),
             },
-            AST::Break { .. } => Ok(EvalOutcome::Break),
+            AST::Break { label, .. } => Ok(EvalOutcome::Break(label.clone())),
+            AST::Continue { label, .. } => Ok(EvalOutcome::Continue(label.clone())),
             AST::FunctionCall {
                 callee,
                 arguments,
@@ -339,39 +994,33 @@ impl Interpreter {
                         body,
                         env,
                     }) => (parameters, body, env),
+                    EvalOutcome::Value(EvaluationType::Builtin(builtin)) => {
+                        return self.call_builtin(builtin, arguments, *span);
+                    }
+                    EvalOutcome::Value(EvaluationType::Native(native)) => {
+                        return self.call_native(&native, arguments, *span);
+                    }
                     EvalOutcome::Value(_) => {
                         return Err(FlavorError::with_span(
                             ErrorPhase::Runtime,
                             "Callee is not a function",
                             *callee.span(),
-                        ));
+                        )
+                        .with_code(ErrorCode::RuntimeTypeError));
                     }
                     control_flow => return Ok(control_flow),
                 };
 
-                if parameters.len() != arguments.len() {
-                    return Err(FlavorError::with_span(
-                        ErrorPhase::Runtime,
-                        format!(
-                            "Expected {} arguments but got {}",
-                            parameters.len(),
-                            arguments.len()
-                        ),
-                        *span,
-                    ));
-                }
+                operations::check_arity(parameters.len(), arguments.len(), *span)?;
 
-                let call_env = Rc::new(RefCell::new(EnvFrame::new(Some(Rc::clone(&captured_env)))));
-                for (param, arg) in parameters.iter().zip(arguments.iter()) {
-                    let arg_value = match self.eval(arg)? {
-                        EvalOutcome::Value(value) => value,
+                let mut values = Vec::with_capacity(arguments.len());
+                for arg in arguments {
+                    match self.eval(arg)? {
+                        EvalOutcome::Value(value) => values.push(value),
                         control_flow => return Ok(control_flow),
-                    };
-                    call_env
-                        .borrow_mut()
-                        .values
-                        .insert(param.clone(), arg_value);
+                    }
                 }
+                let call_env = self.new_scope(captured_env, values);
 
                 let previous_env = Rc::clone(&self.current_env);
                 self.current_env = call_env;
@@ -381,62 +1030,48 @@ impl Interpreter {
                 match result? {
                     EvalOutcome::Return(value) => Ok(EvalOutcome::Value(value)),
                     EvalOutcome::Value(value) => Ok(EvalOutcome::Value(value)),
-                    EvalOutcome::Break => Err(FlavorError::with_span(
+                    EvalOutcome::Break(_) => Err(FlavorError::with_span(
                         ErrorPhase::Runtime,
                         "Unexpected 'break' outside of loop",
                         *span,
-                    )),
+                    )
+                    .with_code(ErrorCode::JumpOutOfFunction)),
+                    EvalOutcome::Continue(_) => Err(FlavorError::with_span(
+                        ErrorPhase::Runtime,
+                        "Unexpected 'continue' outside of loop",
+                        *span,
+                    )
+                    .with_code(ErrorCode::JumpOutOfFunction)),
                 }
             }
             AST::FunctionExpression {
                 parameters, body, ..
             } => {
-                let func = EvaluationType::Function {
-                    parameters: parameters.iter().map(|(param, _)| param.clone()).collect(),
-                    body: body.clone(),
-                    env: Rc::clone(&self.current_env),
-                };
-                Ok(EvalOutcome::Value(func))
+                Ok(EvalOutcome::Value(self.function(parameters, body)))
             }
             AST::UnitLiteral { .. } => Ok(EvalOutcome::Value(EvaluationType::Unit)),
-            AST::NumberLiteral { value, span } => {
-                let parsed = value.parse::<i64>().map_err(|_| {
-                    FlavorError::with_span(ErrorPhase::Runtime, "Invalid integer literal", *span)
-                })?;
+            AST::NumberLiteral { value, .. } => {
+                let parsed = value.parse::<i64>().expect("the parser checks integer literals");
                 Ok(EvalOutcome::Value(EvaluationType::Int(parsed)))
             }
-            AST::FloatLiteral { value, span } => {
-                let parsed = value.parse::<f64>().map_err(|_| {
-                    FlavorError::with_span(ErrorPhase::Runtime, "Invalid float literal", *span)
-                })?;
+            AST::FloatLiteral { value, .. } => {
+                let parsed = value.parse::<f64>().expect("the lexer only produces valid floats");
                 Ok(EvalOutcome::Value(EvaluationType::Float(parsed)))
             }
             AST::StringLiteral { value, .. } => {
-                // remove the surrounding quotes if present
-                let value = if let Some(stripped) =
-                    value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
-                {
-                    stripped.to_string()
-                } else {
-                    value.clone()
-                };
-                Ok(EvalOutcome::Value(EvaluationType::String(value)))
+                Ok(EvalOutcome::Value(EvaluationType::String(value.clone())))
             }
-            AST::BoolLiteral { value, span } => {
-                let parsed = value.parse::<bool>().map_err(|_| {
-                    FlavorError::with_span(ErrorPhase::Runtime, "Invalid boolean literal", *span)
-                })?;
+            AST::BoolLiteral { value, .. } => {
+                let parsed = value.parse::<bool>().expect("booleans are 'true' or 'false'");
                 Ok(EvalOutcome::Value(EvaluationType::Bool(parsed)))
             }
-            AST::Identifier { name, span } => {
-                self.lookup(name).map(EvalOutcome::Value).ok_or_else(|| {
-                    FlavorError::with_span(
-                        ErrorPhase::Runtime,
-                        format!("Undefined variable: {name}"),
-                        *span,
-                    )
-                })
-            }
+            AST::Identifier {
+                name,
+                span,
+                location,
+            } => self
+                .lookup(resolved(location), name, *span)
+                .map(EvalOutcome::Value),
             AST::ArrayLiteral { elements, .. } => {
                 let mut values = Vec::with_capacity(elements.len());
                 for elem in elements {
@@ -445,7 +1080,83 @@ impl Interpreter {
                         control_flow => return Ok(control_flow),
                     }
                 }
-                Ok(EvalOutcome::Value(EvaluationType::Array(values)))
+                Ok(EvalOutcome::Value(EvaluationType::Array(Rc::new(values))))
+            }
+            AST::StructLiteral { name, fields, .. } => {
+                let mut values = Vec::with_capacity(fields.len());
+                for (field, expr) in fields {
+                    match self.eval(expr)? {
+                        EvalOutcome::Value(value) => values.push((field.clone(), value)),
+                        control_flow => return Ok(control_flow),
+                    }
+                }
+                // Store the fields in declaration order so printing does not depend on the
+                // order they were written in the literal.
+                if let Some(Type::Struct {
+                    fields: declared, ..
+                }) = EnvFrame::get_type(&self.current_env, name)
+                {
+                    values.sort_by_key(|(field, _)| {
+                        declared
+                            .iter()
+                            .position(|(declared_field, _)| declared_field == field)
+                    });
+                }
+                Ok(EvalOutcome::Value(EvaluationType::Struct {
+                    name: name.clone(),
+                    fields: values,
+                }))
+            }
+            AST::EnumVariant {
+                enum_name,
+                variant,
+                arguments,
+                ..
+            } => {
+                let mut payload = Vec::with_capacity(arguments.len());
+                for arg in arguments {
+                    match self.eval(arg)? {
+                        EvalOutcome::Value(value) => payload.push(value),
+                        control_flow => return Ok(control_flow),
+                    }
+                }
+                Ok(EvalOutcome::Value(EvaluationType::Variant {
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    payload,
+                }))
+            }
+            AST::Match {
+                scrutinee,
+                arms,
+                span,
+            } => {
+                let value = match self.eval(scrutinee)? {
+                    EvalOutcome::Value(value) => value,
+                    control_flow => return Ok(control_flow),
+                };
+                for arm in arms {
+                    if let Some(bindings) = Self::match_pattern(&arm.pattern, &value) {
+                        return self.eval_match_arm(arm, bindings);
+                    }
+                }
+                Err(FlavorError::with_span(
+                    ErrorPhase::Runtime,
+                    format!("No match arm matched the value {value}"),
+                    *span,
+                )
+                .with_code(ErrorCode::NoMatchingArm))
+            }
+            AST::FieldAccess {
+                object,
+                field,
+                span,
+            } => {
+                let object_value = match self.eval(object)? {
+                    EvalOutcome::Value(value) => value,
+                    control_flow => return Ok(control_flow),
+                };
+                operations::field(object_value, field, *span).map(EvalOutcome::Value)
             }
             AST::ArrayAccess { array, index, span } => {
                 let array_value = match self.eval(array)? {
@@ -456,32 +1167,7 @@ impl Interpreter {
                     EvalOutcome::Value(value) => value,
                     control_flow => return Ok(control_flow),
                 };
-                match (array_value, index_value) {
-                    (EvaluationType::Array(arr), EvaluationType::Int(idx)) => {
-                        if idx < 0 {
-                            return Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                "Negative array index",
-                                *span,
-                            ));
-                        }
-                        arr.get(idx as usize)
-                            .cloned()
-                            .map(EvalOutcome::Value)
-                            .ok_or_else(|| {
-                                FlavorError::with_span(
-                                    ErrorPhase::Runtime,
-                                    "Array index out of bounds",
-                                    *span,
-                                )
-                            })
-                    }
-                    _ => Err(FlavorError::with_span(
-                        ErrorPhase::Runtime,
-                        "Invalid array access",
-                        *span,
-                    )),
-                }
+                operations::index(array_value, index_value, *span).map(EvalOutcome::Value)
             }
             AST::BinaryExpression {
                 left,
@@ -496,133 +1182,34 @@ impl Interpreter {
                     };
 
                     match &**left {
-                        AST::Identifier { name, .. } => {
-                            if !self.assign_existing(name, right_value.clone()) {
-                                return Err(FlavorError::with_span(
-                                    ErrorPhase::Runtime,
-                                    format!("Undefined variable: {name}"),
-                                    *left.span(),
-                                ));
-                            }
+                        AST::Identifier {
+                            name,
+                            span,
+                            location,
+                        } => {
+                            let value = right_value.clone();
+                            self.with_variable(resolved(location), name, *span, |target| {
+                                *target = value;
+                            })?;
                             Ok(EvalOutcome::Value(right_value))
                         }
-                        AST::ArrayAccess { .. } => {
-                            let mut index_nodes = Vec::new();
-                            let mut current = left.as_ref();
-                            let (base_name, base_span) = loop {
-                                match current {
-                                    AST::Identifier { name, span } => break (name.clone(), *span),
-                                    AST::ArrayAccess { array, index, .. } => {
-                                        index_nodes.push((
-                                            index.as_ref(),
-                                            *index.span(),
-                                            *array.span(),
-                                        ));
-                                        current = array.as_ref();
-                                    }
-                                    _ => {
-                                        return Err(FlavorError::with_span(
-                                            ErrorPhase::Runtime,
-                                            "Left side of assignment must be an identifier or array access",
-                                            *current.span(),
-                                        ));
-                                    }
-                                }
+                        AST::ArrayAccess { .. } | AST::FieldAccess { .. } => {
+                            let place = match self.eval_place(left)? {
+                                Ok(place) => place,
+                                Err(control_flow) => return Ok(control_flow),
                             };
-
-                            let mut index_chain = Vec::with_capacity(index_nodes.len());
-                            for &(index_ast, index_span, array_span) in index_nodes.iter().rev() {
-                                let index_value = match self.eval(index_ast)? {
-                                    EvalOutcome::Value(value) => value,
-                                    control_flow => return Ok(control_flow),
-                                };
-                                let idx_int = if let EvaluationType::Int(i) = index_value {
-                                    i
-                                } else {
-                                    return Err(FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        "Array index must be an integer",
-                                        index_span,
-                                    ));
-                                };
-                                if idx_int < 0 {
-                                    return Err(FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        "Negative array index",
-                                        index_span,
-                                    ));
-                                }
-                                index_chain.push((idx_int as usize, index_span, array_span));
-                            }
-
-                            let target_env = self.find_env_for(&base_name).ok_or_else(|| {
-                                FlavorError::with_span(
-                                    ErrorPhase::Runtime,
-                                    format!("Undefined variable: {base_name}"),
-                                    base_span,
-                                )
+                            self.with_place_mut(place, |target| {
+                                *target = right_value.clone();
+                                Ok(())
                             })?;
-
-                            let mut env_ref = target_env.borrow_mut();
-                            let mut target =
-                                env_ref.values.get_mut(&base_name).ok_or_else(|| {
-                                    FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        format!("Undefined variable: {base_name}"),
-                                        base_span,
-                                    )
-                                })?;
-
-                            let last_index = index_chain.len().saturating_sub(1);
-                            for (depth, (idx, index_span, array_span)) in
-                                index_chain.into_iter().enumerate()
-                            {
-                                match target {
-                                    EvaluationType::Array(arr) => {
-                                        if idx >= arr.len() {
-                                            let message = if depth == 0 {
-                                                let len = arr.len();
-                                                format!(
-                                                    "Index {idx} out of bounds for array '{base_name}' of length {len}"
-                                                )
-                                            } else {
-                                                "Array index out of bounds".to_string()
-                                            };
-                                            return Err(FlavorError::with_span(
-                                                ErrorPhase::Runtime,
-                                                message,
-                                                index_span,
-                                            ));
-                                        }
-                                        if depth == last_index {
-                                            arr[idx] = right_value.clone();
-                                            return Ok(EvalOutcome::Value(right_value));
-                                        }
-                                        target = &mut arr[idx];
-                                    }
-                                    _ => {
-                                        let span = if depth == 0 { base_span } else { array_span };
-                                        let message = if depth == 0 {
-                                            format!("Variable '{base_name}' is not an array")
-                                        } else {
-                                            "Value is not an array".to_string()
-                                        };
-                                        return Err(FlavorError::with_span(
-                                            ErrorPhase::Runtime,
-                                            message,
-                                            span,
-                                        ));
-                                    }
-                                }
-                            }
-
-                            unreachable!("Array assignment without indices");
+                            Ok(EvalOutcome::Value(right_value))
                         }
                         _ => Err(FlavorError::with_span(
                             ErrorPhase::Runtime,
-                            "Left side of assignment must be an identifier or array access",
+                            "Left side of assignment must be an identifier, array access or field access",
                             *left.span(),
-                        )),
+                        )
+                        .with_code(ErrorCode::RuntimeTypeError)),
                     }
                 } else {
                     let left_value = match self.eval(left)? {
@@ -634,207 +1221,17 @@ impl Interpreter {
                         control_flow => return Ok(control_flow),
                     };
 
-                    match operator.as_str() {
-                        "+" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => Ok(EvalOutcome::Value(ET::Int(l + r))),
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(ET::Float(l + r)))
-                            }
-                            (ET::String(l), ET::String(r)) => {
-                                Ok(EvalOutcome::Value(ET::String(format!("{l}{r}"))))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} + {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "-" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => Ok(EvalOutcome::Value(ET::Int(l - r))),
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(ET::Float(l - r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} - {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "*" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => Ok(EvalOutcome::Value(ET::Int(l * r))),
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(ET::Float(l * r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} * {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "/" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                if r == 0 {
-                                    Err(FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        "Division by zero",
-                                        *span,
-                                    ))
-                                } else {
-                                    Ok(EvalOutcome::Value(ET::Int(l / r)))
-                                }
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                if r == 0.0 {
-                                    Err(FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        "Division by zero",
-                                        *span,
-                                    ))
-                                } else {
-                                    Ok(EvalOutcome::Value(ET::Float(l / r)))
-                                }
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} / {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "%" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                if r == 0 {
-                                    Err(FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        "Modulo by zero",
-                                        *span,
-                                    ))
-                                } else {
-                                    Ok(EvalOutcome::Value(ET::Int(l % r)))
-                                }
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} % {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "==" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l == r)))
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l == r)))
-                            }
-                            (ET::Bool(l), ET::Bool(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l == r)))
-                            }
-                            (ET::String(l), ET::String(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l == r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} == {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "!=" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l != r)))
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l != r)))
-                            }
-                            (ET::Bool(l), ET::Bool(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l != r)))
-                            }
-                            (ET::String(l), ET::String(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l != r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} != {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "<" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l < r)))
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l < r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} < {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "<=" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l <= r)))
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l <= r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} <= {r:?}",),
-                                *span,
-                            )),
-                        },
-                        ">" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l > r)))
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l > r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} > {r:?}",),
-                                *span,
-                            )),
-                        },
-                        ">=" => match (left_value, right_value) {
-                            (ET::Int(l), ET::Int(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l >= r)))
-                            }
-                            (ET::Float(l), ET::Float(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l >= r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} >= {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "&&" => match (left_value, right_value) {
-                            (ET::Bool(l), ET::Bool(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l && r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} && {r:?}",),
-                                *span,
-                            )),
-                        },
-                        "||" => match (left_value, right_value) {
-                            (ET::Bool(l), ET::Bool(r)) => {
-                                Ok(EvalOutcome::Value(EvaluationType::Bool(l || r)))
-                            }
-                            (l, r) => Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!("Unsupported binary operation: {l:?} || {r:?}",),
-                                *span,
-                            )),
-                        },
-                        op => Err(FlavorError::with_span(
+                    match BinaryOperator::parse(operator) {
+                        Some(operator) => operations::binary(operator, left_value, right_value, *span)
+                            .map(EvalOutcome::Value),
+                        None => Err(FlavorError::with_span(
                             ErrorPhase::Runtime,
                             format!(
-                                "Unsupported binary operation: {left_value:?} {op} {right_value:?}",
+                                "Unsupported binary operation: {left_value:?} {operator} {right_value:?}",
                             ),
                             *span,
-                        )),
+                        )
+                        .with_code(ErrorCode::RuntimeTypeError)),
                     }
                 }
             }
@@ -845,149 +1242,29 @@ impl Interpreter {
                 span,
             } => match operator.as_str() {
                 "-" if !is_postfix => match self.eval(operand)? {
-                    EvalOutcome::Value(EvaluationType::Int(value)) => {
-                        Ok(EvalOutcome::Value(EvaluationType::Int(-value)))
-                    }
-                    EvalOutcome::Value(EvaluationType::Float(value)) => {
-                        Ok(EvalOutcome::Value(EvaluationType::Float(-value)))
+                    EvalOutcome::Value(value) => {
+                        operations::negate(value, *span).map(EvalOutcome::Value)
                     }
-                    EvalOutcome::Value(value) => Err(FlavorError::with_span(
-                        ErrorPhase::Runtime,
-                        format!(
-                            "Unsupported unary operation: {operator} (postfix: {is_postfix}) on {value:?}",
-                        ),
-                        *operand.span(),
-                    )),
                     control_flow => Ok(control_flow),
                 },
                 "!" if !is_postfix => match self.eval(operand)? {
-                    EvalOutcome::Value(EvaluationType::Bool(value)) => {
-                        Ok(EvalOutcome::Value(EvaluationType::Bool(!value)))
+                    EvalOutcome::Value(value) => {
+                        operations::not(value, *operand.span()).map(EvalOutcome::Value)
                     }
-                    EvalOutcome::Value(value) => Err(FlavorError::with_span(
-                        ErrorPhase::Runtime,
-                        format!(
-                            "Unsupported unary operation: {operator} (postfix: {is_postfix}) on {value:?}",
-                        ),
-                        *operand.span(),
-                    )),
                     control_flow => Ok(control_flow),
                 },
                 "++" | "--" => {
                     let delta: i64 = if operator == "++" { 1 } else { -1 };
 
-                    let mut index_nodes = Vec::new();
-                    let mut current = operand.as_ref();
-                    let (base_name, base_span) = loop {
-                        match current {
-                            AST::Identifier { name, span } => break (name.clone(), *span),
-                            AST::ArrayAccess { array, index, .. } => {
-                                index_nodes.push((index.as_ref(), *index.span(), *array.span()));
-                                current = array.as_ref();
-                            }
-                            _ => {
-                                return Err(FlavorError::with_span(
-                                    ErrorPhase::Runtime,
-                                    "Operand must be an identifier or array access for increment/decrement",
-                                    *current.span(),
-                                ));
-                            }
-                        }
+                    let place = match self.eval_place(operand)? {
+                        Ok(place) => place,
+                        Err(control_flow) => return Ok(control_flow),
                     };
 
-                    let mut index_chain = Vec::with_capacity(index_nodes.len());
-                    for (index_ast, index_span, array_span) in index_nodes.iter().rev() {
-                        let index_value = match self.eval(index_ast)? {
-                            EvalOutcome::Value(value) => value,
-                            control_flow => return Ok(control_flow),
-                        };
-                        let idx_int = if let EvaluationType::Int(i) = index_value {
-                            i
-                        } else {
-                            return Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                "Array index must be an integer",
-                                *index_span,
-                            ));
-                        };
-                        if idx_int < 0 {
-                            return Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                "Negative array index",
-                                *index_span,
-                            ));
-                        }
-                        index_chain.push((idx_int as usize, *index_span, *array_span));
-                    }
-
-                    let target_env = self.find_env_for(&base_name).ok_or_else(|| {
-                        FlavorError::with_span(
-                            ErrorPhase::Runtime,
-                            format!("Undefined variable: {base_name}"),
-                            base_span,
-                        )
-                    })?;
-
-                    let mut env_ref = target_env.borrow_mut();
-                    let mut target = env_ref.values.get_mut(&base_name).ok_or_else(|| {
-                        FlavorError::with_span(
-                            ErrorPhase::Runtime,
-                            format!("Undefined variable: {base_name}"),
-                            base_span,
-                        )
-                    })?;
-
-                    for (depth, (idx, index_span, array_span)) in
-                        index_chain.into_iter().enumerate()
-                    {
-                        match target {
-                            EvaluationType::Array(arr) => {
-                                if idx >= arr.len() {
-                                    return Err(FlavorError::with_span(
-                                        ErrorPhase::Runtime,
-                                        "Array index out of bounds",
-                                        index_span,
-                                    ));
-                                }
-                                target = &mut arr[idx];
-                            }
-                            _ => {
-                                let span = if depth == 0 { base_span } else { array_span };
-                                let message = if depth == 0 {
-                                    format!("{base_name} is not an array")
-                                } else {
-                                    "Value is not an array".to_string()
-                                };
-                                return Err(FlavorError::with_span(
-                                    ErrorPhase::Runtime,
-                                    message,
-                                    span,
-                                ));
-                            }
-                        }
-                    }
-
-                    let (old_value, new_value) = match target {
-                        EvaluationType::Int(value) => {
-                            let old_int = *value;
-                            *value += delta;
-                            (EvaluationType::Int(old_int), EvaluationType::Int(*value))
-                        }
-                        other => {
-                            return Err(FlavorError::with_span(
-                                ErrorPhase::Runtime,
-                                format!(
-                                    "Unsupported unary operation: {operator} (postfix: {is_postfix}) on {other:?}",
-                                ),
-                                *operand.span(),
-                            ));
-                        }
-                    };
-                    if *is_postfix {
-                        Ok(EvalOutcome::Value(old_value))
-                    } else {
-                        Ok(EvalOutcome::Value(new_value))
-                    }
+                    self.with_place_mut(place, |target| {
+                        operations::increment(target, delta, *is_postfix, *span)
+                    })
+                    .map(EvalOutcome::Value)
                 }
 
                 _ => match self.eval(operand)? {
@@ -997,7 +1274,8 @@ impl Interpreter {
                             "Unsupported unary operation: {operator} (postfix: {is_postfix}) on {operand_value:?}",
                         ),
                         *span,
-                    )),
+                    )
+                    .with_code(ErrorCode::RuntimeTypeError)),
                     control_flow => Ok(control_flow),
                 },
             },
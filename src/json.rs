@@ -0,0 +1,494 @@
+use std::fmt::{self, Display, Write};
+
+use crate::types::{ASTNode, MatchArm, Parameter, Pattern, Span, Token, Type};
+
+/// A JSON value, just enough to emit machine-readable output without extra dependencies.
+/// Object keys keep their insertion order so the output is stable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    pub fn string(value: impl Into<String>) -> Self {
+        Json::String(value.into())
+    }
+
+    pub fn object(fields: impl IntoIterator<Item = (&'static str, Json)>) -> Self {
+        Json::Object(fields.into_iter().collect())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as i64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::string(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<&String> for Json {
+    fn from(value: &String) -> Self {
+        Json::string(value)
+    }
+}
+
+impl From<Span> for Json {
+    fn from(span: Span) -> Self {
+        Json::object([
+            ("start_line", span.start_line.into()),
+            ("start_column", span.start_column.into()),
+            ("end_line", span.end_line.into()),
+            ("end_column", span.end_column.into()),
+        ])
+    }
+}
+
+impl From<&Type> for Json {
+    fn from(ty: &Type) -> Self {
+        Json::string(ty.to_string())
+    }
+}
+
+impl From<&Token> for Json {
+    fn from(token: &Token) -> Self {
+        Json::object([
+            ("kind", Json::string(format!("{:?}", token.tok_name))),
+            ("lexeme", (&token.lexeme).into()),
+            ("value", token.value.as_ref().into()),
+            ("span", token.span.into()),
+        ])
+    }
+}
+
+impl From<&Pattern> for Json {
+    fn from(pattern: &Pattern) -> Self {
+        match pattern {
+            Pattern::Wildcard { span } => node("Wildcard", span, []),
+            Pattern::Binding { name, span } => node("Binding", span, [("name", name.into())]),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                bindings,
+                span,
+            } => node(
+                "Variant",
+                span,
+                [
+                    ("enum_name", enum_name.into()),
+                    ("variant", variant.into()),
+                    (
+                        "bindings",
+                        Json::Array(bindings.iter().map(Json::from).collect()),
+                    ),
+                ],
+            ),
+        }
+    }
+}
+
+impl From<&MatchArm> for Json {
+    fn from(arm: &MatchArm) -> Self {
+        Json::object([
+            ("pattern", (&arm.pattern).into()),
+            ("body", (&arm.body).into()),
+            ("span", arm.span.into()),
+        ])
+    }
+}
+
+/// Every node is an object whose `node` field names the variant, followed by the variant's
+/// fields under their Rust names and the `span`. Types are spelled as in Flavor source.
+impl From<&ASTNode> for Json {
+    fn from(ast: &ASTNode) -> Self {
+        match ast {
+            ASTNode::Print { expressions, span } => {
+                node("Print", span, [("expressions", nodes(expressions))])
+            }
+            ASTNode::Body { nodes: body, span } => node("Body", span, [("nodes", nodes(body))]),
+            ASTNode::If {
+                guard,
+                then_body,
+                else_body,
+                span,
+            } => node(
+                "If",
+                span,
+                [
+                    ("guard", guard.as_ref().into()),
+                    ("then_body", then_body.as_ref().into()),
+                    ("else_body", else_body.as_deref().into()),
+                ],
+            ),
+            ASTNode::While {
+                label,
+                guard,
+                body,
+                span,
+            } => node(
+                "While",
+                span,
+                [
+                    ("label", label.as_ref().into()),
+                    ("guard", guard.as_ref().into()),
+                    ("body", body.as_ref().into()),
+                ],
+            ),
+            ASTNode::For {
+                label,
+                variable,
+                iterable,
+                body,
+                span,
+            } => node(
+                "For",
+                span,
+                [
+                    ("label", label.as_ref().into()),
+                    ("variable", variable.into()),
+                    ("iterable", iterable.as_ref().into()),
+                    ("body", body.as_ref().into()),
+                ],
+            ),
+            ASTNode::Range {
+                start,
+                end,
+                inclusive,
+                span,
+            } => node(
+                "Range",
+                span,
+                [
+                    ("start", start.as_ref().into()),
+                    ("end", end.as_ref().into()),
+                    ("inclusive", (*inclusive).into()),
+                ],
+            ),
+            ASTNode::LetDeclaration {
+                identifier,
+                var_type,
+                expr,
+                span,
+                ..
+            } => node(
+                "LetDeclaration",
+                span,
+                [
+                    ("identifier", identifier.into()),
+                    ("var_type", var_type.as_ref().into()),
+                    ("expr", expr.as_ref().into()),
+                ],
+            ),
+            ASTNode::FunctionDeclaration {
+                name,
+                parameters,
+                return_type,
+                body,
+                span,
+                ..
+            } => node(
+                "FunctionDeclaration",
+                span,
+                [
+                    ("name", name.into()),
+                    ("parameters", parameter_list(parameters)),
+                    ("return_type", return_type.into()),
+                    ("body", body.as_ref().into()),
+                ],
+            ),
+            ASTNode::FunctionExpression {
+                parameters,
+                return_type,
+                body,
+                span,
+            } => node(
+                "FunctionExpression",
+                span,
+                [
+                    ("parameters", parameter_list(parameters)),
+                    ("return_type", return_type.into()),
+                    ("body", body.as_ref().into()),
+                ],
+            ),
+            ASTNode::AliasDeclaration {
+                name,
+                aliased_type,
+                span,
+            } => node(
+                "AliasDeclaration",
+                span,
+                [("name", name.into()), ("aliased_type", aliased_type.into())],
+            ),
+            ASTNode::StructDeclaration { name, fields, span } => node(
+                "StructDeclaration",
+                span,
+                [("name", name.into()), ("fields", typed_names(fields))],
+            ),
+            ASTNode::EnumDeclaration {
+                name,
+                variants,
+                span,
+            } => node(
+                "EnumDeclaration",
+                span,
+                [
+                    ("name", name.into()),
+                    (
+                        "variants",
+                        Json::Array(
+                            variants
+                                .iter()
+                                .map(|(variant, payload)| {
+                                    Json::object([
+                                        ("name", variant.into()),
+                                        (
+                                            "payload",
+                                            Json::Array(payload.iter().map(Json::from).collect()),
+                                        ),
+                                    ])
+                                })
+                                .collect(),
+                        ),
+                    ),
+                ],
+            ),
+            ASTNode::Return { expr, span } => {
+                node("Return", span, [("expr", expr.as_ref().into())])
+            }
+            ASTNode::Break { label, span } => {
+                node("Break", span, [("label", label.as_ref().into())])
+            }
+            ASTNode::Continue { label, span } => {
+                node("Continue", span, [("label", label.as_ref().into())])
+            }
+            ASTNode::FunctionCall {
+                callee,
+                arguments,
+                span,
+            } => node(
+                "FunctionCall",
+                span,
+                [
+                    ("callee", callee.as_ref().into()),
+                    ("arguments", nodes(arguments)),
+                ],
+            ),
+            ASTNode::UnitLiteral { span } => node("UnitLiteral", span, []),
+            ASTNode::NumberLiteral { value, span } => {
+                node("NumberLiteral", span, [("value", value.into())])
+            }
+            ASTNode::FloatLiteral { value, span } => {
+                node("FloatLiteral", span, [("value", value.into())])
+            }
+            ASTNode::StringLiteral { value, span } => {
+                node("StringLiteral", span, [("value", value.into())])
+            }
+            ASTNode::BoolLiteral { value, span } => {
+                node("BoolLiteral", span, [("value", value.into())])
+            }
+            ASTNode::Identifier { name, span, .. } => {
+                node("Identifier", span, [("name", name.into())])
+            }
+            ASTNode::ArrayLiteral { elements, span } => {
+                node("ArrayLiteral", span, [("elements", nodes(elements))])
+            }
+            ASTNode::ArrayAccess { array, index, span } => node(
+                "ArrayAccess",
+                span,
+                [
+                    ("array", array.as_ref().into()),
+                    ("index", index.as_ref().into()),
+                ],
+            ),
+            ASTNode::StructLiteral { name, fields, span } => node(
+                "StructLiteral",
+                span,
+                [
+                    ("name", name.into()),
+                    (
+                        "fields",
+                        Json::Array(
+                            fields
+                                .iter()
+                                .map(|(field, value)| {
+                                    Json::object([("name", field.into()), ("value", value.into())])
+                                })
+                                .collect(),
+                        ),
+                    ),
+                ],
+            ),
+            ASTNode::FieldAccess {
+                object,
+                field,
+                span,
+            } => node(
+                "FieldAccess",
+                span,
+                [("object", object.as_ref().into()), ("field", field.into())],
+            ),
+            ASTNode::EnumVariant {
+                enum_name,
+                variant,
+                arguments,
+                span,
+            } => node(
+                "EnumVariant",
+                span,
+                [
+                    ("enum_name", enum_name.into()),
+                    ("variant", variant.into()),
+                    ("arguments", nodes(arguments)),
+                ],
+            ),
+            ASTNode::Match {
+                scrutinee,
+                arms,
+                span,
+            } => node(
+                "Match",
+                span,
+                [
+                    ("scrutinee", scrutinee.as_ref().into()),
+                    ("arms", Json::Array(arms.iter().map(Json::from).collect())),
+                ],
+            ),
+            ASTNode::BinaryExpression {
+                left,
+                operator,
+                right,
+                span,
+            } => node(
+                "BinaryExpression",
+                span,
+                [
+                    ("left", left.as_ref().into()),
+                    ("operator", operator.into()),
+                    ("right", right.as_ref().into()),
+                ],
+            ),
+            ASTNode::UnaryExpression {
+                operator,
+                operand,
+                is_postfix,
+                span,
+            } => node(
+                "UnaryExpression",
+                span,
+                [
+                    ("operator", operator.into()),
+                    ("operand", operand.as_ref().into()),
+                    ("is_postfix", (*is_postfix).into()),
+                ],
+            ),
+            ASTNode::ExpressionStatement { expr, span } => node(
+                "ExpressionStatement",
+                span,
+                [("expr", expr.as_ref().into())],
+            ),
+        }
+    }
+}
+
+fn node<const N: usize>(kind: &str, span: &Span, fields: [(&'static str, Json); N]) -> Json {
+    let mut object = vec![("node", Json::string(kind))];
+    object.extend(fields);
+    object.push(("span", (*span).into()));
+    Json::Object(object)
+}
+
+fn nodes(list: &[ASTNode]) -> Json {
+    Json::Array(list.iter().map(Json::from).collect())
+}
+
+fn parameter_list(parameters: &[Parameter]) -> Json {
+    Json::Array(
+        parameters
+            .iter()
+            .map(|parameter| {
+                Json::object([
+                    ("name", parameter.name.as_str().into()),
+                    ("type", (&parameter.ty).into()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn typed_names(list: &[(String, Type)]) -> Json {
+    Json::Array(
+        list.iter()
+            .map(|(name, ty)| Json::object([("name", name.into()), ("type", ty.into())]))
+            .collect(),
+    )
+}
+
+/// Writes the value on a single line.
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                f.write_char('[')?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for ch in value.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => f.write_char(ch)?,
+        }
+    }
+    f.write_char('"')
+}
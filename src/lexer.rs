@@ -1,8 +1,8 @@
 use crate::{
     error::{ErrorPhase, FlavorError},
+    error_codes::ErrorCode,
     types::{Span, Token, TokenName as TN},
 };
-use regex::Regex;
 
 pub struct Lexer {
     pub tokens: Vec<Token>,
@@ -35,99 +35,144 @@ impl Lexer {
     }
 
     fn next_token(&mut self) -> Result<Token, FlavorError> {
-        self.skip_whitespace();
+        self.skip_trivia()?;
 
         if self.pos >= self.source.len() {
             return Ok(Token {
                 tok_name: TN::Eof,
                 lexeme: "\0".to_string(),
                 span: Span::point(self.line, self.column),
+                value: None,
             });
         }
 
-        let patterns = [
-            (r"print\b", TN::Print),
-            (r"let\b", TN::Let),
-            (r"fn\b", TN::Fn),
-            (r"alias\b", TN::Alias),
-            (r"int\b", TN::Int),
-            (r"float\b", TN::Float),
-            (r"string\b", TN::String),
-            (r"bool\b", TN::Bool),
-            (r"array\b", TN::Array),
-            (r"return\b", TN::Return),
-            (r"break\b", TN::Break),
-            (r"if\b", TN::If),
-            (r"else\b", TN::Else),
-            (r"while\b", TN::While),
-            (r"nothing\b", TN::Nothing),
-            (r"true\b", TN::True),
-            (r"false\b", TN::False),
-            (r"\.", TN::Dot),
-            (r",", TN::Comma),
-            (r":", TN::Colon),
-            (r";", TN::Semicolon),
-            (r"->", TN::SlimArrow),
-            (r"=>", TN::BoldArrow),
-            (r"==", TN::Eq),
-            (r"\!=", TN::NotEq),
-            (r"\=", TN::Assign),
-            (r"\!", TN::Not),
-            (r">=", TN::Ge),
-            (r"<=", TN::Le),
-            (r">", TN::Gt),
-            (r"<", TN::Lt),
-            (r"\+\+", TN::PlusPlus),
-            (r"--", TN::MinusMinus),
-            (r"\+", TN::Plus),
-            (r"-", TN::Minus),
-            (r"\*", TN::Times),
-            (r"/", TN::Div),
-            (r"%", TN::Percent),
-            (r"&&", TN::And),
-            (r"\|\|", TN::Or),
-            (r"\(", TN::LPar),
-            (r"\)", TN::RPar),
-            (r"\[", TN::LSqu),
-            (r"\]", TN::RSqu),
-            (r"\{", TN::LBra),
-            (r"\}", TN::RBra),
-            (r"\b\d+\.\d+\b", TN::FloatLiteral),
-            (r"[0-9]+", TN::Number),
-            (r#""(.*?)""#, TN::StringLiteral),
-            (r"[a-zA-Z_][a-zA-Z0-9_]*", TN::Identifier),
-            (r"[\s\S]*", TN::Unknown),
-        ];
-
-        let mut token_name = TN::Unknown;
-        let mut lexeme = String::new();
-
-        for (pattern, name) in patterns.iter() {
-            if let Some(matched) = self.match_start(pattern) {
-                token_name = name.clone();
-                lexeme = matched.to_string();
-                break;
-            }
+        if let Some(token) = self.lex_string()? {
+            return Ok(token);
         }
 
-        if token_name == TN::Unknown {
+        let rest = self.remaining_source();
+        let first = rest.chars().next().unwrap_or('\0');
+        let scanned = if first.is_ascii_alphabetic() || first == '_' {
+            let len = identifier_len(rest);
+            Some((keyword(&rest[..len]).unwrap_or(TN::Identifier), len))
+        } else if first.is_ascii_digit() {
+            Some(scan_number(rest))
+        } else if first == '\'' && identifier_len(&rest[1..]) > 0 {
+            Some((TN::Label, 1 + identifier_len(&rest[1..])))
+        } else if is_doc_comment(rest) {
+            Some((TN::DocComment, rest.find('\n').unwrap_or(rest.len())))
+        } else {
+            SYMBOLS
+                .iter()
+                .find(|(symbol, _)| rest.starts_with(symbol))
+                .map(|(symbol, name)| (name.clone(), symbol.len()))
+        };
+
+        let Some((token_name, len)) = scanned else {
+            let lexeme = first.to_string();
             let span = self.advance_with(&lexeme);
             return Err(FlavorError::with_span(
                 ErrorPhase::Lexing,
                 format!("Unknown token: {lexeme}"),
                 span,
-            ));
+            )
+            .with_code(ErrorCode::UnknownToken));
         };
 
+        let lexeme = rest[..len].to_string();
         let span = self.advance_with(&lexeme);
 
         Ok(Token {
             tok_name: token_name,
             lexeme,
             span,
+            value: None,
         })
     }
 
+    /// Lexes a string literal starting at the current position, if there is one.
+    /// Plain (`"..."`) and triple-quoted (`"""..."""`) strings decode escape sequences,
+    /// raw strings (`r"..."`) keep their content verbatim. Only triple-quoted strings
+    /// may span several lines.
+    fn lex_string(&mut self) -> Result<Option<Token>, FlavorError> {
+        let rest = self.remaining_source();
+        let (opening, terminator, raw) = if rest.starts_with("r\"") {
+            ("r\"", "\"", true)
+        } else if rest.starts_with("\"\"\"") {
+            ("\"\"\"", "\"\"\"", false)
+        } else if rest.starts_with('"') {
+            ("\"", "\"", false)
+        } else {
+            return Ok(None);
+        };
+        let multiline = terminator.len() == 3;
+        let opening_span = Span::new(
+            self.line,
+            self.column,
+            self.line,
+            self.column + opening.len() - 1,
+        );
+        let unterminated = || {
+            FlavorError::with_span(
+                ErrorPhase::Lexing,
+                "Unterminated string literal",
+                opening_span,
+            )
+            .with_code(ErrorCode::UnterminatedString)
+        };
+
+        let mut value = String::new();
+        let mut offset = opening.len();
+        let mut line = self.line;
+        let mut column = self.column + opening.len();
+        loop {
+            let tail = &rest[offset..];
+            if tail.starts_with(terminator) {
+                offset += terminator.len();
+                break;
+            }
+            let Some(ch) = tail.chars().next() else {
+                return Err(unterminated());
+            };
+            if ch == '\n' && !multiline {
+                return Err(unterminated());
+            }
+
+            if ch == '\\' && !raw {
+                let (decoded, len) = decode_escape(tail).map_err(|(message, width)| {
+                    FlavorError::with_span(
+                        ErrorPhase::Lexing,
+                        message,
+                        Span::new(line, column, line, column + width - 1),
+                    )
+                    .with_code(ErrorCode::InvalidEscape)
+                })?;
+                value.push(decoded);
+                offset += len;
+                column += tail[..len].chars().count();
+                continue;
+            }
+
+            value.push(ch);
+            offset += ch.len_utf8();
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        let lexeme = rest[..offset].to_string();
+        let span = self.advance_with(&lexeme);
+        Ok(Some(Token {
+            tok_name: TN::StringLiteral,
+            lexeme,
+            span,
+            value: Some(value),
+        }))
+    }
+
     fn advance_with(&mut self, text: &str) -> Span {
         let start_line = self.line;
         let start_column = self.column;
@@ -159,30 +204,69 @@ impl Lexer {
         Span::new(start_line, start_column, end_line, end_column)
     }
 
-    fn match_start(&self, pattern: &str) -> Option<&str> {
-        let re = Regex::new(pattern).unwrap();
-        if let Some(mat) = re.find(self.remaining_source()) {
-            if mat.start() == 0 {
-                Some(mat.as_str())
+    /// Skips whitespace, `//` line comments and `/* */` block comments.
+    /// Doc comments (`///`) are left in place so they are emitted as tokens.
+    fn skip_trivia(&mut self) -> Result<(), FlavorError> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.remaining_source();
+            if rest.starts_with("//") && !is_doc_comment(rest) {
+                let end = rest.find('\n').unwrap_or(rest.len());
+                self.consume_n_char(end);
+            } else if rest.starts_with("/*") {
+                self.skip_block_comment()?;
             } else {
-                None
+                return Ok(());
             }
-        } else {
-            None
         }
     }
 
-    fn skip_whitespace(&mut self) {
-        let re = Regex::new(r"^\s+").unwrap();
-        while let Some(m) = re.find(self.remaining_source()) {
-            if m.start() == 0 {
-                self.consume_n_char(m.end());
+    /// Skips a block comment starting at the current position. Block comments nest,
+    /// so `/* a /* b */ c */` is a single comment.
+    fn skip_block_comment(&mut self) -> Result<(), FlavorError> {
+        let rest = self.remaining_source();
+        let mut depth = 0;
+        let mut offset = 0;
+        let mut end = None;
+        while offset < rest.len() {
+            let tail = &rest[offset..];
+            if tail.starts_with("/*") {
+                depth += 1;
+                offset += 2;
+            } else if tail.starts_with("*/") {
+                depth -= 1;
+                offset += 2;
+                if depth == 0 {
+                    end = Some(offset);
+                    break;
+                }
             } else {
-                break;
+                offset += tail.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+
+        match end {
+            Some(end) => {
+                self.consume_n_char(end);
+                Ok(())
             }
+            None => Err(FlavorError::with_span(
+                ErrorPhase::Lexing,
+                "Unterminated comment",
+                Span::new(self.line, self.column, self.line, self.column + 1),
+            )
+            .with_code(ErrorCode::UnterminatedComment)),
         }
     }
 
+    fn skip_whitespace(&mut self) {
+        let rest = self.remaining_source();
+        let len = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        self.consume_n_char(len);
+    }
+
     fn remaining_source(&self) -> &str {
         &self.source[self.pos..]
     }
@@ -200,3 +284,147 @@ impl Lexer {
         self.pos += text.len();
     }
 }
+
+/// Operators and delimiters, longer symbols first so that e.g. `..=` wins over `..` and `.`.
+const SYMBOLS: &[(&str, TN)] = &[
+    ("..=", TN::DotDotEq),
+    ("..", TN::DotDot),
+    (".", TN::Dot),
+    (",", TN::Comma),
+    ("::", TN::DoubleColon),
+    (":", TN::Colon),
+    (";", TN::Semicolon),
+    ("->", TN::SlimArrow),
+    ("=>", TN::BoldArrow),
+    ("==", TN::Eq),
+    ("!=", TN::NotEq),
+    ("=", TN::Assign),
+    ("!", TN::Not),
+    (">=", TN::Ge),
+    ("<=", TN::Le),
+    (">", TN::Gt),
+    ("<", TN::Lt),
+    ("++", TN::PlusPlus),
+    ("--", TN::MinusMinus),
+    ("+", TN::Plus),
+    ("-", TN::Minus),
+    ("*", TN::Times),
+    ("/", TN::Div),
+    ("%", TN::Percent),
+    ("&&", TN::And),
+    ("||", TN::Or),
+    ("(", TN::LPar),
+    (")", TN::RPar),
+    ("[", TN::LSqu),
+    ("]", TN::RSqu),
+    ("{", TN::LBra),
+    ("}", TN::RBra),
+];
+
+fn keyword(word: &str) -> Option<TN> {
+    let name = match word {
+        "print" => TN::Print,
+        "let" => TN::Let,
+        "fn" => TN::Fn,
+        "alias" => TN::Alias,
+        "struct" => TN::Struct,
+        "enum" => TN::Enum,
+        "match" => TN::Match,
+        "int" => TN::Int,
+        "float" => TN::Float,
+        "string" => TN::String,
+        "bool" => TN::Bool,
+        "array" => TN::Array,
+        "return" => TN::Return,
+        "break" => TN::Break,
+        "continue" => TN::Continue,
+        "if" => TN::If,
+        "else" => TN::Else,
+        "while" => TN::While,
+        "for" => TN::For,
+        "in" => TN::In,
+        "nothing" => TN::Nothing,
+        "true" => TN::True,
+        "false" => TN::False,
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Length of the identifier (`[a-zA-Z_][a-zA-Z0-9_]*`) at the start of `text`, 0 if there is none.
+fn identifier_len(text: &str) -> usize {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return 0;
+    }
+    text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len())
+}
+
+fn digits_len(text: &str) -> usize {
+    text.find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len())
+}
+
+/// Scans an integer or a float (`12.5`) at the start of `text`. A fraction directly
+/// followed by a word character is not a float, so `1.5x` lexes as `1`, `.`, `5`, `x`.
+fn scan_number(text: &str) -> (TN, usize) {
+    let int_len = digits_len(text);
+    if let Some(fraction) = text[int_len..].strip_prefix('.') {
+        let end = int_len + 1 + digits_len(fraction);
+        if end > int_len + 1 && !text[end..].starts_with(is_word_char) {
+            return (TN::FloatLiteral, end);
+        }
+    }
+    (TN::Number, int_len)
+}
+
+/// `///` starts a doc comment, but four or more slashes are a plain line comment.
+fn is_doc_comment(text: &str) -> bool {
+    text.starts_with("///") && !text.starts_with("////")
+}
+
+/// Decodes the escape sequence at the start of `text`, which begins with a backslash.
+/// Returns the character and the number of bytes consumed, or an error message
+/// together with the width (in characters) of the offending sequence.
+fn decode_escape(text: &str) -> Result<(char, usize), (String, usize)> {
+    match text[1..].chars().next() {
+        Some('n') => Ok(('\n', 2)),
+        Some('t') => Ok(('\t', 2)),
+        Some('\\') => Ok(('\\', 2)),
+        Some('"') => Ok(('"', 2)),
+        Some('u') => {
+            let Some(body) = text[2..].strip_prefix('{') else {
+                return Err((
+                    "Invalid unicode escape: expected '{' after '\\u'".to_string(),
+                    2,
+                ));
+            };
+            let digits: String = body.chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+            let width = 3 + digits.len();
+            if !body[digits.len()..].starts_with('}') || digits.is_empty() || digits.len() > 6 {
+                return Err((
+                    "Invalid unicode escape: expected 1 to 6 hex digits followed by '}'"
+                        .to_string(),
+                    width,
+                ));
+            }
+            let code = u32::from_str_radix(&digits, 16).unwrap_or(u32::MAX);
+            match char::from_u32(code) {
+                Some(ch) => Ok((ch, width + 1)),
+                None => Err((
+                    format!("Invalid unicode escape: '{digits}' is not a valid code point"),
+                    width + 1,
+                )),
+            }
+        }
+        Some(other) => Err((format!("Invalid escape sequence '\\{other}'"), 2)),
+        None => Err(("Invalid escape sequence at end of input".to_string(), 1)),
+    }
+}
+
+#[cfg(test)]
+mod regex_reference;
@@ -0,0 +1,153 @@
+//! The regex-based lexer the scanner replaced, kept as a reference for the tests: both must
+//! produce the same tokens, and the lexer benchmark times one against the other.
+
+use regex::Regex;
+
+use super::Lexer;
+use crate::error::{ErrorPhase, FlavorError};
+use crate::error_codes::ErrorCode;
+use crate::types::{Span, Token, TokenName as TN};
+
+/// Token patterns tried in order at the current position. The first one matching there wins.
+const PATTERNS: [(&str, TN); 61] = [
+    (r"print\b", TN::Print),
+    (r"let\b", TN::Let),
+    (r"fn\b", TN::Fn),
+    (r"alias\b", TN::Alias),
+    (r"struct\b", TN::Struct),
+    (r"enum\b", TN::Enum),
+    (r"match\b", TN::Match),
+    (r"int\b", TN::Int),
+    (r"float\b", TN::Float),
+    (r"string\b", TN::String),
+    (r"bool\b", TN::Bool),
+    (r"array\b", TN::Array),
+    (r"return\b", TN::Return),
+    (r"break\b", TN::Break),
+    (r"continue\b", TN::Continue),
+    (r"if\b", TN::If),
+    (r"else\b", TN::Else),
+    (r"while\b", TN::While),
+    (r"for\b", TN::For),
+    (r"in\b", TN::In),
+    (r"nothing\b", TN::Nothing),
+    (r"true\b", TN::True),
+    (r"false\b", TN::False),
+    (r"\.\.=", TN::DotDotEq),
+    (r"\.\.", TN::DotDot),
+    (r"\.", TN::Dot),
+    (r",", TN::Comma),
+    (r"::", TN::DoubleColon),
+    (r":", TN::Colon),
+    (r";", TN::Semicolon),
+    (r"->", TN::SlimArrow),
+    (r"=>", TN::BoldArrow),
+    (r"==", TN::Eq),
+    (r"\!=", TN::NotEq),
+    (r"\=", TN::Assign),
+    (r"\!", TN::Not),
+    (r">=", TN::Ge),
+    (r"<=", TN::Le),
+    (r">", TN::Gt),
+    (r"<", TN::Lt),
+    (r"\+\+", TN::PlusPlus),
+    (r"--", TN::MinusMinus),
+    (r"\+", TN::Plus),
+    (r"-", TN::Minus),
+    (r"\*", TN::Times),
+    (r"///[^\n]*", TN::DocComment),
+    (r"/", TN::Div),
+    (r"%", TN::Percent),
+    (r"&&", TN::And),
+    (r"\|\|", TN::Or),
+    (r"\(", TN::LPar),
+    (r"\)", TN::RPar),
+    (r"\[", TN::LSqu),
+    (r"\]", TN::RSqu),
+    (r"\{", TN::LBra),
+    (r"\}", TN::RBra),
+    (r"\b\d+\.\d+\b", TN::FloatLiteral),
+    (r"[0-9]+", TN::Number),
+    (r"'[a-zA-Z_][a-zA-Z0-9_]*", TN::Label),
+    (r"[a-zA-Z_][a-zA-Z0-9_]*", TN::Identifier),
+    (r"[\s\S]*", TN::Unknown),
+];
+
+impl Lexer {
+    /// Lexes the source like `lexe`, matching each token with the regexes of the old lexer.
+    /// Like it, compiles the patterns for every token and searches the rest of the source
+    /// with them, so its running time grows with the square of the length of the source.
+    pub(crate) fn lexe_with_regexes(&mut self) -> Result<(), FlavorError> {
+        loop {
+            let tok = self.next_token_with_regexes()?;
+
+            self.tokens.push(tok.clone());
+            if tok.tok_name == TN::Eof {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_token_with_regexes(&mut self) -> Result<Token, FlavorError> {
+        self.skip_whitespace_with_regex();
+        self.skip_trivia()?;
+
+        if self.pos >= self.source.len() {
+            return Ok(Token {
+                tok_name: TN::Eof,
+                lexeme: "\0".to_string(),
+                span: Span::point(self.line, self.column),
+                value: None,
+            });
+        }
+
+        if let Some(token) = self.lex_string()? {
+            return Ok(token);
+        }
+
+        let mut token_name = TN::Unknown;
+        let mut lexeme = String::new();
+
+        for (pattern, name) in PATTERNS.iter() {
+            if let Some(matched) = self.match_start(pattern) {
+                token_name = name.clone();
+                lexeme = matched.to_string();
+                break;
+            }
+        }
+
+        if token_name == TN::Unknown {
+            let span = self.advance_with(&lexeme);
+            return Err(FlavorError::with_span(
+                ErrorPhase::Lexing,
+                format!("Unknown token: {lexeme}"),
+                span,
+            )
+            .with_code(ErrorCode::UnknownToken));
+        };
+
+        let span = self.advance_with(&lexeme);
+
+        Ok(Token {
+            tok_name: token_name,
+            lexeme,
+            span,
+            value: None,
+        })
+    }
+
+    fn match_start(&self, pattern: &str) -> Option<&str> {
+        let re = Regex::new(pattern).unwrap();
+        re.find(self.remaining_source())
+            .filter(|mat| mat.start() == 0)
+            .map(|mat| mat.as_str())
+    }
+
+    fn skip_whitespace_with_regex(&mut self) {
+        let re = Regex::new(r"^\s+").unwrap();
+        while let Some(m) = re.find(self.remaining_source()) {
+            self.consume_n_char(m.end());
+        }
+    }
+}
@@ -0,0 +1,2436 @@
+//! Flavor is a small statically typed language. This crate holds its whole pipeline: the
+//! `Lexer`, the `Parser`, the `TypeChecker`, the lints, the tree-walking `Interpreter` and the
+//! bytecode `Vm`. `Engine` ties them together for programs embedding Flavor.
+
+mod builtins;
+mod bytecode;
+mod compiler;
+pub mod engine;
+pub mod error;
+pub mod error_codes;
+mod gc;
+pub mod interpreter;
+pub mod json;
+pub mod lexer;
+pub mod lint;
+mod operations;
+pub mod parser;
+pub mod repl;
+mod resolver;
+pub mod typechecker;
+pub mod types;
+pub mod vm;
+
+pub use engine::{Backend, Engine};
+pub use error::FlavorError;
+pub use interpreter::{EvalOutcome, EvaluationType, Interpreter};
+pub use lexer::Lexer;
+pub use parser::Parser;
+pub use typechecker::TypeChecker;
+pub use vm::Vm;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorPhase, Severity};
+    use crate::error_codes::ErrorCode;
+    use crate::json::Json;
+    use crate::lint::Linter;
+    use crate::repl::{self, Repl};
+    use crate::types::{ASTNode, Pattern, Span, Token, TokenName as TN, Type};
+    use std::rc::Rc;
+
+    fn lex_source(source: &str) -> Result<Vec<Token>, FlavorError> {
+        let mut lexer = Lexer::new(source);
+        lexer.lexe()?;
+        Ok(lexer.tokens)
+    }
+
+    fn token_names(source: &str) -> Vec<TN> {
+        lex_source(source)
+            .map(|tokens| {
+                tokens
+                    .into_iter()
+                    .map(|token| token.tok_name)
+                    .collect::<Vec<_>>()
+            })
+            .expect("lexing failed during test setup")
+    }
+
+    fn parse_source(source: &str) -> Result<Vec<ASTNode>, FlavorError> {
+        let tokens = lex_source(source)?;
+        let mut parser = Parser::new(tokens);
+        let (nodes, mut errors) = parser.parse_program();
+        if errors.is_empty() {
+            Ok(nodes)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    fn type_check_nodes(nodes: &[ASTNode]) -> Result<(), FlavorError> {
+        let mut checker = TypeChecker::new();
+        checker
+            .check_program(nodes)
+            .map_err(|mut errors| errors.remove(0))
+    }
+
+    fn compile_source(source: &str) -> Result<Vec<ASTNode>, FlavorError> {
+        let nodes = parse_source(source)?;
+        type_check_nodes(&nodes)?;
+        Ok(nodes)
+    }
+
+    fn evaluate_source(source: &str) -> Result<EvaluationType, FlavorError> {
+        let nodes = compile_source(source)?;
+        let mut interpreter = Interpreter::new();
+        match interpreter.eval_program(&nodes)? {
+            EvalOutcome::Value(value) => Ok(value),
+            EvalOutcome::Return(value) => Ok(value),
+            EvalOutcome::Break(_) | EvalOutcome::Continue(_) => Err(FlavorError::new(
+                ErrorPhase::Runtime,
+                "Top-level break or continue is not allowed",
+                None,
+            )),
+        }
+    }
+
+    #[test]
+    fn lexer_recognizes_keywords_and_symbols() {
+        let names = token_names(
+            "let score = 10; if score >= 5 && score < 20 { print score; } else { score = score - 1; } let x = 40.574;",
+        );
+        assert_eq!(
+            names,
+            vec![
+                TN::Let,
+                TN::Identifier,
+                TN::Assign,
+                TN::Number,
+                TN::Semicolon,
+                TN::If,
+                TN::Identifier,
+                TN::Ge,
+                TN::Number,
+                TN::And,
+                TN::Identifier,
+                TN::Lt,
+                TN::Number,
+                TN::LBra,
+                TN::Print,
+                TN::Identifier,
+                TN::Semicolon,
+                TN::RBra,
+                TN::Else,
+                TN::LBra,
+                TN::Identifier,
+                TN::Assign,
+                TN::Identifier,
+                TN::Minus,
+                TN::Number,
+                TN::Semicolon,
+                TN::RBra,
+                TN::Let,
+                TN::Identifier,
+                TN::Assign,
+                TN::FloatLiteral,
+                TN::Semicolon,
+                TN::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_handles_literals_and_delimiters() {
+        let names = token_names(
+            r#"alias => value; fn f() -> [string] { print "hi", data[0]; return nothing; }"#,
+        );
+        assert_eq!(
+            names,
+            vec![
+                TN::Alias,
+                TN::BoldArrow,
+                TN::Identifier,
+                TN::Semicolon,
+                TN::Fn,
+                TN::Identifier,
+                TN::LPar,
+                TN::RPar,
+                TN::SlimArrow,
+                TN::LSqu,
+                TN::String,
+                TN::RSqu,
+                TN::LBra,
+                TN::Print,
+                TN::StringLiteral,
+                TN::Comma,
+                TN::Identifier,
+                TN::LSqu,
+                TN::Number,
+                TN::RSqu,
+                TN::Semicolon,
+                TN::Return,
+                TN::Nothing,
+                TN::Semicolon,
+                TN::RBra,
+                TN::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_skips_comments_and_keeps_doc_comments() {
+        let source = r#"
+/// Doubles a value
+fn double(x: int) -> int { // trailing note
+    /* outer /* nested */ still comment */
+    return x * 2; //// not a doc comment
+}
+"#;
+        let tokens = lex_source(source).expect("comments should lex");
+        let names: Vec<TN> = tokens.iter().map(|token| token.tok_name.clone()).collect();
+        assert_eq!(names.first(), Some(&TN::DocComment));
+        assert_eq!(tokens[0].lexeme, "/// Doubles a value");
+        assert_eq!(
+            names.iter().filter(|name| **name == TN::DocComment).count(),
+            1
+        );
+        assert!(!names.contains(&TN::Div));
+
+        let nodes = parse_source(source).expect("doc comments should not reach the parser");
+        assert!(matches!(nodes[0], ASTNode::FunctionDeclaration { .. }));
+    }
+
+    #[test]
+    fn lexer_reports_unterminated_block_comment() {
+        let err = lex_source("let x = 1;\n  /* open /* nested */\nprint x;")
+            .expect_err("unterminated comment should fail");
+        assert!(matches!(err.phase, ErrorPhase::Lexing));
+        assert!(err.message.contains("Unterminated comment"));
+        let span = err
+            .span
+            .expect("error should point at the opening delimiter");
+        assert_eq!(
+            (span.start_line, span.start_column, span.end_column),
+            (2, 3, 4)
+        );
+    }
+
+    #[test]
+    fn lexer_decodes_string_literals() {
+        let tokens = lex_source(
+            "\"a\\tb \\\"q\\\" \\\\ \\u{e9}\" r\"raw\\n\" \"\"\"two\nlines \"q\" end\"\"\" \"\"",
+        )
+        .expect("strings should lex");
+        let values: Vec<Option<&str>> = tokens.iter().map(|token| token.value.as_deref()).collect();
+        assert_eq!(
+            values,
+            vec![
+                Some("a\tb \"q\" \\ é"),
+                Some("raw\\n"),
+                Some("two\nlines \"q\" end"),
+                Some(""),
+                None,
+            ]
+        );
+        assert_eq!(tokens[0].lexeme, "\"a\\tb \\\"q\\\" \\\\ \\u{e9}\"");
+        assert_eq!(tokens[2].span.end_line, 2);
+    }
+
+    #[test]
+    fn lexer_reports_invalid_string_literals() {
+        let cases = [
+            (
+                "print \"bad \\q\";",
+                "Invalid escape sequence '\\q'",
+                (1, 12, 13),
+            ),
+            (
+                "print \"\\u{110000}\";",
+                "not a valid code point",
+                (1, 8, 17),
+            ),
+            ("print \"\\u{zz}\";", "Invalid unicode escape", (1, 8, 10)),
+            (
+                "let s = \"open;\nprint s;",
+                "Unterminated string literal",
+                (1, 9, 9),
+            ),
+            ("print \"\"\"open", "Unterminated string literal", (1, 7, 9)),
+        ];
+        for (source, expected, (line, start, end)) in cases {
+            let err = lex_source(source).expect_err("invalid string should fail to lex");
+            assert!(matches!(err.phase, ErrorPhase::Lexing));
+            assert!(
+                err.message.contains(expected),
+                "unexpected error message for `{source}`: {}",
+                err.message
+            );
+            let span = err.span.expect("string errors should carry a span");
+            assert_eq!(
+                (span.start_line, span.start_column, span.end_column),
+                (line, start, end),
+                "unexpected span for `{source}`"
+            );
+        }
+    }
+
+    #[test]
+    fn lexer_reports_unknown_character() {
+        let err = lex_source("let x = 1;\nlet y = x # 2;").expect_err("'#' is not a token");
+        assert!(matches!(err.phase, ErrorPhase::Lexing));
+        assert_eq!(err.message, "Unknown token: #");
+        let span = err.span.expect("unknown token should carry a span");
+        assert_eq!(
+            (span.start_line, span.start_column, span.end_column),
+            (2, 11, 11)
+        );
+    }
+
+    /// `test_files/mega.tokens` was written by the regex-based lexer the scanner replaced, one
+    /// token per line.
+    #[test]
+    fn lexer_produces_the_recorded_tokens_for_mega() {
+        let tokens =
+            lex_source(include_str!("../test_files/mega.flv")).expect("mega.flv should lex");
+        let dump: String = tokens
+            .iter()
+            .map(|token| {
+                let span = token.span;
+                let value = token
+                    .value
+                    .as_ref()
+                    .map(|value| format!(" {value:?}"))
+                    .unwrap_or_default();
+                format!(
+                    "{}:{}-{}:{} {:?} {:?}{value}\n",
+                    span.start_line,
+                    span.start_column,
+                    span.end_line,
+                    span.end_column,
+                    token.tok_name,
+                    token.lexeme
+                )
+            })
+            .collect();
+        assert_eq!(dump, include_str!("../test_files/mega.tokens"));
+    }
+
+    /// Lexing benchmark over `test_files/mega.flv` repeated many times, timing the scanner
+    /// against the regex-based lexer it replaced on the same source.
+    /// Run with `cargo test --release lexer_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn lexer_benchmark_scaled_mega() {
+        let mega = std::fs::read_to_string("test_files/mega.flv").expect("mega.flv should exist");
+        let copies = 10;
+        let source = mega.repeat(copies);
+
+        let start = std::time::Instant::now();
+        let tokens = lex_source(&source).expect("scaled mega.flv should lex");
+        let scanned = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut lexer = Lexer::new(&source);
+        lexer
+            .lexe_with_regexes()
+            .expect("scaled mega.flv should lex with regexes");
+        let matched = start.elapsed();
+
+        let ratio = matched.as_secs_f64() / scanned.as_secs_f64();
+        println!(
+            "lexed {} lines into {} tokens: scanner {scanned:?} ({:.0} tokens/s), \
+             regexes {matched:?} ({:.0} tokens/s), scanner speedup: {ratio:.0}x",
+            source.lines().count(),
+            tokens.len(),
+            tokens.len() as f64 / scanned.as_secs_f64(),
+            tokens.len() as f64 / matched.as_secs_f64()
+        );
+        assert_eq!(
+            tokens.len(),
+            (lex_source(&mega).unwrap().len() - 1) * copies + 1
+        );
+        assert_eq!(tokens, lexer.tokens);
+        assert!(ratio > 100.0, "the scanner is only {ratio:.0} times faster");
+    }
+
+    #[test]
+    fn parser_builds_function_declaration_ast() {
+        let source = r#"
+fn add(a: int, b: int) -> int {
+    let tmp: int = a + b;
+    return tmp;
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse function declaration");
+        assert_eq!(nodes.len(), 1);
+        match &nodes[0] {
+            ASTNode::FunctionDeclaration {
+                name,
+                parameters,
+                return_type,
+                body,
+                ..
+            } => {
+                assert_eq!(name, "add");
+                assert_eq!(parameters.len(), 2);
+                let parameters: Vec<(&str, &Type)> = parameters
+                    .iter()
+                    .map(|parameter| (parameter.name.as_str(), &parameter.ty))
+                    .collect();
+                assert_eq!(parameters, vec![("a", &Type::Int), ("b", &Type::Int)]);
+                assert_eq!(*return_type, Type::Int);
+                match body.as_ref() {
+                    ASTNode::Body {
+                        nodes: body_nodes, ..
+                    } => {
+                        assert_eq!(body_nodes.len(), 2);
+                        assert!(matches!(body_nodes[0], ASTNode::LetDeclaration { .. }));
+                        assert!(matches!(body_nodes[1], ASTNode::Return { .. }));
+                    }
+                    other => panic!("expected function body, found {other:?}"),
+                }
+            }
+            other => panic!("expected function declaration, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parser_handles_if_else_blocks() {
+        let source = r#"
+if true {
+    print 1;
+} else {
+    while false {
+        break;
+    }
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse conditional");
+        assert_eq!(nodes.len(), 1);
+        match &nodes[0] {
+            ASTNode::If {
+                guard,
+                then_body,
+                else_body,
+                ..
+            } => {
+                assert!(
+                    matches!(guard.as_ref(), ASTNode::BoolLiteral { value, .. } if value == "true")
+                );
+                match then_body.as_ref() {
+                    ASTNode::Body {
+                        nodes: then_nodes, ..
+                    } => {
+                        assert_eq!(then_nodes.len(), 1);
+                        assert!(matches!(then_nodes[0], ASTNode::Print { .. }));
+                    }
+                    other => panic!("expected then body, found {other:?}"),
+                }
+                let else_body = else_body
+                    .as_ref()
+                    .expect("else branch should have been parsed");
+                match else_body.as_ref() {
+                    ASTNode::Body {
+                        nodes: else_nodes, ..
+                    } => {
+                        assert_eq!(else_nodes.len(), 1);
+                        assert!(matches!(else_nodes[0], ASTNode::While { .. }));
+                    }
+                    other => panic!("expected else body, found {other:?}"),
+                }
+            }
+            other => panic!("expected if statement, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parser_builds_alias_declaration() {
+        let nodes = parse_source("alias Grid = [[int]];").expect("failed to parse alias");
+        assert_eq!(nodes.len(), 1);
+        match &nodes[0] {
+            ASTNode::AliasDeclaration {
+                name, aliased_type, ..
+            } => {
+                assert_eq!(name, "Grid");
+                assert_eq!(
+                    *aliased_type,
+                    Type::Array(Box::new(Type::Array(Box::new(Type::Int))))
+                );
+            }
+            other => panic!("expected alias declaration, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn typechecker_resolves_nested_aliases() {
+        let source = r#"
+alias Grid = [Row];
+alias Row = [Cell];
+alias Cell = int;
+alias Reducer = (Grid) -> Cell;
+fn first(grid: Grid) -> Cell {
+    return grid[0][0];
+}
+let reduce: Reducer = first;
+let plain: [[int]] = [[1, 2], [3, 4]];
+let value: int = reduce(plain);
+"#;
+        let nodes = parse_source(source).expect("failed to parse aliases");
+        type_check_nodes(&nodes).expect("aliases should resolve to their underlying types");
+    }
+
+    #[test]
+    fn typechecker_rejects_unknown_type_names() {
+        let nodes = parse_source("let x: Missing = 1;").expect("failed to parse unknown type");
+        let err = type_check_nodes(&nodes).expect_err("unknown types should be rejected");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("Unknown type 'Missing'"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_detects_recursive_aliases() {
+        let source = r#"
+alias A = [B];
+alias B = (int) -> A;
+"#;
+        let nodes = parse_source(source).expect("failed to parse recursive aliases");
+        let err = type_check_nodes(&nodes).expect_err("alias cycles should be rejected");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("A -> B -> A"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_reports_each_alias_cycle_once() {
+        let errors = type_check_all("alias A = A;\nalias B = [B];\nlet a: A = 1;\nlet b: B = [];");
+        let messages: Vec<&str> = errors.iter().map(|err| err.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Recursive type definition detected: A -> A",
+                "Recursive type definition detected: B -> B",
+            ]
+        );
+    }
+
+    #[test]
+    fn structs_and_enums_can_refer_to_themselves() {
+        let source = r#"
+struct Node { v: int, kids: [Node] }
+enum List { Cons(int, [List]), Nil }
+fn total(node: Node) -> int {
+    let sum = node.v;
+    for kid in node.kids {
+        sum = sum + total(kid);
+    }
+    return sum;
+}
+fn length(list: List) -> int {
+    return match list {
+        List::Cons(_, rest) => 1 + length(rest[0]),
+        List::Nil => 0,
+    };
+}
+let leaf = Node { v: 2, kids: [] };
+let root = Node { v: 1, kids: [leaf, Node { v: 3, kids: [] }] };
+root.kids[0].kids = [Node { v: 10, kids: [] }];
+print total(root), " ", root.kids[1].v, " ", root.kids[0] == leaf;
+print length(List::Cons(1, [List::Cons(2, [List::Nil])]));
+"#;
+        for output in run_on_both(source) {
+            assert_eq!(output.expect("program should run"), "16 3 false\n2\n");
+        }
+    }
+
+    #[test]
+    fn interpreter_accepts_values_of_aliased_types() {
+        let source = r#"
+alias Score = float;
+alias Scores = [Score];
+let scores: Scores = [1.5, 2.5];
+let best: Score = scores[1];
+best;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Float(value) => assert_eq!(value, 2.5),
+            other => panic!("expected float result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parser_builds_struct_declaration_and_field_access() {
+        let source = r#"
+struct Point { x: int, y: int }
+let p = Point { x: 1, y: 2 };
+p.x = p.y;
+"#;
+        let nodes = parse_source(source).expect("failed to parse struct program");
+        assert_eq!(nodes.len(), 3);
+        match &nodes[0] {
+            ASTNode::StructDeclaration { name, fields, .. } => {
+                assert_eq!(name, "Point");
+                assert_eq!(
+                    *fields,
+                    vec![("x".to_string(), Type::Int), ("y".to_string(), Type::Int)]
+                );
+            }
+            other => panic!("expected struct declaration, found {other:?}"),
+        }
+        match &nodes[1] {
+            ASTNode::LetDeclaration { expr, .. } => {
+                assert!(
+                    matches!(expr.as_ref(), ASTNode::StructLiteral { name, fields, .. } if name == "Point" && fields.len() == 2)
+                );
+            }
+            other => panic!("expected let declaration, found {other:?}"),
+        }
+        match &nodes[2] {
+            ASTNode::ExpressionStatement { expr, .. } => match expr.as_ref() {
+                ASTNode::BinaryExpression { left, right, .. } => {
+                    assert!(
+                        matches!(left.as_ref(), ASTNode::FieldAccess { field, .. } if field == "x")
+                    );
+                    assert!(
+                        matches!(right.as_ref(), ASTNode::FieldAccess { field, .. } if field == "y")
+                    );
+                }
+                other => panic!("expected assignment, found {other:?}"),
+            },
+            other => panic!("expected expression statement, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn typechecker_rejects_struct_field_errors() {
+        let cases = [
+            (
+                "struct P { x: int } let p = P { x: true };",
+                "Field type mismatch",
+            ),
+            (
+                "struct P { x: int, y: int } let p = P { x: 1 };",
+                "Missing fields",
+            ),
+            (
+                "struct P { x: int } let p = P { x: 1, z: 2 };",
+                "no field named 'z'",
+            ),
+            (
+                "struct P { x: int } let p = P { x: 1 }; p.z;",
+                "no field named 'z'",
+            ),
+            ("let n = 1; n.x;", "non-struct type"),
+        ];
+        for (source, expected) in cases {
+            let nodes = parse_source(source).expect("failed to parse struct source");
+            let err = type_check_nodes(&nodes).expect_err("struct misuse should be rejected");
+            assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+            assert!(
+                err.message.contains(expected),
+                "unexpected error message for `{source}`: {}",
+                err.message
+            );
+        }
+    }
+
+    #[test]
+    fn interpreter_reads_and_writes_struct_fields() {
+        let source = r#"
+struct Point { x: int, y: int }
+struct Segment { from: Point, to: Point }
+let seg = Segment { to: Point { x: 10, y: 20 }, from: Point { x: 1, y: 2 } };
+seg.from.x = 5;
+seg.to.y++;
+let points: [Point] = [seg.from, seg.to];
+points[1].x--;
+if seg.from.x == 5 {
+    seg.from.y = points[1].x + points[1].y;
+}
+seg.from.y;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 30),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parser_builds_enum_declaration_and_match() {
+        let source = r#"
+enum Shape { Circle(float), Rect(float, float), Empty }
+match Shape::Circle(1.0) {
+    Shape::Circle(r) => r,
+    Shape::Rect(w, _) => { print w; }
+    _ => 0.0,
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse enum program");
+        assert_eq!(nodes.len(), 2);
+        match &nodes[0] {
+            ASTNode::EnumDeclaration { name, variants, .. } => {
+                assert_eq!(name, "Shape");
+                assert_eq!(
+                    *variants,
+                    vec![
+                        ("Circle".to_string(), vec![Type::Float]),
+                        ("Rect".to_string(), vec![Type::Float, Type::Float]),
+                        ("Empty".to_string(), vec![]),
+                    ]
+                );
+            }
+            other => panic!("expected enum declaration, found {other:?}"),
+        }
+        match &nodes[1] {
+            ASTNode::Match {
+                scrutinee, arms, ..
+            } => {
+                assert!(matches!(
+                    scrutinee.as_ref(),
+                    ASTNode::EnumVariant { variant, arguments, .. } if variant == "Circle" && arguments.len() == 1
+                ));
+                assert_eq!(arms.len(), 3);
+                assert!(matches!(
+                    &arms[1].pattern,
+                    Pattern::Variant { variant, bindings, .. } if variant == "Rect" && bindings == &["w", "_"]
+                ));
+                assert!(matches!(arms[1].body, ASTNode::Body { .. }));
+                assert!(matches!(arms[2].pattern, Pattern::Wildcard { .. }));
+            }
+            other => panic!("expected match, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn typechecker_reports_non_exhaustive_match() {
+        let source = r#"
+enum Light { Red, Yellow, Green }
+let light = Light::Red;
+let wait: int = match light {
+    Light::Red => 30,
+    Light::Yellow => 5,
+};
+"#;
+        let nodes = parse_source(source).expect("failed to parse match");
+        let err = type_check_nodes(&nodes).expect_err("missing variants should be reported");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("Non-exhaustive") && err.message.contains("Light::Green"),
+            "unexpected error message: {}",
+            err.message
+        );
+        let span = err.span.expect("exhaustiveness errors should carry a span");
+        assert_eq!((span.start_line, span.start_column), (4, 23));
+        assert_eq!(err.labels.len(), 1);
+        assert_eq!(err.labels[0].message, "enum 'Light' declared here");
+        assert_eq!(
+            (
+                err.labels[0].span.start_line,
+                err.labels[0].span.start_column
+            ),
+            (2, 1)
+        );
+    }
+
+    #[test]
+    fn typechecker_rejects_mismatched_variant_payloads() {
+        let cases = [
+            (
+                "enum E { A(int) } let e = E::A(true);",
+                "payload type mismatch",
+            ),
+            ("enum E { A(int) } let e = E::A;", "expects 1 value(s)"),
+            ("enum E { A } let e = E::B;", "no variant named 'B'"),
+            (
+                "enum E { A(int) } match E::A(1) { E::A(x, y) => 1, }",
+                "binds 2 value(s)",
+            ),
+            (
+                "enum E { A } enum F { A } match E::A { F::A => 1, }",
+                "cannot match",
+            ),
+            (
+                "enum E { A, B } let n: int = match E::A { E::A => 1, E::B => true, };",
+                "different types",
+            ),
+        ];
+        for (source, expected) in cases {
+            let nodes = parse_source(source).expect("failed to parse enum source");
+            let err = type_check_nodes(&nodes).expect_err("enum misuse should be rejected");
+            assert!(
+                err.message.contains(expected),
+                "unexpected error message for `{source}`: {}",
+                err.message
+            );
+        }
+    }
+
+    #[test]
+    fn interpreter_matches_enum_variants_and_binds_payloads() {
+        let source = r#"
+enum Shape { Circle(int), Rect(int, int), Empty }
+fn area(shape: Shape) -> int {
+    match shape {
+        Shape::Circle(r) => {
+            return 3 * r * r;
+        }
+        Shape::Rect(w, h) => {
+            return w * h;
+        }
+        Shape::Empty => {
+            return 0;
+        }
+    }
+}
+let shapes: [Shape] = [Shape::Rect(2, 5), Shape::Circle(2), Shape::Empty];
+let bonus = match shapes[2] {
+    Shape::Empty => 100,
+    other => area(other),
+};
+area(shapes[0]) + area(shapes[1]) + area(shapes[2]) + bonus;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 122),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parser_builds_for_loops_over_arrays_and_ranges() {
+        let source = r#"
+for value in values { print value; }
+for i in 0..n + 1 { print i; }
+for i in 1..=10 { break; }
+"#;
+        let nodes = parse_source(source).expect("failed to parse for loops");
+        assert_eq!(nodes.len(), 3);
+        assert!(matches!(
+            &nodes[0],
+            ASTNode::For { variable, iterable, .. }
+                if variable == "value" && matches!(iterable.as_ref(), ASTNode::Identifier { .. })
+        ));
+        match &nodes[1] {
+            ASTNode::For { iterable, .. } => match iterable.as_ref() {
+                ASTNode::Range { end, inclusive, .. } => {
+                    assert!(!inclusive);
+                    assert!(matches!(end.as_ref(), ASTNode::BinaryExpression { .. }));
+                }
+                other => panic!("expected range, found {other:?}"),
+            },
+            other => panic!("expected for loop, found {other:?}"),
+        }
+        assert!(matches!(
+            &nodes[2],
+            ASTNode::For { iterable, .. }
+                if matches!(iterable.as_ref(), ASTNode::Range { inclusive: true, .. })
+        ));
+    }
+
+    #[test]
+    fn typechecker_infers_for_loop_variable_types() {
+        let source = r#"
+let matrix: [[int]] = [[1, 2], [3, 4]];
+let total: int = 0;
+for row in matrix {
+    for value in row {
+        total = total + value;
+    }
+}
+for i in 0..total {
+    if i > 3 {
+        break;
+    }
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse for loops");
+        type_check_nodes(&nodes).expect("for loops should type check");
+
+        let cases = [
+            ("for c in 5 { print c; }", "iterate over arrays or ranges"),
+            ("for i in 0..true { print i; }", "Range bounds"),
+            ("for s in [\"a\"] { let n: int = s; }", "Type mismatch"),
+            (
+                "for i in 0..1 { print i; } print i;",
+                "undeclared identifier 'i'",
+            ),
+        ];
+        for (source, expected) in cases {
+            let nodes = parse_source(source).expect("failed to parse for loop");
+            let err = type_check_nodes(&nodes).expect_err("for loop misuse should be rejected");
+            assert!(
+                err.message.contains(expected),
+                "unexpected error message for `{source}`: {}",
+                err.message
+            );
+        }
+    }
+
+    #[test]
+    fn interpreter_runs_for_loops_with_fresh_scope_per_iteration() {
+        let source = r#"
+let total: int = 0;
+for value in [1, 2, 3] {
+    total = total + value;
+}
+for i in 0..10 {
+    if i == 3 {
+        break;
+    }
+    total = total + 10;
+}
+for i in 1..=3 {
+    total = total + i * 100;
+}
+let remembered = <> -> int { return 0; };
+for i in 0..3 {
+    if i == 1 {
+        remembered = <> -> int { return i; };
+    }
+}
+total + remembered() * 1000;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 1636),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parser_handles_labels_break_and_continue() {
+        let source = r#"
+'outer: for i in 0..3 {
+    while true {
+        continue 'outer;
+    }
+    break;
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse labeled loops");
+        match &nodes[0] {
+            ASTNode::For { label, body, .. } => {
+                assert_eq!(label.as_deref(), Some("outer"));
+                let ASTNode::Body { nodes: inner, .. } = body.as_ref() else {
+                    panic!("expected loop body, found {body:?}");
+                };
+                match &inner[0] {
+                    ASTNode::While { label, body, .. } => {
+                        assert!(label.is_none());
+                        assert!(matches!(
+                            body.as_ref(),
+                            ASTNode::Body { nodes, .. }
+                                if matches!(&nodes[0], ASTNode::Continue { label: Some(l), .. } if l == "outer")
+                        ));
+                    }
+                    other => panic!("expected while loop, found {other:?}"),
+                }
+                assert!(matches!(inner[1], ASTNode::Break { label: None, .. }));
+            }
+            other => panic!("expected labeled for loop, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn typechecker_validates_loop_jumps() {
+        let cases = [
+            ("continue;", "Continue statement not within a loop"),
+            (
+                "while true { break 'missing; }",
+                "Use of undeclared loop label 'missing'",
+            ),
+            (
+                "'a: while true { 'a: while true { break; } }",
+                "Loop label 'a' shadows the label of an enclosing loop",
+            ),
+            (
+                "'a: while true { fn f() -> nothing { break 'a; } }",
+                "Break statement not within a loop",
+            ),
+        ];
+        for (source, expected) in cases {
+            let nodes = parse_source(source).expect("failed to parse loop jump");
+            let err = type_check_nodes(&nodes).expect_err("invalid jump should be rejected");
+            assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+            assert!(err.span.is_some(), "loop jump errors should carry a span");
+            assert_eq!(err.message, expected, "unexpected error for `{source}`");
+        }
+    }
+
+    #[test]
+    fn interpreter_handles_continue_and_labeled_jumps() {
+        let source = r#"
+let total: int = 0;
+for i in 0..10 {
+    if i % 2 == 0 {
+        continue;
+    }
+    total = total + i;
+}
+let pairs: int = 0;
+'rows: for row in 0..5 {
+    let col: int = 0;
+    while true {
+        col++;
+        if col > row {
+            continue 'rows;
+        }
+        if row == 4 {
+            break 'rows;
+        }
+        pairs++;
+    }
+}
+total * 100 + pairs;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 2506),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn typechecker_validates_builtin_calls() {
+        let valid = r#"
+let xs: [int] = [1, 2];
+let grid: [[float]] = [[1.0]];
+push(xs, 3);
+push(grid[0], 2.0);
+insert(xs, 0, 0);
+let last: int = pop(xs);
+let first: float = remove(grid[0], 0);
+let size: int = len(xs) + len("abc");
+let grown = [];
+push(grown, 1);
+insert(grown, 0, 2);
+let total: int = grown[0] + grown[1];
+"#;
+        let nodes = parse_source(valid).expect("failed to parse builtin calls");
+        type_check_nodes(&nodes).expect("builtin calls should type check");
+
+        let cases = [
+            (
+                "let xs: [int] = [1]; push(xs, true);",
+                "'push' expects a value of type Int",
+            ),
+            ("let xs: [int] = [1]; pop(xs, 1);", "argument count"),
+            ("let n: int = 3; pop(n);", "expects an array"),
+            ("print len(true);", "'len' expects an array or a string"),
+            ("push([1], 2);", "First argument of 'push'"),
+            ("let f = len;", "can only be called directly"),
+            (
+                "let a = [];\nlet b = a;\npush(b, 1);",
+                "declare the element type of an empty array, e.g. `let values: [int] = [];`",
+            ),
+        ];
+        for (source, expected) in cases {
+            let nodes = parse_source(source).expect("failed to parse builtin call");
+            let err =
+                type_check_nodes(&nodes).expect_err("invalid builtin call should be rejected");
+            assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+            assert!(
+                err.message.contains(expected),
+                "unexpected error message for `{source}`: {}",
+                err.message
+            );
+        }
+
+        let errors = type_check_all("let xs: [int] = [];\npush(xs, missing);");
+        assert_eq!(errors.len(), 1, "unexpected errors: {errors:?}");
+    }
+
+    #[test]
+    fn interpreter_mutates_arrays_through_builtins() {
+        let source = r#"
+struct Bag { items: [int] }
+let bag: Bag = Bag { items: [2, 3] };
+push(bag.items, 4);
+insert(bag.items, 0, 1);
+let removed: int = remove(bag.items, 1);
+let popped: int = pop(bag.items);
+bag.items[0] * 1000 + removed * 100 + popped * 10 + len(bag.items);
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 1242),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_reports_builtin_runtime_errors() {
+        let cases = [
+            ("let xs: [int] = []; pop(xs);", "empty array"),
+            (
+                "let xs: [int] = [1]; remove(xs, 1);",
+                "Index 1 out of bounds for remove",
+            ),
+            (
+                "let xs: [int] = [1]; insert(xs, 2, 5);",
+                "Index 2 out of bounds for insert",
+            ),
+            ("let xs: [int] = [1]; remove(xs, 0 - 1);", "Negative"),
+        ];
+        for (source, expected) in cases {
+            let err = evaluate_source(source).expect_err("runtime error expected");
+            assert!(matches!(err.phase, ErrorPhase::Runtime));
+            assert!(err.span.is_some(), "builtin errors should carry a span");
+            assert!(
+                err.message.contains(expected),
+                "unexpected error message for `{source}`: {}",
+                err.message
+            );
+        }
+    }
+
+    fn parse_recovering(source: &str) -> (Vec<ASTNode>, Vec<FlavorError>) {
+        let tokens = lex_source(source).expect("lexing failed during test setup");
+        Parser::new(tokens).parse_program()
+    }
+
+    #[test]
+    fn parser_recovers_and_reports_every_syntax_error() {
+        let source = r#"
+let a: int = ;
+let b: int = 2;
+fn f(x: int) -> int {
+    let y = x + ;
+    return x;
+}
+if b == { print b; } else { print 0; }
+print b
+let c = 3;
+"#;
+        let (nodes, errors) = parse_recovering(source);
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|err| err.span.expect("syntax errors carry a span").start_line)
+            .collect();
+        assert_eq!(lines, vec![2, 5, 8, 10]);
+        assert!(
+            errors
+                .iter()
+                .all(|err| matches!(err.phase, ErrorPhase::Parsing))
+        );
+
+        // `let b`, `fn f` (missing its broken statement) and `let c` survive
+        assert_eq!(nodes.len(), 3);
+        match &nodes[1] {
+            ASTNode::FunctionDeclaration { body, .. } => match body.as_ref() {
+                ASTNode::Body { nodes, .. } => {
+                    assert_eq!(nodes.len(), 1);
+                    assert!(matches!(nodes[0], ASTNode::Return { .. }));
+                }
+                other => panic!("expected function body, found {other:?}"),
+            },
+            other => panic!("expected function declaration, found {other:?}"),
+        }
+        assert!(
+            matches!(&nodes[2], ASTNode::LetDeclaration { identifier, .. } if identifier == "c")
+        );
+    }
+
+    #[test]
+    fn parser_recovery_terminates_on_stray_and_missing_braces() {
+        let (nodes, errors) = parse_recovering("} let x = 1; }");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(nodes.len(), 1);
+
+        let (_, errors) = parse_recovering("fn f() -> int { return 1;");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("Eof"));
+
+        let (_, errors) = parse_recovering("match s {\n    Shape::Circle(r) => print r,\n}");
+        let positions: Vec<(usize, usize)> = errors
+            .iter()
+            .filter_map(|err| err.span)
+            .map(|span| (span.start_line, span.start_column))
+            .collect();
+        assert_eq!(positions, vec![(2, 25), (3, 1)]);
+    }
+
+    fn type_check_all(source: &str) -> Vec<FlavorError> {
+        let nodes = parse_source(source).expect("failed to parse program");
+        TypeChecker::new()
+            .check_program(&nodes)
+            .err()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn typechecker_reports_every_error_in_source_order() {
+        let source = r#"
+let a: int = true;
+fn f(x: int) -> int {
+    return "no";
+}
+if a { print a; }
+let xs: [int] = [1, "two"];
+struct P { x: int }
+let p: P = P { x: true, y: 1 };
+"#;
+        let errors = type_check_all(source);
+        let messages: Vec<&str> = errors.iter().map(|err| err.message.as_str()).collect();
+        assert_eq!(errors.len(), 6, "unexpected errors: {messages:#?}");
+        assert!(messages[0].contains("variable 'a' declared as Int"));
+        assert!(messages[1].contains("Return type mismatch"));
+        assert!(messages[2].contains("Guard in If"));
+        assert!(messages[3].contains("Array elements must be of the same type"));
+        assert!(messages[4].contains("'P.x' is Int"));
+        assert!(messages[5].contains("no field named 'y'"));
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|err| err.span.expect("type errors carry a span").start_line)
+            .collect();
+        assert!(lines.is_sorted(), "errors out of order: {lines:?}");
+    }
+
+    #[test]
+    fn typechecker_poison_type_suppresses_cascading_errors() {
+        let source = r#"
+let total: int = missing + 1;
+let doubled = total * 2;
+let broken: Unknown = 3;
+print broken.field, broken[0], -broken;
+let items: [int] = [1, 2];
+push(items, broken);
+fn g() -> int {
+    return undefined_call(doubled);
+}
+if broken == 1 { print g(); }
+"#;
+        let errors = type_check_all(source);
+        let messages: Vec<&str> = errors.iter().map(|err| err.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Use of undeclared identifier 'missing'",
+                "Unknown type 'Unknown'",
+                "Use of undeclared identifier 'undefined_call'",
+            ]
+        );
+    }
+
+    #[test]
+    fn typechecker_accepts_well_typed_program() {
+        let source = r#"
+fn add(a: int, b: int) -> int {
+    return a + b;
+}
+let answer: int = add(40, 2);
+print answer;
+"#;
+        let nodes = parse_source(source).expect("failed to parse program");
+        type_check_nodes(&nodes).expect("program should type check");
+    }
+
+    #[test]
+    fn typechecker_rejects_assignment_type_mismatch() {
+        let nodes =
+            parse_source("let x: int = true;").expect("failed to parse assignment mismatch source");
+        let err =
+            type_check_nodes(&nodes).expect_err("type checker should reject mismatched types");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("Type mismatch"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_flags_missing_return() {
+        let source = r#"
+fn bad(n: int) -> int {
+    if n > 0 {
+        return n;
+    }
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse missing return function");
+        let err = type_check_nodes(&nodes).expect_err("type checker should require returns");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("does not guarantee a return"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_detects_array_element_type_mismatch() {
+        let nodes = parse_source("let xs: [int] = [1, true];")
+            .expect("failed to parse array literal source");
+        let err = type_check_nodes(&nodes).expect_err("type checker should reject mixed arrays");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message
+                .contains("Array elements must be of the same type")
+                || err.message.contains("Array literal element type mismatch"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_requires_boolean_condition_in_while() {
+        let source = r#"
+let counter: int = 0;
+while counter {
+    break;
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse while loop");
+        let err = type_check_nodes(&nodes).expect_err("type checker should reject non-bool guard");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("While"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_rejects_non_boolean_if_guard() {
+        let source = r#"
+if 1 {
+    print 1;
+}
+"#;
+        let nodes = parse_source(source).expect("failed to parse if statement");
+        let err = type_check_nodes(&nodes).expect_err("type checker should reject non-bool guard");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("If"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_detects_function_argument_mismatch() {
+        let source = r#"
+fn add(a: int, b: int) -> int {
+    return a + b;
+}
+let value = add(1);
+"#;
+        let nodes = parse_source(source).expect("failed to parse mismatched call");
+        let err =
+            type_check_nodes(&nodes).expect_err("type checker should reject arity mismatches");
+        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
+        assert!(
+            err.message.contains("argument count"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn typechecker_accepts_matrix_manipulation() {
+        let source = r#"
+let matrix: [[int]] = [[1, 2], [3, 4]];
+matrix[0][1] = matrix[1][0];
+matrix[1][1]++;
+let value: int = matrix[0][1] + matrix[1][1];
+"#;
+        let nodes = parse_source(source).expect("failed to parse matrix manipulation source");
+        type_check_nodes(&nodes).expect("matrix program should type check");
+    }
+
+    #[test]
+    fn interpreter_evaluates_arithmetic_and_assignment() {
+        let source = r#"
+let x: int = 1;
+x = x + 41;
+x;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 42),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_supports_closures_and_calls() {
+        let source = r#"
+let base: int = 10;
+let make_adder = <offset: int> -> (int) -> int {
+    return <value: int> -> int {
+        return value + offset + base;
+    };
+};
+let add_five = make_adder(5);
+add_five(3);
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 18),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_handles_while_and_break() {
+        let source = r#"
+let total: int = 0;
+let current: int = 0;
+while true {
+    if current == 5 {
+        break;
+    }
+    total = total + current;
+    current++;
+}
+total;
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 10),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_performs_array_indexing_and_assignment() {
+        let source = r#"
+let values: [int] = [0, 1, 2];
+values[1] = 10;
+values[1];
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 10),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_reports_runtime_errors_for_out_of_bounds_access() {
+        let source = r#"
+let data: [int] = [0];
+data[5];
+"#;
+        let err = evaluate_source(source).expect_err("runtime error expected");
+        assert!(matches!(err.phase, ErrorPhase::Runtime));
+        assert!(
+            err.message.contains("out of bounds"),
+            "unexpected error message: {}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn interpreter_executes_recursive_function() {
+        let source = r#"
+fn factorial(n: int) -> int {
+    if n <= 1 {
+        return 1;
+    }
+    return n * factorial(n - 1);
+}
+factorial(5);
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 120),
+            other => panic!("expected integer result, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_manages_matrices_with_mixed_increments() {
+        let source = r#"
+let matrix: [[int]] = [[1, 2], [3, 4]];
+matrix[0][1] = matrix[1][0];
+matrix[1][1]++;
+++matrix[0][0];
+matrix[0][1] + matrix[1][1] + matrix[0][0];
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 10),
+            other => panic!("expected integer result after matrix manipulation, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interpreter_closure_clones_share_state() {
+        let source = r#"
+let make_tick = <start: int> -> () -> int {
+    let value: int = start;
+    return <> -> int {
+        ++value;
+        return value;
+    };
+};
+
+let ticker = make_tick(7);
+let twin = ticker;
+ticker();
+twin();
+ticker();
+"#;
+        match evaluate_source(source).expect("program should run") {
+            EvaluationType::Int(value) => assert_eq!(value, 10),
+            other => {
+                panic!("expected integer result showing shared closure state, found {other:?}")
+            }
+        }
+    }
+
+    fn lint_source(source: &str) -> Vec<FlavorError> {
+        let nodes = compile_source(source).expect("failed to compile program");
+        Linter::new().lint_program(&nodes)
+    }
+
+    #[test]
+    fn linter_warns_about_unused_and_shadowed_bindings() {
+        let source = r#"
+let unused: int = 1;
+let _ignored: int = 2;
+let x: int = 3;
+fn f(a: int, b: int) -> int {
+    let x: int = a;
+    return x;
+}
+print f(x, 0);
+"#;
+        let warnings = lint_source(source);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unused variable 'unused'",
+                "Unused parameter 'b'",
+                "Variable 'x' shadows a binding from an enclosing scope",
+            ]
+        );
+        assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+        assert_eq!(warnings[0].span.map(|span| span.start_line), Some(2));
+        let parameter = warnings[1].span.expect("parameters have a span");
+        assert_eq!(
+            (
+                parameter.start_line,
+                parameter.start_column,
+                parameter.end_column
+            ),
+            (5, 14, 19)
+        );
+    }
+
+    #[test]
+    fn linter_counts_only_reads_as_uses() {
+        let source = r#"
+let w = 0;
+w = 5;
+let scores = [1, 2];
+scores[0] = 3;
+let read = 1;
+read = read + 1;
+"#;
+        let warnings = lint_source(source);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["Unused variable 'w'", "Unused variable 'scores'"]
+        );
+    }
+
+    #[test]
+    fn linter_warns_about_unreachable_code_and_literal_guards() {
+        let source = r#"
+let i: int = 0;
+while i < 3 {
+    break;
+    print i;
+    i = i + 1;
+}
+if false { print "never"; }
+"#;
+        let warnings = lint_source(source);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["Unreachable code after 'break'", "If guard is always false"]
+        );
+        let unreachable = warnings[0].span.expect("unreachable code has a span");
+        assert_eq!((unreachable.start_line, unreachable.end_line), (5, 6));
+        assert!(
+            warnings[1]
+                .render(source)
+                .contains("[Linting] warning[F0404]:")
+        );
+    }
+
+    fn render_plain(error: &FlavorError, source: &str) -> String {
+        colored::control::set_override(false);
+        error.render(source)
+    }
+
+    #[test]
+    fn render_draws_multiline_spans_in_the_gutter_with_notes() {
+        let source = "fn f(a: int) -> int {\n    if a > 0 {\n        return a;\n    }\n}\n";
+        let errors = type_check_all(source);
+        let rendered = render_plain(&errors[0], source);
+        let expected = "\
+ --> 1:1
+  |
+1 | / fn f(a: int) -> int {
+2 | |     if a > 0 {
+3 | |         return a;
+4 | |     }
+5 | | }
+  | |_^
+  = note: 'f' returns Int, so every path through its body must end in a return
+";
+        assert!(
+            rendered.contains(expected),
+            "unexpected rendering:{rendered}"
+        );
+    }
+
+    #[test]
+    fn render_shows_labels_and_aligns_wide_characters() {
+        let source = "let y: int = 1;\nprint y;\nprint y; y = \"名前\";\n";
+        let errors = type_check_all(source);
+        let rendered = render_plain(&errors[0], source);
+        let expected = "\
+ --> 3:10
+  |
+1 | let y: int = 1;
+  | --------------- 'y' declared here as Int
+...
+3 | print y; y = \"名前\";
+  |          ^^^^^^^^^^
+";
+        assert!(
+            rendered.contains(expected),
+            "unexpected rendering:{rendered}"
+        );
+    }
+
+    #[test]
+    fn errors_carry_stable_codes_from_every_phase() {
+        let lex_error = lex_source("let x = 1 $ 2;").unwrap_err();
+        assert_eq!(lex_error.code, Some(ErrorCode::UnknownToken));
+
+        let type_error = compile_source(r#"let count: int = "three";"#).unwrap_err();
+        assert_eq!(type_error.code, Some(ErrorCode::LetTypeMismatch));
+        let rendered = render_plain(&type_error, r#"let count: int = "three";"#);
+        assert!(rendered.contains("[TypeChecking] error[F0203]: Type mismatch in let declaration"));
+
+        let runtime_error = evaluate_source("let d: int = 0; print 10 / d;").unwrap_err();
+        assert_eq!(runtime_error.code, Some(ErrorCode::DivisionByZero));
+
+        let pop_error = evaluate_source("let values: [int] = []; print pop(values);").unwrap_err();
+        assert_eq!(pop_error.code, Some(ErrorCode::EmptyArray));
+    }
+
+    #[test]
+    fn error_codes_are_unique_and_explained() {
+        for (index, code) in ErrorCode::ALL.iter().enumerate() {
+            assert!(
+                ErrorCode::ALL[..index]
+                    .iter()
+                    .all(|other| other.as_str() != code.as_str()),
+                "{code} is assigned twice"
+            );
+            assert_eq!(ErrorCode::parse(code.as_str()), Some(*code));
+            assert!(
+                !code.explanation().trim().is_empty(),
+                "{code} has no explanation"
+            );
+        }
+        assert_eq!(ErrorCode::parse("f0203"), Some(ErrorCode::LetTypeMismatch));
+        assert_eq!(ErrorCode::parse("F9999"), None);
+    }
+
+    #[test]
+    fn diagnostics_serialize_to_stable_json() {
+        let source = "let y: int = 1;\nprint y;\ny = \"two\";\n";
+        let errors = type_check_all(source);
+        assert_eq!(
+            errors[0].to_json("main.flv").to_string(),
+            concat!(
+                r#"{"severity":"error","phase":"TypeChecking","code":"F0204","#,
+                r#""message":"Assignment type mismatch: left is Int, right is String","#,
+                r#""file":"main.flv","#,
+                r#""span":{"start_line":3,"start_column":1,"end_line":3,"end_column":9},"#,
+                r#""labels":[{"message":"'y' declared here as Int","#,
+                r#""span":{"start_line":1,"start_column":1,"end_line":1,"end_column":15}}],"#,
+                r#""notes":[]}"#
+            )
+        );
+
+        let error = FlavorError::new(ErrorPhase::Runtime, "say \"hi\"\n\tnow\\", None)
+            .with_help("tab\there");
+        assert_eq!(
+            error.to_json("a.flv").to_string(),
+            concat!(
+                r#"{"severity":"error","phase":"Runtime","code":null,"#,
+                r#""message":"say \"hi\"\n\tnow\\","file":"a.flv","span":null,"#,
+                r#""labels":[],"notes":[{"kind":"help","message":"tab\there"}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn ast_serializes_to_json_with_flavor_types() {
+        let nodes = parse_source("let f: (int) -> [int] = <x: int> -> [int] { return [x]; };")
+            .expect("program should parse");
+        let json = Json::from(&nodes[0]).to_string();
+        assert!(json.starts_with(
+            r#"{"node":"LetDeclaration","identifier":"f","var_type":"(int) -> [int]","expr":{"node":"FunctionExpression","parameters":[{"name":"x","type":"int"}],"return_type":"[int]""#
+        ));
+        assert!(json.ends_with(
+            r#""span":{"start_line":1,"start_column":1,"end_line":1,"end_column":58}}"#
+        ));
+
+        let tokens = lex_source(r#""a\tb""#).expect("string should lex");
+        assert_eq!(
+            Json::from(&tokens[0]).to_string(),
+            r#"{"kind":"StringLiteral","lexeme":"\"a\\tb\"","value":"a\tb","span":{"start_line":1,"start_column":1,"end_line":1,"end_column":6}}"#
+        );
+    }
+
+    #[test]
+    fn repl_keeps_declarations_across_inputs_and_recovers_from_errors() {
+        colored::control::set_override(false);
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval("let x = 5;\n"), Ok(None));
+        assert_eq!(
+            repl.eval("fn twice(n: int) -> int { return n * 2; }"),
+            Ok(None)
+        );
+        assert_eq!(repl.eval("twice(x)"), Ok(Some("10".to_string())));
+
+        let error = repl.eval("let y: int = \"s\";").unwrap_err();
+        assert!(error.contains("error[F0203]"));
+        assert!(repl.eval("y").unwrap_err().contains("error[F0201]"));
+        assert!(
+            repl.eval("let z = x / 0;")
+                .unwrap_err()
+                .contains("error[F0301]")
+        );
+        assert!(repl.eval("z").unwrap_err().contains("error[F0201]"));
+        assert_eq!(repl.eval("let z = x + 1; z"), Ok(Some("6".to_string())));
+    }
+
+    #[test]
+    fn repl_echoes_expressions_but_not_assignments() {
+        colored::control::set_override(false);
+        let mut repl = Repl::new();
+        repl.eval("let x = 1;").unwrap();
+        assert_eq!(repl.eval("x = 10;"), Ok(None));
+        assert_eq!(repl.eval("x = 20"), Ok(None));
+        assert_eq!(repl.eval("x++"), Ok(Some("20".to_string())));
+        assert_eq!(repl.eval("x == 21"), Ok(Some("true".to_string())));
+
+        // The `;` added to the missing one is not part of what was typed
+        let error = repl.eval("missing").unwrap_err();
+        assert!(error.contains("error[F0201]"));
+        assert!(error.contains("1 | missing\n"), "{error}");
+    }
+
+    #[test]
+    fn repl_commands_inspect_and_reset_the_session() {
+        let mut repl = Repl::new();
+        repl.eval("let names = [\"a\", \"b\"];").unwrap();
+        assert_eq!(repl.eval(":type names"), Ok(Some("[string]".to_string())));
+        assert_eq!(
+            repl.eval(":type <n: int> -> bool { return n > 0; }"),
+            Ok(Some("(int) -> bool".to_string()))
+        );
+        assert!(
+            repl.eval(":ast names[0]")
+                .unwrap()
+                .unwrap()
+                .contains("ArrayAccess")
+        );
+        assert!(repl.eval(":type let a = 1;").is_err());
+        assert!(repl.eval(":nope").is_err());
+
+        repl.eval(":reset").unwrap();
+        assert!(repl.eval("names").is_err());
+    }
+
+    #[test]
+    fn repl_waits_for_unbalanced_input() {
+        assert!(repl::is_complete("let x = 1;\n"));
+        assert!(!repl::is_complete("fn f() -> int {\n"));
+        assert!(!repl::is_complete("let xs = [1,\n"));
+        assert!(!repl::is_complete("/* still\n"));
+        assert!(!repl::is_complete("let s = \"\"\"first line\n"));
+        assert!(repl::is_complete("let s = \"oops\n"));
+        assert!(repl::is_complete("fn f() -> int {\n    return 1;\n}\n"));
+    }
+
+    #[test]
+    fn engine_runs_sources_against_earlier_declarations() {
+        let mut engine = Engine::new();
+        engine.define_global("base", Type::Int, EvaluationType::Int(40));
+        engine
+            .run_source("fn add(a: int, b: int) -> int { return a + b; }")
+            .expect("declaration should run");
+        let value = engine.run_source("add(base, 2);").expect("call should run");
+        assert!(matches!(value, EvaluationType::Int(42)));
+
+        let errors = engine.run_source("let broken = add(base);").unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::ArgumentCountMismatch));
+        let errors = engine.run_source("let ratio = base / 0;").unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::DivisionByZero));
+        let errors = engine.run_source("ratio;").unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::UndeclaredIdentifier));
+
+        let errors = Engine::parse("add(1, 2)").unwrap_err();
+        assert!(matches!(errors[0].phase, ErrorPhase::Parsing));
+    }
+
+    fn function_type(param_types: Vec<Type>, return_type: Type) -> Type {
+        Type::Function {
+            param_types,
+            return_type: Box::new(return_type),
+        }
+    }
+
+    #[test]
+    fn engine_calls_registered_host_functions() {
+        let mut engine = Engine::new();
+        engine.register_function(
+            "repeat",
+            function_type(vec![Type::String, Type::Int], Type::String),
+            |args| match args {
+                [EvaluationType::String(text), EvaluationType::Int(times)] if *times >= 0 => {
+                    Ok(EvaluationType::String(text.repeat(*times as usize)))
+                }
+                _ => Err("the count must not be negative".to_string()),
+            },
+        );
+        engine.register_function("broken", function_type(vec![], Type::Int), |_| {
+            Ok(EvaluationType::Bool(true))
+        });
+
+        let value = engine
+            .run_source(
+                "fn apply(f: (string, int) -> string) -> string { return f(\"ab\", 2); }\n\
+                 apply(repeat);",
+            )
+            .expect("host function should run");
+        assert_eq!(value.to_string(), "abab");
+
+        let errors = engine.run_source("repeat(\"ab\", \"x\");").unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::ArgumentTypeMismatch));
+
+        let source = "let s = \"ab\";\nprint repeat(s, 0 - 1);";
+        let errors = engine.run_source(source).unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::HostFunctionFailed));
+        assert_eq!(errors[0].span, Some(Span::new(2, 7, 2, 22)));
+        assert_eq!(
+            errors[0].message,
+            "'repeat' failed: the count must not be negative"
+        );
+
+        let errors = engine.run_source("broken();").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("returned a bool but is declared to return int")
+        );
+    }
+
+    #[test]
+    fn engine_captures_output_and_reads_input() {
+        let input = std::io::Cursor::new("Ada\r\nGrace\n");
+        let mut engine = Engine::new().with_input(input);
+        let output = engine
+            .run_to_string(
+                r#"
+let first = read_line();
+let second = read_line();
+print "hello ", first, "!";
+print "hello ", second, "!";
+print len(read_line());
+"#,
+            )
+            .expect("program should run");
+        assert_eq!(output, "hello Ada!\nhello Grace!\n0\n");
+    }
+
+    #[test]
+    fn programs_can_read_until_the_end_of_input() {
+        let source = r#"
+let count = 0;
+while !eof() {
+    let line = read_line();
+    count++;
+    print count, ": '", line, "'";
+}
+print "lines: ", count, ", at end: ", eof(), ", then: '", read_line(), "'";
+"#;
+        for backend in [Backend::Interpreter, Backend::Bytecode] {
+            // The empty line and the last line, without a line ending, are both read
+            let input = std::io::Cursor::new("first\n\nlast");
+            let output = Engine::with_backend(backend)
+                .with_input(input)
+                .run_to_string(source)
+                .expect("program should run");
+            assert_eq!(
+                output,
+                "1: 'first'\n2: ''\n3: 'last'\nlines: 3, at end: true, then: ''\n"
+            );
+
+            let output = Engine::with_backend(backend)
+                .with_input(std::io::empty())
+                .run_to_string(source)
+                .expect("program should run");
+            assert_eq!(output, "lines: 0, at end: true, then: ''\n");
+        }
+    }
+
+    struct ClosedOutput;
+
+    impl std::io::Write for ClosedOutput {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn engine_reports_failing_output_at_the_print() {
+        let mut engine = Engine::new().with_output(ClosedOutput);
+        let errors = engine.run_source("let x = 1;\nprint x;").unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::IoFailed));
+        assert_eq!(errors[0].span, Some(Span::new(2, 1, 2, 8)));
+    }
+
+    /// Runs `source` on both backends and returns what each printed.
+    fn run_on_both(source: &str) -> [Result<String, Vec<FlavorError>>; 2] {
+        [Backend::Interpreter, Backend::Bytecode]
+            .map(|backend| Engine::with_backend(backend).run_to_string(source))
+    }
+
+    #[test]
+    fn vm_prints_what_the_interpreter_prints() {
+        let programs = [
+            include_str!("../test_files/basics.flv"),
+            include_str!("../test_files/control_flow.flv"),
+            include_str!("../test_files/functions.flv"),
+            include_str!("../test_files/test.flv"),
+            r#"
+struct Point { x: int, y: int }
+enum Shape { Circle(int), Rect(int, int), Empty }
+
+fn make_counter() -> () -> int {
+    let count = 0;
+    return <> -> int {
+        count++;
+        return count;
+    };
+}
+let counter = make_counter();
+counter();
+print "counter: ", counter();
+
+let adders: [() -> int] = [];
+for i in 0..3 {
+    push(adders, <> -> int { return i * 10; });
+}
+for adder in adders {
+    print adder();
+}
+
+let p = Point { y: 2, x: 1 };
+p.x = 10;
+p.y++;
+let grid = [[1, 2], [3, 4]];
+grid[1][0] = 30;
+grid[0][1]--;
+push(grid[0], 5);
+print p, " ", grid, " ", remove(grid[1], 0), " ", grid;
+
+fn area(s: Shape) -> int {
+    return match s {
+        Shape::Circle(r) => 3 * r * r,
+        Shape::Rect(w, h) => w * h,
+        Shape::Empty => 0,
+    };
+}
+print area(Shape::Circle(2)), " ", area(Shape::Rect(3, 4)), " ", Shape::Rect(1, 2);
+
+let total = 0;
+'outer: for a in 0..=5 {
+    for b in 0..5 {
+        if b == 3 { continue 'outer; }
+        if a == 4 { break 'outer; }
+        total = total + a * b;
+    }
+}
+print "total ", total;
+"#,
+        ];
+        for program in programs {
+            let [interpreted, compiled] = run_on_both(program);
+            let interpreted = interpreted.expect("program should run");
+            assert!(!interpreted.is_empty());
+            assert_eq!(compiled.expect("program should run"), interpreted);
+        }
+    }
+
+    #[test]
+    fn struct_values_compare_field_by_field() {
+        let source = r#"
+struct P { x: int, tags: [string] }
+let p = P { x: 1, tags: ["a"] };
+print P { x: 1, tags: [] } == P { tags: [], x: 1 }, " ", p == P { x: 1, tags: ["b"] };
+let q = p;
+q.x++;
+print p != q, " ", [p] == [P { x: 1, tags: ["a"] }];
+"#;
+        for output in run_on_both(source) {
+            assert_eq!(
+                output.expect("program should run"),
+                "true false\ntrue true\n"
+            );
+        }
+
+        let errors = type_check_all(
+            "struct F { f: () -> int }\nlet f = F { f: <> -> int { return 1; } };\nprint f == f;",
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].code,
+            Some(ErrorCode::InvalidOperandTypes)
+        ));
+    }
+
+    #[test]
+    fn enum_values_compare_by_variant_and_payload() {
+        let source = r#"
+enum Shape { Circle(float), Rect(int, int), Empty }
+let state = Shape::Circle(1.0);
+print state == Shape::Circle(1.0), " ", state != Shape::Circle(2.0), " ", state == Shape::Empty;
+print Shape::Rect(1, 2) == Shape::Rect(1, 2), " ", [Shape::Empty] == [Shape::Rect(0, 0)];
+"#;
+        for output in run_on_both(source) {
+            assert_eq!(
+                output.expect("program should run"),
+                "true true false\ntrue false\n"
+            );
+        }
+
+        let errors = type_check_all(
+            "enum E { F(() -> int) }\nlet f = E::F(<> -> int { return 1; });\nprint f == f;",
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].code,
+            Some(ErrorCode::InvalidOperandTypes)
+        ));
+    }
+
+    #[test]
+    fn vm_reports_runtime_errors_like_the_interpreter() {
+        let programs = [
+            "let a = [[1]];\na[0][2] = 5;",
+            "fn f(x: int) -> int { return x % 0; }\nprint f(1);",
+            "let a: [int] = [];\nfor i in 0..3 { print i; }\npop(a);",
+        ];
+        for program in programs {
+            let [interpreted, compiled] = run_on_both(program);
+            let (interpreted, compiled) = (interpreted.unwrap_err(), compiled.unwrap_err());
+            assert_eq!(compiled[0].message, interpreted[0].message);
+            assert_eq!(compiled[0].code, interpreted[0].code);
+            assert_eq!(compiled[0].span, interpreted[0].span);
+        }
+    }
+
+    #[test]
+    fn vm_keeps_globals_across_programs() {
+        let mut engine = Engine::with_backend(Backend::Bytecode);
+        engine.define_global("base", Type::Int, EvaluationType::Int(40));
+        engine.register_function("twice", function_type(vec![Type::Int], Type::Int), |args| {
+            match args {
+                [EvaluationType::Int(value)] => Ok(EvaluationType::Int(value * 2)),
+                _ => Err("expected an int".to_string()),
+            }
+        });
+        engine
+            .run_source("let offset = 1;\nfn add(a: int) -> int { return a + offset; }")
+            .expect("declarations should run");
+        engine
+            .run_source("offset = 2;")
+            .expect("assignment should run");
+        let value = engine
+            .run_source("twice(add(base));")
+            .expect("call should run");
+        assert!(matches!(value, EvaluationType::Int(84)));
+    }
+
+    #[test]
+    fn resolver_locates_captured_variables_by_scope_and_slot() {
+        let nodes = compile_source(
+            "let total = 1;\nfn make(a: int) -> () -> int {\n    let b = a;\n    return <> -> int { return a + b + total; };\n}",
+        )
+        .expect("program should check");
+        let mut globals = compiler::Globals::default();
+        resolver::Resolver::new(&mut globals).resolve_program(&nodes);
+
+        let ASTNode::FunctionDeclaration { body, location, .. } = &nodes[1] else {
+            panic!("expected a function declaration");
+        };
+        assert_eq!(location.get(), Some(types::Location::Global(1)));
+        let ASTNode::Body { nodes: body, .. } = body.as_ref() else {
+            panic!("expected a body");
+        };
+        let ASTNode::Return { expr, .. } = &body[1] else {
+            panic!("expected a return");
+        };
+        let ASTNode::FunctionExpression { body, .. } = expr.as_ref() else {
+            panic!("expected a closure");
+        };
+        let ASTNode::Body { nodes: body, .. } = body.as_ref() else {
+            panic!("expected a body");
+        };
+        let ASTNode::Return { expr, .. } = &body[0] else {
+            panic!("expected a return");
+        };
+        let ASTNode::BinaryExpression { left, right, .. } = expr.as_ref() else {
+            panic!("expected an addition");
+        };
+        let ASTNode::BinaryExpression {
+            left: a, right: b, ..
+        } = left.as_ref()
+        else {
+            panic!("expected an addition");
+        };
+        let location = |node: &ASTNode| match node {
+            ASTNode::Identifier { location, .. } => location.get(),
+            _ => None,
+        };
+        // The closure's parameters, then the body of `make` holding `b`, then its parameters.
+        assert_eq!(
+            location(a),
+            Some(types::Location::Local { depth: 2, slot: 0 })
+        );
+        assert_eq!(
+            location(b),
+            Some(types::Location::Local { depth: 1, slot: 0 })
+        );
+        assert_eq!(location(right), Some(types::Location::Global(0)));
+    }
+
+    #[test]
+    fn closures_see_the_variables_visible_where_they_are_written() {
+        let source = r#"
+let x = 1;
+{
+    let f = <> -> int { return x; };
+    let x = 2;
+    print f(), " ", x;
+}
+fn count() -> () -> int {
+    let n = 0;
+    let step = <> -> int {
+        n++;
+        return n;
+    };
+    let n = 10;
+    return step;
+}
+let step = count();
+step();
+print step();
+let n = match Option::Some(3) {
+    Option::Some(n) => n + x,
+    Option::None => 0,
+};
+print n;
+"#;
+        let source = format!("enum Option {{ Some(int), None }}\n{source}");
+        for output in run_on_both(&source) {
+            assert_eq!(output.expect("program should run"), "1 2\n12\n4\n");
+        }
+    }
+
+    #[test]
+    fn interpreter_frees_closure_cycles_created_in_loops() {
+        let nodes = compile_source(
+            r#"
+fn run(n: int) -> int {
+    fn twice(x: int) -> int { return x * 2; }
+    return twice(n);
+}
+let total = 0;
+for i in 0..20000 {
+    let f = <> -> int { return i; };
+    total = total + f() + run(i);
+}
+total;
+"#,
+        )
+        .expect("program should check");
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .eval_program(&nodes)
+            .expect("program should run");
+        assert!(matches!(
+            value,
+            EvalOutcome::Value(EvaluationType::Int(599_970_000))
+        ));
+
+        // Each iteration leaves four scopes in cycles, but collections keep their number down.
+        assert!(interpreter.live_scopes() <= 2048);
+        interpreter.collect_garbage();
+        assert_eq!(interpreter.live_scopes(), 0);
+    }
+
+    #[test]
+    fn vm_frees_closure_cycles_created_in_loops() {
+        let nodes = compile_source(
+            r#"
+fn run(n: int) -> int {
+    fn sum(x: int) -> int {
+        if x == 0 { return 0; }
+        return x + sum(x - 1);
+    }
+    return sum(n % 10);
+}
+let total = 0;
+for i in 0..20000 {
+    let f = <> -> int { return i; };
+    total = total + f() + run(i);
+}
+total;
+"#,
+        )
+        .expect("program should check");
+        let mut vm = Vm::new();
+        let value = vm.run(&nodes).expect("program should run");
+        assert!(matches!(value, EvaluationType::Int(200_320_000)));
+
+        // Each call to `run` leaves `sum` holding the upvalue that holds it.
+        assert!(vm.live_upvalues() <= 2048);
+        vm.collect_garbage();
+        assert_eq!(vm.live_upvalues(), 0);
+    }
+
+    /// Call benchmark running a recursive `fib(27)`, 635,621 calls, on both backends.
+    /// Run with `cargo test --release call_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn call_benchmark_fib() {
+        let nodes = compile_source(
+            r#"
+fn fib(n: int) -> int {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+fib(27);
+"#,
+        )
+        .expect("program should check");
+        let calls = 635_621.0;
+
+        let start = std::time::Instant::now();
+        let value = Interpreter::new()
+            .eval_program(&nodes)
+            .expect("program should run");
+        let interpreted = start.elapsed();
+        assert!(matches!(
+            value,
+            EvalOutcome::Value(EvaluationType::Int(196_418))
+        ));
+
+        let start = std::time::Instant::now();
+        let value = Vm::new().run(&nodes).expect("program should run");
+        let compiled = start.elapsed();
+        assert!(matches!(value, EvaluationType::Int(196_418)));
+
+        let ratio = interpreted.as_secs_f64() / compiled.as_secs_f64();
+        println!(
+            "interpreter: {interpreted:?} ({:.0} calls/s), vm: {compiled:?} ({:.0} calls/s), \
+             interpreter/vm: {ratio:.1}",
+            calls / interpreted.as_secs_f64(),
+            calls / compiled.as_secs_f64()
+        );
+        // Resolved slots keep calls on the interpreter within a small factor of the virtual
+        // machine. Looking variables up by name, or copying the function body on each call,
+        // made them eight times slower or more.
+        assert!(ratio < 5.0, "the interpreter is {ratio:.1} times slower");
+    }
+
+    #[test]
+    fn garbage_collection_keeps_reachable_closures() {
+        let nodes = compile_source(
+            r#"
+fn make_counter() -> () -> int {
+    let count = 0;
+    fn next() -> int {
+        count++;
+        return count;
+    }
+    return next;
+}
+let counter = make_counter();
+let kept: [() -> int] = [];
+for i in 0..3000 {
+    let other = make_counter();
+    other();
+    if i % 1000 == 0 {
+        push(kept, other);
+    }
+    counter();
+}
+let second = kept[1];
+second();
+counter() * 10 + second();
+"#,
+        )
+        .expect("program should check");
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .eval_program(&nodes)
+            .expect("program should run");
+        assert!(matches!(
+            value,
+            EvalOutcome::Value(EvaluationType::Int(30013))
+        ));
+
+        interpreter.collect_garbage();
+        // The call and the body of `make_counter` for each of the four counters left.
+        assert_eq!(interpreter.live_scopes(), 8);
+    }
+
+    #[test]
+    fn arrays_are_values_on_both_backends() {
+        let source = r#"
+fn fill(values: [int]) -> [int] {
+    values[0] = 100;
+    values[1]++;
+    push(values, 4);
+    return values;
+}
+let a = [1, 2, 3];
+let b = a;
+b[0] = 10;
+b[2]--;
+let grid = [a, a];
+grid[0][1] = 20;
+let filled = fill(a);
+print a, " ", b, " ", grid, " ", filled;
+let snapshot = a;
+for x in a {
+    push(a, x);
+}
+print a, " ", snapshot;
+"#;
+        for output in run_on_both(source) {
+            assert_eq!(
+                output.expect("program should run"),
+                "[1, 2, 3] [10, 2, 2] [[1, 20, 3], [1, 2, 3]] [100, 3, 3, 4]\n[1, 2, 3, 1, 2, 3] [1, 2, 3]\n"
+            );
+        }
+    }
+
+    #[test]
+    fn arrays_are_shared_until_changed() {
+        let shared: Rc<Vec<_>> = Rc::new((0..100_000).map(EvaluationType::Int).collect());
+        for backend in [Backend::Interpreter, Backend::Bytecode] {
+            let mut engine = Engine::with_backend(backend);
+            engine.define_global(
+                "big",
+                Type::Array(Box::new(Type::Int)),
+                EvaluationType::Array(Rc::clone(&shared)),
+            );
+            let value = engine
+                .run_source("fn same(values: [int]) -> [int] { return values; }\nlet copy = same(big);\ncopy;")
+                .expect("program should run");
+            let EvaluationType::Array(copy) = value else {
+                panic!("expected an array, found {value}");
+            };
+            assert!(Rc::ptr_eq(&copy, &shared));
+
+            let value = engine
+                .run_source("copy[0] = -1;\ncopy;")
+                .expect("program should run");
+            let EvaluationType::Array(copy) = value else {
+                panic!("expected an array, found {value}");
+            };
+            assert!(!Rc::ptr_eq(&copy, &shared));
+            assert!(matches!(copy[0], EvaluationType::Int(-1)));
+            assert!(matches!(shared[0], EvaluationType::Int(0)));
+        }
+    }
+
+    #[test]
+    fn integer_literals_too_large_for_an_int_are_syntax_errors() {
+        let (_, errors) =
+            parse_recovering("let x = 9223372036854775808;\nlet y = 9223372036854775807;");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].phase, ErrorPhase::Parsing));
+        assert_eq!(errors[0].code, Some(ErrorCode::IntegerLiteralTooLarge));
+        assert_eq!(
+            errors[0].message,
+            "Integer literal 9223372036854775808 does not fit in an int"
+        );
+        assert_eq!(errors[0].span, Some(Span::new(1, 9, 1, 27)));
+    }
+
+    #[test]
+    fn integer_overflow_is_reported_at_the_operator() {
+        let cases = [
+            ("print big + 1;", Span::new(3, 7, 3, 13)),
+            ("print small - 1;", Span::new(3, 7, 3, 15)),
+            ("print big * 2;", Span::new(3, 7, 3, 13)),
+            ("print small / (0 - 1);", Span::new(3, 7, 3, 20)),
+            ("print small % (0 - 1);", Span::new(3, 7, 3, 20)),
+            ("print -small;", Span::new(3, 7, 3, 12)),
+            ("big++;", Span::new(3, 1, 3, 5)),
+            (
+                "let values = [small];\n--values[0];",
+                Span::new(4, 1, 4, 11),
+            ),
+        ];
+        for (statement, span) in cases {
+            let source =
+                format!("let big = 9223372036854775807;\nlet small = -big - 1;\n{statement}");
+            for result in run_on_both(&source) {
+                let errors = result.unwrap_err();
+                assert_eq!(
+                    errors[0].code,
+                    Some(ErrorCode::IntegerOverflow),
+                    "{statement}"
+                );
+                assert_eq!(errors[0].span, Some(span), "{statement}");
+            }
+        }
+    }
+
+    #[test]
+    fn wrapping_and_saturating_builtins_choose_what_overflow_does() {
+        let source = r#"
+let big = 9223372036854775807;
+let small = -big - 1;
+print wrapping_add(big, 1), " ", wrapping_sub(small, 1), " ", wrapping_mul(big, 2);
+print saturating_add(big, 1), " ", saturating_sub(small, 1), " ", saturating_mul(small, 2);
+print wrapping_add(2, 3), " ", saturating_mul(4, 5);
+"#;
+        for output in run_on_both(source) {
+            assert_eq!(
+                output.expect("program should run"),
+                "-9223372036854775808 9223372036854775807 -2\n\
+                 9223372036854775807 -9223372036854775808 -9223372036854775808\n\
+                 5 20\n"
+            );
+        }
+
+        let errors = Engine::new()
+            .run_source("print wrapping_add(1, 2.0);")
+            .unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::BuiltinArgumentMismatch));
+    }
+}
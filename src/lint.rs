@@ -0,0 +1,358 @@
+use crate::error::{ErrorPhase, FlavorError};
+use crate::error_codes::ErrorCode;
+use crate::types::{ASTNode, MatchArm, Parameter, Pattern, Span};
+
+/// What introduced a name. Only unused variables and parameters are worth a warning.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BindingKind {
+    Variable,
+    Parameter,
+    Other,
+}
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    kind: BindingKind,
+    span: Span,
+    used: bool,
+}
+
+/// Walks a type checked program and collects warnings about code that is valid but most
+/// likely a mistake: unused variables and parameters, unreachable statements, shadowed
+/// variables and `if` guards that are a boolean literal.
+///
+/// Only reading a variable uses it, assigning to it does not. Names starting with `_` are
+/// never reported as unused.
+pub struct Linter {
+    scopes: Vec<Vec<Binding>>,
+    warnings: Vec<FlavorError>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![vec![]],
+            warnings: vec![],
+        }
+    }
+
+    /// Lints the whole program and returns its warnings in source order.
+    pub fn lint_program(&mut self, nodes: &[ASTNode]) -> Vec<FlavorError> {
+        self.lint_body(nodes);
+        // Reports the unused globals and leaves a fresh global scope behind
+        self.exit_scope();
+        self.enter_scope();
+
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.sort_by_key(|warning| {
+            warning
+                .span
+                .map(|span| (span.start_line, span.start_column))
+        });
+        warnings
+    }
+
+    fn warn(&mut self, code: ErrorCode, message: impl Into<String>, span: Span) {
+        self.warnings
+            .push(FlavorError::warning(ErrorPhase::Linting, message, span).with_code(code));
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    /// Drops the innermost scope, reporting the variables and parameters nobody read.
+    fn exit_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for binding in scope {
+            if binding.used || binding.name.starts_with('_') {
+                continue;
+            }
+            match binding.kind {
+                BindingKind::Variable => self.warn_unused("variable", binding),
+                BindingKind::Parameter => self.warn_unused("parameter", binding),
+                BindingKind::Other => {}
+            }
+        }
+    }
+
+    fn warn_unused(&mut self, what: &str, binding: Binding) {
+        self.warnings.push(
+            FlavorError::warning(
+                ErrorPhase::Linting,
+                format!("Unused {what} '{}'", binding.name),
+                binding.span,
+            )
+            .with_code(ErrorCode::UnusedBinding)
+            .with_help(format!(
+                "if this is intentional, prefix it with an underscore: '_{}'",
+                binding.name
+            )),
+        );
+    }
+
+    fn declare(&mut self, name: &str, kind: BindingKind, span: Span) {
+        let (current, enclosing) = self
+            .scopes
+            .split_last_mut()
+            .expect("lint scopes should never be empty");
+
+        let shadows = enclosing
+            .iter()
+            .any(|scope| scope.iter().any(|binding| binding.name == name));
+        if shadows && kind != BindingKind::Other {
+            let what = if kind == BindingKind::Parameter {
+                "Parameter"
+            } else {
+                "Variable"
+            };
+            self.warnings.push(
+                FlavorError::warning(
+                    ErrorPhase::Linting,
+                    format!("{what} '{name}' shadows a binding from an enclosing scope"),
+                    span,
+                )
+                .with_code(ErrorCode::ShadowedBinding),
+            );
+        }
+
+        current.push(Binding {
+            name: name.to_string(),
+            kind,
+            span,
+            used: false,
+        });
+    }
+
+    /// Marks the innermost binding called `name` as read.
+    fn use_name(&mut self, name: &str) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name);
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    /// Lints the statements of a body, reporting everything after a `return`, `break` or
+    /// `continue` as a single unreachable block.
+    fn lint_body(&mut self, nodes: &[ASTNode]) {
+        let mut jump: Option<&str> = None;
+        let mut unreachable: Option<Span> = None;
+
+        for node in nodes {
+            if jump.is_some() {
+                unreachable = Some(match unreachable {
+                    Some(span) => span.merge(node.span()),
+                    None => *node.span(),
+                });
+            }
+            self.lint(node);
+            if jump.is_none() {
+                jump = match node {
+                    ASTNode::Return { .. } => Some("return"),
+                    ASTNode::Break { .. } => Some("break"),
+                    ASTNode::Continue { .. } => Some("continue"),
+                    _ => None,
+                };
+            }
+        }
+
+        if let (Some(keyword), Some(span)) = (jump, unreachable) {
+            self.warn(
+                ErrorCode::UnreachableCode,
+                format!("Unreachable code after '{keyword}'"),
+                span,
+            );
+        }
+    }
+
+    fn lint_function(&mut self, parameters: &[Parameter], body: &ASTNode) {
+        self.enter_scope();
+        for parameter in parameters {
+            self.declare(&parameter.name, BindingKind::Parameter, parameter.span);
+        }
+        self.lint(body);
+        self.exit_scope();
+    }
+
+    fn lint_match_arm(&mut self, arm: &MatchArm) {
+        self.enter_scope();
+        match &arm.pattern {
+            Pattern::Wildcard { .. } => {}
+            Pattern::Binding { name, span } => self.declare(name, BindingKind::Other, *span),
+            Pattern::Variant { bindings, span, .. } => {
+                for binding in bindings.iter().filter(|binding| *binding != "_") {
+                    self.declare(binding, BindingKind::Other, *span);
+                }
+            }
+        }
+        self.lint(&arm.body);
+        self.exit_scope();
+    }
+
+    /// Lints the left side of an `=`, which writes the variable at its root without reading it.
+    fn lint_assignment_target(&mut self, target: &ASTNode) {
+        match target {
+            ASTNode::Identifier { .. } => {}
+            ASTNode::ArrayAccess { array, index, .. } => {
+                self.lint_assignment_target(array);
+                self.lint(index);
+            }
+            ASTNode::FieldAccess { object, .. } => self.lint_assignment_target(object),
+            _ => self.lint(target),
+        }
+    }
+
+    fn lint(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::Print { expressions, .. } => {
+                for expr in expressions {
+                    self.lint(expr);
+                }
+            }
+            ASTNode::Body { nodes, .. } => {
+                self.enter_scope();
+                self.lint_body(nodes);
+                self.exit_scope();
+            }
+            ASTNode::If {
+                guard,
+                then_body,
+                else_body,
+                ..
+            } => {
+                if let ASTNode::BoolLiteral { value, span } = guard.as_ref() {
+                    self.warn(
+                        ErrorCode::ConstantGuard,
+                        format!("If guard is always {value}"),
+                        *span,
+                    );
+                }
+                self.lint(guard);
+                self.lint(then_body);
+                if let Some(else_body) = else_body {
+                    self.lint(else_body);
+                }
+            }
+            ASTNode::While { guard, body, .. } => {
+                self.lint(guard);
+                self.lint(body);
+            }
+            ASTNode::For {
+                variable,
+                iterable,
+                body,
+                span,
+                ..
+            } => {
+                self.lint(iterable);
+                self.enter_scope();
+                self.declare(variable, BindingKind::Other, *span);
+                self.lint(body);
+                self.exit_scope();
+            }
+            ASTNode::Range { start, end, .. } => {
+                self.lint(start);
+                self.lint(end);
+            }
+            ASTNode::LetDeclaration {
+                identifier,
+                expr,
+                span,
+                ..
+            } => {
+                self.lint(expr);
+                self.declare(identifier, BindingKind::Variable, *span);
+            }
+            ASTNode::FunctionDeclaration {
+                name,
+                parameters,
+                body,
+                span,
+                ..
+            } => {
+                self.declare(name, BindingKind::Other, *span);
+                self.lint_function(parameters, body);
+            }
+            ASTNode::FunctionExpression {
+                parameters, body, ..
+            } => self.lint_function(parameters, body),
+            ASTNode::Return { expr, .. } => self.lint(expr),
+            ASTNode::FunctionCall {
+                callee, arguments, ..
+            } => {
+                self.lint(callee);
+                for arg in arguments {
+                    self.lint(arg);
+                }
+            }
+            ASTNode::Identifier { name, .. } => self.use_name(name),
+            ASTNode::ArrayLiteral { elements, .. } => {
+                for elem in elements {
+                    self.lint(elem);
+                }
+            }
+            ASTNode::ArrayAccess { array, index, .. } => {
+                self.lint(array);
+                self.lint(index);
+            }
+            ASTNode::StructLiteral { fields, .. } => {
+                for (_, value) in fields {
+                    self.lint(value);
+                }
+            }
+            ASTNode::FieldAccess { object, .. } => self.lint(object),
+            ASTNode::EnumVariant { arguments, .. } => {
+                for arg in arguments {
+                    self.lint(arg);
+                }
+            }
+            ASTNode::Match {
+                scrutinee, arms, ..
+            } => {
+                self.lint(scrutinee);
+                for arm in arms {
+                    self.lint_match_arm(arm);
+                }
+            }
+            ASTNode::BinaryExpression {
+                left,
+                operator,
+                right,
+                ..
+            } if operator == "=" => {
+                self.lint_assignment_target(left);
+                self.lint(right);
+            }
+            ASTNode::BinaryExpression { left, right, .. } => {
+                self.lint(left);
+                self.lint(right);
+            }
+            ASTNode::UnaryExpression { operand, .. } => self.lint(operand),
+            ASTNode::ExpressionStatement { expr, .. } => self.lint(expr),
+            ASTNode::AliasDeclaration { .. }
+            | ASTNode::StructDeclaration { .. }
+            | ASTNode::EnumDeclaration { .. }
+            | ASTNode::Break { .. }
+            | ASTNode::Continue { .. }
+            | ASTNode::UnitLiteral { .. }
+            | ASTNode::NumberLiteral { .. }
+            | ASTNode::FloatLiteral { .. }
+            | ASTNode::StringLiteral { .. }
+            | ASTNode::BoolLiteral { .. } => {}
+        }
+    }
+}
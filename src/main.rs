@@ -1,570 +1,365 @@
-mod error;
-mod interpreter;
-mod lexer;
-mod parser;
-mod typechecker;
-mod types;
-
-use error::FlavorError;
-use interpreter::Interpreter;
-use lexer::Lexer;
-use parser::Parser;
-use typechecker::TypeChecker;
-
-fn report_and_exit(error: FlavorError, source: &str) -> ! {
-    eprintln!("{}", error.render(source));
-    std::process::exit(1);
+use flavor::error_codes::ErrorCode;
+use flavor::json::Json;
+use flavor::lint::Linter;
+use flavor::repl::Repl;
+use flavor::types::Type;
+use flavor::{Backend, Engine, EvaluationType, FlavorError, Lexer};
+use std::io::Read;
+use std::rc::Rc;
+
+/// How diagnostics are printed, chosen with `--error-format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    /// Colored snippets of the source, for people
+    Human,
+    /// One JSON object per line, see `FlavorError::to_json`
+    Json,
 }
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <source_file>", args[0]);
-        std::process::exit(1);
-    }
-    let filename = &args[1];
-    //check file extension
-    if !filename.ends_with(".flv") {
-        eprintln!("Error: Source file must have a .flv extension");
-        std::process::exit(1);
-    }
-    let code = std::fs::read_to_string(filename).expect("Failed to read source file");
-
-    let debug = false;
-
-    if debug {
-        println!("Debug prints are ON");
-        println!("SOURCE CODE\n{code}\n----\n");
-    }
-
-    let mut lexer = Lexer::new(&code);
-    let lex_res = lexer.lexe();
-    if let Err(err) = lex_res {
-        report_and_exit(err, &code);
-    }
-
-    if debug {
-        println!("TOKENS\n");
-        for t in lexer.tokens.clone() {
-            println!("{t:?}");
+impl ErrorFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
         }
-        println!("\n----\n");
-    }
-
-    let mut parser = Parser::new(lexer.tokens);
-    let nodes = match parser.parse_program() {
-        Ok(nodes) => nodes,
-        Err(err) => report_and_exit(err, &code),
-    };
-
-    if debug {
-        println!("AST\n");
-        for n in nodes.clone() {
-            println!("{n:#?}");
-        }
-        println!("\n----\n");
-    }
-
-    let mut tc = TypeChecker::new();
-    if let Err(err) = tc.check_program(&nodes) {
-        report_and_exit(err, &code);
-    }
-
-    if debug {
-        println!("Type Checking\n\n----\n");
-    }
-
-    let mut interpreter = Interpreter::new();
-    if let Err(err) = interpreter.eval_program(&nodes) {
-        report_and_exit(err, &code);
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::error::{ErrorPhase, FlavorError};
-    use crate::interpreter::{EvalOutcome, EvaluationType, Interpreter};
-    use crate::types::{ASTNode, Token, TokenName as TN, Type};
-
-    fn lex_source(source: &str) -> Result<Vec<Token>, FlavorError> {
-        let mut lexer = Lexer::new(source);
-        lexer.lexe()?;
-        Ok(lexer.tokens)
-    }
-
-    fn token_names(source: &str) -> Vec<TN> {
-        lex_source(source)
-            .map(|tokens| {
-                tokens
-                    .into_iter()
-                    .map(|token| token.tok_name)
-                    .collect::<Vec<_>>()
-            })
-            .expect("lexing failed during test setup")
-    }
-
-    fn parse_source(source: &str) -> Result<Vec<ASTNode>, FlavorError> {
-        let tokens = lex_source(source)?;
-        let mut parser = Parser::new(tokens);
-        parser.parse_program()
-    }
-
-    fn type_check_nodes(nodes: &[ASTNode]) -> Result<(), FlavorError> {
-        let mut checker = TypeChecker::new();
-        checker.check_program(nodes)
-    }
-
-    fn compile_source(source: &str) -> Result<Vec<ASTNode>, FlavorError> {
-        let nodes = parse_source(source)?;
-        type_check_nodes(&nodes)?;
-        Ok(nodes)
-    }
+/// Prints the diagnostics of one source file to standard error.
+struct Reporter<'a> {
+    file: &'a str,
+    source: &'a str,
+    format: ErrorFormat,
+}
 
-    fn evaluate_source(source: &str) -> Result<EvaluationType, FlavorError> {
-        let nodes = compile_source(source)?;
-        let mut interpreter = Interpreter::new();
-        match interpreter.eval_program(&nodes)? {
-            EvalOutcome::Value(value) => Ok(value),
-            EvalOutcome::Return(value) => Ok(value),
-            EvalOutcome::Break => Err(FlavorError::new(
-                ErrorPhase::Runtime,
-                "Top-level break is not allowed",
-                None,
-            )),
+impl Reporter<'_> {
+    fn report(&self, diagnostics: &[FlavorError]) {
+        for diagnostic in diagnostics {
+            match self.format {
+                ErrorFormat::Human => eprintln!("{}", diagnostic.render(self.source)),
+                ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(self.file)),
+            }
         }
     }
 
-    #[test]
-    fn lexer_recognizes_keywords_and_symbols() {
-        let names = token_names(
-            "let score = 10; if score >= 5 && score < 20 { print score; } else { score = score - 1; } let x = 40.574;",
-        );
-        assert_eq!(
-            names,
-            vec![
-                TN::Let,
-                TN::Identifier,
-                TN::Assign,
-                TN::Number,
-                TN::Semicolon,
-                TN::If,
-                TN::Identifier,
-                TN::Ge,
-                TN::Number,
-                TN::And,
-                TN::Identifier,
-                TN::Lt,
-                TN::Number,
-                TN::LBra,
-                TN::Print,
-                TN::Identifier,
-                TN::Semicolon,
-                TN::RBra,
-                TN::Else,
-                TN::LBra,
-                TN::Identifier,
-                TN::Assign,
-                TN::Identifier,
-                TN::Minus,
-                TN::Number,
-                TN::Semicolon,
-                TN::RBra,
-                TN::Let,
-                TN::Identifier,
-                TN::Assign,
-                TN::FloatLiteral,
-                TN::Semicolon,
-                TN::Eof,
-            ]
-        );
+    fn report_and_exit(&self, errors: Vec<FlavorError>) -> ! {
+        self.report(&errors);
+        if self.format == ErrorFormat::Human {
+            if errors.len() > 1 {
+                eprintln!("Aborting due to {} errors", errors.len());
+            }
+            if let Some(code) = errors.iter().find_map(|error| error.code) {
+                eprintln!("For more information about an error, try `flavor --explain {code}`.");
+            }
+        }
+        std::process::exit(1);
     }
+}
 
-    #[test]
-    fn lexer_handles_literals_and_delimiters() {
-        let names = token_names(
-            r#"alias => value; fn f() -> [string] { print "hi", data[0]; return nothing; }"#,
-        );
-        assert_eq!(
-            names,
-            vec![
-                TN::Alias,
-                TN::BoldArrow,
-                TN::Identifier,
-                TN::Semicolon,
-                TN::Fn,
-                TN::Identifier,
-                TN::LPar,
-                TN::RPar,
-                TN::SlimArrow,
-                TN::LSqu,
-                TN::String,
-                TN::RSqu,
-                TN::LBra,
-                TN::Print,
-                TN::StringLiteral,
-                TN::Comma,
-                TN::Identifier,
-                TN::LSqu,
-                TN::Number,
-                TN::RSqu,
-                TN::Semicolon,
-                TN::Return,
-                TN::Nothing,
-                TN::Semicolon,
-                TN::RBra,
-                TN::Eof,
-            ]
-        );
-    }
+/// What the command line asked for.
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    /// Check the program, then run it
+    Run,
+    /// Lex, parse, type check and lint without running
+    Check,
+    /// Print the token stream
+    Tokens,
+    /// Print the syntax tree
+    Ast,
+    /// Read and run statements interactively
+    Repl,
+    /// Print the long-form explanation of an error code
+    Explain(String),
+    /// Print the usage
+    Help,
+}
 
-    #[test]
-    fn parser_builds_function_declaration_ast() {
-        let source = r#"
-fn add(a: int, b: int) -> int {
-    let tmp: int = a + b;
-    return tmp;
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    /// Source file, `-` for standard input
+    path: String,
+    error_format: ErrorFormat,
+    /// Print `tokens` and `ast` output as JSON
+    json: bool,
+    /// Run the program on the bytecode virtual machine
+    vm: bool,
+    /// Everything after `--`, visible to the program as `args`
+    script_args: Vec<String>,
 }
-"#;
-        let nodes = parse_source(source).expect("failed to parse function declaration");
-        assert_eq!(nodes.len(), 1);
-        match &nodes[0] {
-            ASTNode::FunctionDeclaration {
-                name,
-                parameters,
-                return_type,
-                body,
-                ..
-            } => {
-                assert_eq!(name, "add");
-                assert_eq!(parameters.len(), 2);
-                assert_eq!(parameters[0], ("a".to_string(), Type::Int));
-                assert_eq!(parameters[1], ("b".to_string(), Type::Int));
-                assert_eq!(*return_type, Type::Int);
-                match body.as_ref() {
-                    ASTNode::Body {
-                        nodes: body_nodes, ..
-                    } => {
-                        assert_eq!(body_nodes.len(), 2);
-                        assert!(matches!(body_nodes[0], ASTNode::LetDeclaration { .. }));
-                        assert!(matches!(body_nodes[1], ASTNode::Return { .. }));
+
+const USAGE: &str = "\
+Usage: flavor [run] [options] [--vm] <file.flv | -> [-- <script args>...]
+       flavor check [options] <file.flv | ->
+       flavor tokens [options] [--json] <file.flv | ->
+       flavor ast [options] [--json] <file.flv | ->
+       flavor explain <error_code>
+       flavor [repl]
+
+Options:
+    --error-format=human|json   How to print diagnostics (default: human)
+    --json                      Print tokens or the syntax tree as JSON
+    --vm                        Run the program on the bytecode virtual machine
+    --explain <error_code>      Same as `flavor explain <error_code>`
+    -h, --help                  Print this message
+
+Use `-` as the file to read the program from standard input.
+Without arguments, statements are read interactively.";
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut command = None;
+        let mut path = None;
+        let mut error_format = ErrorFormat::Human;
+        let mut json = false;
+        let mut vm = false;
+        let mut script_args = vec![];
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => {
+                    script_args = args.by_ref().cloned().collect();
+                }
+                "--json" => json = true,
+                "--vm" => vm = true,
+                "-h" | "--help" => command = Some(Command::Help),
+                "--explain" => {
+                    let code = args.next().ok_or("Missing error code after --explain")?;
+                    command = Some(Command::Explain(code.clone()));
+                }
+                "run" | "check" | "tokens" | "ast" | "repl" | "explain"
+                    if command.is_none() && path.is_none() =>
+                {
+                    command = Some(match arg.as_str() {
+                        "run" => Command::Run,
+                        "check" => Command::Check,
+                        "tokens" => Command::Tokens,
+                        "ast" => Command::Ast,
+                        "repl" => Command::Repl,
+                        _ => {
+                            let code = args.next().ok_or("Missing error code after explain")?;
+                            Command::Explain(code.clone())
+                        }
+                    });
+                }
+                _ => {
+                    if let Some(name) = arg.strip_prefix("--error-format=") {
+                        error_format = ErrorFormat::parse(name)
+                            .ok_or_else(|| format!("Unknown error format '{name}'"))?;
+                    } else if arg.starts_with("--") {
+                        return Err(format!("Unknown option '{arg}'"));
+                    } else if path.is_none() {
+                        path = Some(arg.clone());
+                    } else {
+                        return Err(format!(
+                            "Unexpected argument '{arg}', pass script arguments after '--'"
+                        ));
                     }
-                    other => panic!("expected function body, found {other:?}"),
                 }
             }
-            other => panic!("expected function declaration, found {other:?}"),
         }
-    }
 
-    #[test]
-    fn parser_handles_if_else_blocks() {
-        let source = r#"
-if true {
-    print 1;
-} else {
-    while false {
-        break;
-    }
-}
-"#;
-        let nodes = parse_source(source).expect("failed to parse conditional");
-        assert_eq!(nodes.len(), 1);
-        match &nodes[0] {
-            ASTNode::If {
-                guard,
-                then_body,
-                else_body,
-                ..
-            } => {
-                assert!(
-                    matches!(guard.as_ref(), ASTNode::BoolLiteral { value, .. } if value == "true")
-                );
-                match then_body.as_ref() {
-                    ASTNode::Body {
-                        nodes: then_nodes, ..
-                    } => {
-                        assert_eq!(then_nodes.len(), 1);
-                        assert!(matches!(then_nodes[0], ASTNode::Print { .. }));
-                    }
-                    other => panic!("expected then body, found {other:?}"),
-                }
-                let else_body = else_body
-                    .as_ref()
-                    .expect("else branch should have been parsed");
-                match else_body.as_ref() {
-                    ASTNode::Body {
-                        nodes: else_nodes, ..
-                    } => {
-                        assert_eq!(else_nodes.len(), 1);
-                        assert!(matches!(else_nodes[0], ASTNode::While { .. }));
-                    }
-                    other => panic!("expected else body, found {other:?}"),
-                }
+        let command = match (command, &path) {
+            (Some(command), _) => command,
+            (None, Some(_)) => Command::Run,
+            (None, None) => Command::Repl,
+        };
+        let path = match (&command, path) {
+            (Command::Explain(_) | Command::Help, _) | (Command::Repl, None) => String::new(),
+            (Command::Repl, Some(_)) => {
+                return Err("'repl' does not take a source file".to_string());
             }
-            other => panic!("expected if statement, found {other:?}"),
+            (_, Some(path)) => path,
+            (_, None) => return Err("Missing source file".to_string()),
+        };
+        if !script_args.is_empty() && command != Command::Run {
+            return Err("Script arguments are only accepted by 'run'".to_string());
+        }
+        if json && !matches!(command, Command::Tokens | Command::Ast) {
+            return Err("--json is only accepted by 'tokens' and 'ast'".to_string());
+        }
+        if vm && command != Command::Run {
+            return Err("--vm is only accepted by 'run'".to_string());
         }
+        Ok(Options {
+            command,
+            path,
+            error_format,
+            json,
+            vm,
+            script_args,
+        })
     }
-
-    #[test]
-    fn typechecker_accepts_well_typed_program() {
-        let source = r#"
-fn add(a: int, b: int) -> int {
-    return a + b;
 }
-let answer: int = add(40, 2);
-print answer;
-"#;
-        let nodes = parse_source(source).expect("failed to parse program");
-        type_check_nodes(&nodes).expect("program should type check");
-    }
 
-    #[test]
-    fn typechecker_rejects_assignment_type_mismatch() {
-        let nodes =
-            parse_source("let x: int = true;").expect("failed to parse assignment mismatch source");
-        let err =
-            type_check_nodes(&nodes).expect_err("type checker should reject mismatched types");
-        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
-        assert!(
-            err.message.contains("Type mismatch"),
-            "unexpected error message: {}",
-            err.message
-        );
-    }
-
-    #[test]
-    fn typechecker_flags_missing_return() {
-        let source = r#"
-fn bad(n: int) -> int {
-    if n > 0 {
-        return n;
+fn explain(code: &str) {
+    match ErrorCode::parse(code) {
+        Some(code) => print!("{}", code.explanation()),
+        None => {
+            eprintln!("Error: '{code}' is not a Flavor error code");
+            std::process::exit(1);
+        }
     }
 }
-"#;
-        let nodes = parse_source(source).expect("failed to parse missing return function");
-        let err = type_check_nodes(&nodes).expect_err("type checker should require returns");
-        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
-        assert!(
-            err.message.contains("does not guarantee a return"),
-            "unexpected error message: {}",
-            err.message
-        );
-    }
 
-    #[test]
-    fn typechecker_detects_array_element_type_mismatch() {
-        let nodes = parse_source("let xs: [int] = [1, true];")
-            .expect("failed to parse array literal source");
-        let err = type_check_nodes(&nodes).expect_err("type checker should reject mixed arrays");
-        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
-        assert!(
-            err.message
-                .contains("Array elements must be of the same type")
-                || err.message.contains("Array literal element type mismatch"),
-            "unexpected error message: {}",
-            err.message
-        );
+fn read_source(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|err| format!("Failed to read standard input: {err}"))?;
+        return Ok(source);
     }
-
-    #[test]
-    fn typechecker_requires_boolean_condition_in_while() {
-        let source = r#"
-let counter: int = 0;
-while counter {
-    break;
-}
-"#;
-        let nodes = parse_source(source).expect("failed to parse while loop");
-        let err = type_check_nodes(&nodes).expect_err("type checker should reject non-bool guard");
-        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
-        assert!(
-            err.message.contains("While"),
-            "unexpected error message: {}",
-            err.message
-        );
+    if !path.ends_with(".flv") {
+        return Err("Source file must have a .flv extension".to_string());
     }
-
-    #[test]
-    fn typechecker_rejects_non_boolean_if_guard() {
-        let source = r#"
-if 1 {
-    print 1;
+    std::fs::read_to_string(path).map_err(|err| format!("Failed to read '{path}': {err}"))
 }
-"#;
-        let nodes = parse_source(source).expect("failed to parse if statement");
-        let err = type_check_nodes(&nodes).expect_err("type checker should reject non-bool guard");
-        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
-        assert!(
-            err.message.contains("If"),
-            "unexpected error message: {}",
-            err.message
-        );
-    }
 
-    #[test]
-    fn typechecker_detects_function_argument_mismatch() {
-        let source = r#"
-fn add(a: int, b: int) -> int {
-    return a + b;
+/// The type of the `args` global holding the script arguments.
+fn script_args_type() -> Type {
+    Type::Array(Box::new(Type::String))
 }
-let value = add(1);
-"#;
-        let nodes = parse_source(source).expect("failed to parse mismatched call");
-        let err =
-            type_check_nodes(&nodes).expect_err("type checker should reject arity mismatches");
-        assert!(matches!(err.phase, ErrorPhase::TypeChecking));
-        assert!(
-            err.message.contains("argument count"),
-            "unexpected error message: {}",
-            err.message
-        );
-    }
 
-    #[test]
-    fn typechecker_accepts_matrix_manipulation() {
-        let source = r#"
-let matrix: [[int]] = [[1, 2], [3, 4]];
-matrix[0][1] = matrix[1][0];
-matrix[1][1]++;
-let value: int = matrix[0][1] + matrix[1][1];
-"#;
-        let nodes = parse_source(source).expect("failed to parse matrix manipulation source");
-        type_check_nodes(&nodes).expect("matrix program should type check");
-    }
-
-    #[test]
-    fn interpreter_evaluates_arithmetic_and_assignment() {
-        let source = r#"
-let x: int = 1;
-x = x + 41;
-x;
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 42),
-            other => panic!("expected integer result, found {other:?}"),
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("Error: {message}\n\n{USAGE}");
+            std::process::exit(1);
         }
+    };
+    match &options.command {
+        Command::Explain(code) => return explain(code),
+        Command::Help => return println!("{USAGE}"),
+        Command::Repl => return Repl::new().run(),
+        _ => {}
     }
 
-    #[test]
-    fn interpreter_supports_closures_and_calls() {
-        let source = r#"
-let base: int = 10;
-let make_adder = <offset: int> -> (int) -> int {
-    return <value: int> -> int {
-        return value + offset + base;
+    let code = read_source(&options.path).unwrap_or_else(|message| {
+        eprintln!("Error: {message}");
+        std::process::exit(1);
+    });
+    let reporter = Reporter {
+        file: if options.path == "-" {
+            "<stdin>"
+        } else {
+            &options.path
+        },
+        source: &code,
+        format: options.error_format,
     };
-};
-let add_five = make_adder(5);
-add_five(3);
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 18),
-            other => panic!("expected integer result, found {other:?}"),
+
+    if options.command == Command::Tokens {
+        let mut lexer = Lexer::new(&code);
+        if let Err(err) = lexer.lexe() {
+            reporter.report_and_exit(vec![err]);
+        }
+        if options.json {
+            println!(
+                "{}",
+                Json::Array(lexer.tokens.iter().map(Json::from).collect())
+            );
+        } else {
+            for token in &lexer.tokens {
+                let span = token.span;
+                println!(
+                    "{}:{}-{}:{} {:?} {:?}",
+                    span.start_line,
+                    span.start_column,
+                    span.end_line,
+                    span.end_column,
+                    token.tok_name,
+                    token.lexeme
+                );
+            }
         }
+        return;
     }
 
-    #[test]
-    fn interpreter_handles_while_and_break() {
-        let source = r#"
-let total: int = 0;
-let current: int = 0;
-while true {
-    if current == 5 {
-        break;
-    }
-    total = total + current;
-    current++;
-}
-total;
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 10),
-            other => panic!("expected integer result, found {other:?}"),
+    let nodes = Engine::parse(&code).unwrap_or_else(|errors| reporter.report_and_exit(errors));
+
+    if options.command == Command::Ast {
+        if options.json {
+            println!("{}", Json::Array(nodes.iter().map(Json::from).collect()));
+        } else {
+            for node in &nodes {
+                println!("{node:#?}");
+            }
         }
+        return;
     }
 
-    #[test]
-    fn interpreter_performs_array_indexing_and_assignment() {
-        let source = r#"
-let values: [int] = [0, 1, 2];
-values[1] = 10;
-values[1];
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 10),
-            other => panic!("expected integer result, found {other:?}"),
-        }
+    let mut engine = Engine::with_backend(if options.vm {
+        Backend::Bytecode
+    } else {
+        Backend::Interpreter
+    });
+    engine.define_global(
+        "args",
+        script_args_type(),
+        EvaluationType::Array(Rc::new(
+            options
+                .script_args
+                .into_iter()
+                .map(EvaluationType::String)
+                .collect(),
+        )),
+    );
+    if let Err(errors) = engine.check(&nodes) {
+        reporter.report_and_exit(errors);
     }
 
-    #[test]
-    fn interpreter_reports_runtime_errors_for_out_of_bounds_access() {
-        let source = r#"
-let data: [int] = [0];
-data[5];
-"#;
-        let err = evaluate_source(source).expect_err("runtime error expected");
-        assert!(matches!(err.phase, ErrorPhase::Runtime));
-        assert!(
-            err.message.contains("out of bounds"),
-            "unexpected error message: {}",
-            err.message
-        );
+    reporter.report(&Linter::new().lint_program(&nodes));
+
+    if options.command == Command::Check {
+        return;
     }
 
-    #[test]
-    fn interpreter_executes_recursive_function() {
-        let source = r#"
-fn factorial(n: int) -> int {
-    if n <= 1 {
-        return 1;
+    if let Err(err) = engine.run(&nodes) {
+        reporter.report_and_exit(vec![err]);
     }
-    return n * factorial(n - 1);
 }
-factorial(5);
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 120),
-            other => panic!("expected integer result, found {other:?}"),
-        }
-    }
 
-    #[test]
-    fn interpreter_manages_matrices_with_mixed_increments() {
-        let source = r#"
-let matrix: [[int]] = [[1, 2], [3, 4]];
-matrix[0][1] = matrix[1][0];
-matrix[1][1]++;
-++matrix[0][0];
-matrix[0][1] + matrix[1][1] + matrix[0][0];
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 10),
-            other => panic!("expected integer result after matrix manipulation, found {other:?}"),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_options(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Options::parse(&args)
     }
 
     #[test]
-    fn interpreter_closure_clones_share_state() {
-        let source = r#"
-let make_tick = <start: int> -> () -> int {
-    let value: int = start;
-    return <> -> int {
-        ++value;
-        return value;
-    };
-};
+    fn cli_parses_subcommands_options_and_script_args() {
+        let options = parse_options(&["main.flv"]).unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(options.path, "main.flv");
+
+        let options =
+            parse_options(&["run", "--error-format=json", "-", "--", "a", "--json"]).unwrap();
+        assert_eq!(options.path, "-");
+        assert_eq!(options.error_format, ErrorFormat::Json);
+        assert_eq!(options.script_args, vec!["a", "--json"]);
+
+        let options = parse_options(&["ast", "main.flv", "--json"]).unwrap();
+        assert_eq!((options.command, options.json), (Command::Ast, true));
+        assert_eq!(
+            parse_options(&["explain", "F0203"]).unwrap().command,
+            Command::Explain("F0203".to_string())
+        );
 
-let ticker = make_tick(7);
-let twin = ticker;
-ticker();
-twin();
-ticker();
-"#;
-        match evaluate_source(source).expect("program should run") {
-            EvaluationType::Int(value) => assert_eq!(value, 10),
-            other => {
-                panic!("expected integer result showing shared closure state, found {other:?}")
-            }
-        }
+        assert!(parse_options(&["check", "main.flv", "extra"]).is_err());
+        assert!(parse_options(&["check", "main.flv", "--", "a"]).is_err());
+        assert!(parse_options(&["run", "main.flv", "--json"]).is_err());
+        assert!(parse_options(&["run", "--vm", "main.flv"]).unwrap().vm);
+        assert!(parse_options(&["check", "--vm", "main.flv"]).is_err());
+        assert!(parse_options(&["tokens"]).is_err());
+        assert_eq!(parse_options(&[]).unwrap().command, Command::Repl);
+        assert!(parse_options(&["repl", "main.flv"]).is_err());
     }
 }
@@ -0,0 +1,456 @@
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::error::{ErrorPhase, FlavorError};
+use crate::error_codes::ErrorCode;
+use crate::interpreter::EvaluationType as ET;
+use crate::types::Span;
+
+/// Binary operators other than assignment. Their runtime behavior lives here so the tree-walking
+/// interpreter and the bytecode VM cannot drift apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    pub(crate) fn parse(operator: &str) -> Option<Self> {
+        Some(match operator {
+            "+" => BinaryOperator::Add,
+            "-" => BinaryOperator::Subtract,
+            "*" => BinaryOperator::Multiply,
+            "/" => BinaryOperator::Divide,
+            "%" => BinaryOperator::Remainder,
+            "==" => BinaryOperator::Equal,
+            "!=" => BinaryOperator::NotEqual,
+            "<" => BinaryOperator::Less,
+            "<=" => BinaryOperator::LessEqual,
+            ">" => BinaryOperator::Greater,
+            ">=" => BinaryOperator::GreaterEqual,
+            "&&" => BinaryOperator::And,
+            "||" => BinaryOperator::Or,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        }
+    }
+}
+
+fn runtime_error(message: impl Into<String>, span: Span, code: ErrorCode) -> FlavorError {
+    FlavorError::with_span(ErrorPhase::Runtime, message, span).with_code(code)
+}
+
+/// Applies `operator` to operands that are both already evaluated: `&&` and `||` do not
+/// short-circuit.
+pub(crate) fn binary(
+    operator: BinaryOperator,
+    left: ET,
+    right: ET,
+    span: Span,
+) -> Result<ET, FlavorError> {
+    use BinaryOperator as Op;
+
+    Ok(match (operator, left, right) {
+        (Op::Add, ET::Int(l), ET::Int(r)) => checked(l.checked_add(r), l, operator, r, span)?,
+        (Op::Add, ET::Float(l), ET::Float(r)) => ET::Float(l + r),
+        (Op::Add, ET::String(l), ET::String(r)) => ET::String(format!("{l}{r}")),
+        (Op::Subtract, ET::Int(l), ET::Int(r)) => checked(l.checked_sub(r), l, operator, r, span)?,
+        (Op::Subtract, ET::Float(l), ET::Float(r)) => ET::Float(l - r),
+        (Op::Multiply, ET::Int(l), ET::Int(r)) => checked(l.checked_mul(r), l, operator, r, span)?,
+        (Op::Multiply, ET::Float(l), ET::Float(r)) => ET::Float(l * r),
+        (Op::Divide, ET::Int(_), ET::Int(0)) => {
+            return Err(runtime_error(
+                "Division by zero",
+                span,
+                ErrorCode::DivisionByZero,
+            ));
+        }
+        (Op::Divide, ET::Int(l), ET::Int(r)) => checked(l.checked_div(r), l, operator, r, span)?,
+        (Op::Divide, ET::Float(_), ET::Float(0.0)) => {
+            return Err(runtime_error(
+                "Division by zero",
+                span,
+                ErrorCode::DivisionByZero,
+            ));
+        }
+        (Op::Divide, ET::Float(l), ET::Float(r)) => ET::Float(l / r),
+        (Op::Remainder, ET::Int(_), ET::Int(0)) => {
+            return Err(runtime_error(
+                "Modulo by zero",
+                span,
+                ErrorCode::DivisionByZero,
+            ));
+        }
+        (Op::Remainder, ET::Int(l), ET::Int(r)) => checked(l.checked_rem(r), l, operator, r, span)?,
+        (Op::Equal | Op::NotEqual, left, right) => {
+            let Some(equal) = equal(&left, &right) else {
+                return Err(unsupported_binary(operator, &left, &right, span));
+            };
+            ET::Bool(equal == (operator == Op::Equal))
+        }
+        (Op::Less, ET::Int(l), ET::Int(r)) => ET::Bool(l < r),
+        (Op::Less, ET::Float(l), ET::Float(r)) => ET::Bool(l < r),
+        (Op::LessEqual, ET::Int(l), ET::Int(r)) => ET::Bool(l <= r),
+        (Op::LessEqual, ET::Float(l), ET::Float(r)) => ET::Bool(l <= r),
+        (Op::Greater, ET::Int(l), ET::Int(r)) => ET::Bool(l > r),
+        (Op::Greater, ET::Float(l), ET::Float(r)) => ET::Bool(l > r),
+        (Op::GreaterEqual, ET::Int(l), ET::Int(r)) => ET::Bool(l >= r),
+        (Op::GreaterEqual, ET::Float(l), ET::Float(r)) => ET::Bool(l >= r),
+        (Op::And, ET::Bool(l), ET::Bool(r)) => ET::Bool(l && r),
+        (Op::Or, ET::Bool(l), ET::Bool(r)) => ET::Bool(l || r),
+        (operator, left, right) => return Err(unsupported_binary(operator, &left, &right, span)),
+    })
+}
+
+/// Compares two values of the same type, arrays element by element, structs field by field
+/// and enum values by variant and payload. Functions cannot be compared, which gives `None`.
+fn equal(left: &ET, right: &ET) -> Option<bool> {
+    Some(match (left, right) {
+        (ET::Unit, ET::Unit) => true,
+        (ET::Int(l), ET::Int(r)) => l == r,
+        (ET::Float(l), ET::Float(r)) => l == r,
+        (ET::Bool(l), ET::Bool(r)) => l == r,
+        (ET::String(l), ET::String(r)) => l == r,
+        (ET::Array(l), ET::Array(r)) => all_equal(l, r)?,
+        (
+            ET::Struct { name, fields },
+            ET::Struct {
+                name: other_name,
+                fields: other_fields,
+            },
+        ) => {
+            if name != other_name || fields.len() != other_fields.len() {
+                return Some(false);
+            }
+            for (field, value) in fields {
+                let (_, other) = other_fields.iter().find(|(other, _)| other == field)?;
+                if !equal(value, other)? {
+                    return Some(false);
+                }
+            }
+            true
+        }
+        (
+            ET::Variant {
+                enum_name,
+                variant,
+                payload,
+            },
+            ET::Variant {
+                enum_name: other_enum,
+                variant: other_variant,
+                payload: other_payload,
+            },
+        ) => {
+            enum_name == other_enum
+                && variant == other_variant
+                && all_equal(payload, other_payload)?
+        }
+        _ => return None,
+    })
+}
+
+fn all_equal(left: &[ET], right: &[ET]) -> Option<bool> {
+    if left.len() != right.len() {
+        return Some(false);
+    }
+    for (left, right) in left.iter().zip(right) {
+        if !equal(left, right)? {
+            return Some(false);
+        }
+    }
+    Some(true)
+}
+
+fn overflow(operation: String, span: Span) -> FlavorError {
+    runtime_error(
+        format!("Integer overflow: {operation} does not fit in an int"),
+        span,
+        ErrorCode::IntegerOverflow,
+    )
+}
+
+/// The result of integer arithmetic, which is `None` when it overflowed.
+fn checked(
+    result: Option<i64>,
+    left: i64,
+    operator: BinaryOperator,
+    right: i64,
+    span: Span,
+) -> Result<ET, FlavorError> {
+    result
+        .map(ET::Int)
+        .ok_or_else(|| overflow(format!("{left} {} {right}", operator.as_str()), span))
+}
+
+fn unsupported_binary(operator: BinaryOperator, left: &ET, right: &ET, span: Span) -> FlavorError {
+    runtime_error(
+        format!(
+            "Unsupported binary operation: {left:?} {} {right:?}",
+            operator.as_str()
+        ),
+        span,
+        ErrorCode::RuntimeTypeError,
+    )
+}
+
+fn unsupported_unary(operator: &str, is_postfix: bool, value: &ET, span: Span) -> FlavorError {
+    runtime_error(
+        format!("Unsupported unary operation: {operator} (postfix: {is_postfix}) on {value:?}"),
+        span,
+        ErrorCode::RuntimeTypeError,
+    )
+}
+
+/// Prefix `-`. `span` is the span of the whole expression.
+pub(crate) fn negate(value: ET, span: Span) -> Result<ET, FlavorError> {
+    match value {
+        ET::Int(value) => value
+            .checked_neg()
+            .map(ET::Int)
+            .ok_or_else(|| overflow(format!("-({value})"), span)),
+        ET::Float(value) => Ok(ET::Float(-value)),
+        value => Err(unsupported_unary("-", false, &value, span)),
+    }
+}
+
+/// Prefix `!`. `span` is the span of the operand.
+pub(crate) fn not(value: ET, span: Span) -> Result<ET, FlavorError> {
+    match value {
+        ET::Bool(value) => Ok(ET::Bool(!value)),
+        value => Err(unsupported_unary("!", false, &value, span)),
+    }
+}
+
+/// Applies `++` (`delta` 1) or `--` (`delta` -1) to `target` in place and returns the old value
+/// for the postfix form or the new one for the prefix form. `span` is the span of the whole
+/// expression.
+pub(crate) fn increment(
+    target: &mut ET,
+    delta: i64,
+    is_postfix: bool,
+    span: Span,
+) -> Result<ET, FlavorError> {
+    match target {
+        ET::Int(value) => {
+            let old = *value;
+            *value = old.checked_add(delta).ok_or_else(|| {
+                let sign = if delta > 0 { '+' } else { '-' };
+                overflow(format!("{old} {sign} 1"), span)
+            })?;
+            Ok(ET::Int(if is_postfix { old } else { *value }))
+        }
+        other => {
+            let operator = if delta > 0 { "++" } else { "--" };
+            Err(unsupported_unary(operator, is_postfix, other, span))
+        }
+    }
+}
+
+/// Reads `field` out of a struct value.
+pub(crate) fn field(object: ET, field: &str, span: Span) -> Result<ET, FlavorError> {
+    match object {
+        ET::Struct { name, fields } => fields
+            .into_iter()
+            .find(|(field_name, _)| field_name == field)
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                runtime_error(
+                    format!("Struct '{name}' has no field named '{field}'"),
+                    span,
+                    ErrorCode::RuntimeTypeError,
+                )
+            }),
+        other => Err(runtime_error(
+            format!("Cannot access field '{field}' on {}", other.type_name()),
+            span,
+            ErrorCode::RuntimeTypeError,
+        )),
+    }
+}
+
+/// Reads the element at `index` out of an array value.
+pub(crate) fn index(array: ET, index: ET, span: Span) -> Result<ET, FlavorError> {
+    match (array, index) {
+        (ET::Array(_), ET::Int(idx)) if idx < 0 => Err(runtime_error(
+            "Negative array index",
+            span,
+            ErrorCode::NegativeIndex,
+        )),
+        (ET::Array(values), ET::Int(idx)) => {
+            let idx = idx as usize;
+            if idx < values.len() {
+                Ok(values[idx].clone())
+            } else {
+                Err(runtime_error(
+                    "Array index out of bounds",
+                    span,
+                    ErrorCode::IndexOutOfBounds,
+                ))
+            }
+        }
+        _ => Err(runtime_error(
+            "Invalid array access",
+            span,
+            ErrorCode::RuntimeTypeError,
+        )),
+    }
+}
+
+/// Checks a value used as the index of an assignment target. `span` is the span of the index.
+pub(crate) fn place_index(index: &ET, span: Span) -> Result<usize, FlavorError> {
+    match index {
+        ET::Int(index) if *index < 0 => Err(runtime_error(
+            "Negative array index",
+            span,
+            ErrorCode::NegativeIndex,
+        )),
+        ET::Int(index) => Ok(*index as usize),
+        _ => Err(runtime_error(
+            "Array index must be an integer",
+            span,
+            ErrorCode::RuntimeTypeError,
+        )),
+    }
+}
+
+/// One step taken from a variable to reach the value written by an assignment.
+pub(crate) enum PlaceStep<'a> {
+    Index {
+        index: usize,
+        index_span: Span,
+        array_span: Span,
+    },
+    Field {
+        name: &'a str,
+        span: Span,
+    },
+}
+
+/// Follows `steps` from `root`, the value of the variable `base_name`, to the value an
+/// assignment, an increment or a mutating builtin writes to.
+pub(crate) fn place_mut<'v, 'a>(
+    root: &'v mut ET,
+    base_name: &str,
+    base_span: Span,
+    steps: impl IntoIterator<Item = PlaceStep<'a>>,
+) -> Result<&'v mut ET, FlavorError> {
+    let mut target = root;
+    for (depth, step) in steps.into_iter().enumerate() {
+        match step {
+            PlaceStep::Index {
+                index,
+                index_span,
+                array_span,
+            } => match target {
+                ET::Array(arr) => {
+                    if index >= arr.len() {
+                        let message = if depth == 0 {
+                            let len = arr.len();
+                            format!(
+                                "Index {index} out of bounds for array '{base_name}' of length {len}"
+                            )
+                        } else {
+                            "Array index out of bounds".to_string()
+                        };
+                        return Err(runtime_error(
+                            message,
+                            index_span,
+                            ErrorCode::IndexOutOfBounds,
+                        ));
+                    }
+                    target = &mut Rc::make_mut(arr)[index];
+                }
+                _ => {
+                    let span = if depth == 0 { base_span } else { array_span };
+                    let message = if depth == 0 {
+                        format!("Variable '{base_name}' is not an array")
+                    } else {
+                        "Value is not an array".to_string()
+                    };
+                    return Err(runtime_error(message, span, ErrorCode::RuntimeTypeError));
+                }
+            },
+            PlaceStep::Field { name, span } => match target {
+                ET::Struct {
+                    name: struct_name,
+                    fields,
+                } => {
+                    let struct_name = struct_name.clone();
+                    target = fields
+                        .iter_mut()
+                        .find(|(field, _)| field == name)
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| {
+                            runtime_error(
+                                format!("Struct '{struct_name}' has no field named '{name}'"),
+                                span,
+                                ErrorCode::RuntimeTypeError,
+                            )
+                        })?;
+                }
+                _ => {
+                    return Err(runtime_error(
+                        format!("Cannot access field '{name}' on a non-struct value"),
+                        span,
+                        ErrorCode::RuntimeTypeError,
+                    ));
+                }
+            },
+        }
+    }
+    Ok(target)
+}
+
+/// Checks the number of arguments given to a function taking `expected` parameters.
+pub(crate) fn check_arity(expected: usize, found: usize, span: Span) -> Result<(), FlavorError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(runtime_error(
+            format!("Expected {expected} arguments but got {found}"),
+            span,
+            ErrorCode::RuntimeTypeError,
+        ))
+    }
+}
+
+/// Writes the values of a `print` statement on one line of `output`.
+pub(crate) fn print(output: &mut dyn Write, values: &[ET], span: Span) -> Result<(), FlavorError> {
+    let line: String = values.iter().map(|value| value.to_string()).collect();
+    writeln!(output, "{line}").map_err(|err| {
+        runtime_error(
+            format!("Failed to write output: {err}"),
+            span,
+            ErrorCode::IoFailed,
+        )
+    })
+}
@@ -1,22 +1,59 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
 use crate::error::{ErrorPhase, FlavorError};
-use crate::types::{ASTNode, Span, Token, TokenName as TN, Type};
+use crate::error_codes::ErrorCode;
+use crate::types::{ASTNode, MatchArm, Parameter, Pattern, Span, Token, TokenName as TN, Type};
 
 type ParseProduction = Result<ASTNode, FlavorError>;
 
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    allow_struct_literal: bool,
+    errors: Vec<FlavorError>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+        // Doc comments are kept by the lexer for tooling, the grammar does not use them
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| token.tok_name != TN::DocComment)
+            .collect();
+        Parser {
+            tokens,
+            pos: 0,
+            allow_struct_literal: true,
+            errors: Vec::new(),
+        }
     }
 
     fn current_tok(&self) -> &Token {
         &self.tokens[self.pos]
     }
 
+    /// Looks at the token `offset` positions after the current one without consuming anything.
+    /// Peeking past the end yields the final `Eof` token.
+    fn peek_tok(&self, offset: usize) -> &Token {
+        let index = (self.pos + offset).min(self.tokens.len() - 1);
+        &self.tokens[index]
+    }
+
+    /// Runs `f` with struct literals enabled or disabled.
+    /// Guards of `if` and `while` disable them, since there a `{` after an identifier opens the
+    /// body of the statement.
+    fn with_struct_literals<F, R>(&mut self, allowed: bool, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.allow_struct_literal;
+        self.allow_struct_literal = allowed;
+        let result = f(self);
+        self.allow_struct_literal = previous;
+        result
+    }
+
     fn consume_tok(&mut self) {
         if self.pos < self.tokens.len() - 1 {
             self.pos += 1;
@@ -42,7 +79,8 @@ impl Parser {
                     expected, tok.tok_name, tok.lexeme
                 ),
                 tok.span,
-            ))
+            )
+            .with_code(ErrorCode::UnexpectedToken))
         }
     }
 
@@ -62,12 +100,79 @@ impl Parser {
         }
     }
 
-    pub fn parse_program(&mut self) -> Result<Vec<ASTNode>, FlavorError> {
+    /// Parses the whole token stream.
+    /// Syntax errors do not stop the parser: it skips to the next statement and keeps going,
+    /// so the returned AST is partial whenever the returned errors are not empty.
+    pub fn parse_program(&mut self) -> (Vec<ASTNode>, Vec<FlavorError>) {
         let mut nodes = Vec::new();
         while self.current_tok().tok_name != TN::Eof {
-            nodes.push(self.parse_statement()?);
+            if let Some(statement) = self.parse_statement_recovering() {
+                nodes.push(statement);
+            }
+        }
+        (nodes, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses a statement, recording the error and synchronizing on failure.
+    fn parse_statement_recovering(&mut self) -> Option<ASTNode> {
+        let start = self.pos;
+        match self.parse_statement() {
+            Ok(statement) => Some(statement),
+            Err(err) => {
+                // A token ending a failed statement fails again when it cannot start the next
+                // one, so it is only reported once.
+                let repeated = err.span.is_some()
+                    && self.errors.last().is_some_and(|last| last.span == err.span);
+                if !repeated {
+                    self.errors.push(err);
+                }
+                if self.pos == start {
+                    // The offending token would otherwise be parsed again forever
+                    self.consume_tok();
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    /// Panic-mode recovery: skips tokens until a likely statement boundary.
+    /// Stops after a `;`, or before a `}` closing the enclosing body or a statement keyword.
+    /// Blocks opened while skipping are skipped as a whole.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.current_tok().tok_name {
+                TN::Eof => return,
+                TN::Semicolon if depth == 0 => {
+                    self.consume_tok();
+                    return;
+                }
+                TN::RBra if depth == 0 => return,
+                TN::RBra => depth -= 1,
+                TN::LBra => depth += 1,
+                TN::Let
+                | TN::Fn
+                | TN::Print
+                | TN::Alias
+                | TN::Struct
+                | TN::Enum
+                | TN::Match
+                | TN::If
+                | TN::While
+                | TN::For
+                | TN::Return
+                | TN::Break
+                | TN::Continue
+                | TN::Label
+                    if depth == 0 =>
+                {
+                    return;
+                }
+                _ => {}
+            }
+            self.consume_tok();
         }
-        Ok(nodes)
     }
 
     fn parse_statement(&mut self) -> ParseProduction {
@@ -75,13 +180,19 @@ impl Parser {
             TN::Print => self.parse_print_statement(),
             TN::Let => self.parse_let_statement(),
             TN::Fn => self.parse_function_declaration(),
+            TN::Alias => self.parse_alias_declaration(),
+            TN::Struct => self.parse_struct_declaration(),
+            TN::Enum => self.parse_enum_declaration(),
+            TN::Match => self.parse_match_statement(),
             TN::If => self.parse_if(),
-            TN::While => self.parse_while(),
+            TN::While => self.parse_while(None),
+            TN::For => self.parse_for(None),
+            TN::Label => self.parse_labeled_loop(),
             TN::Return => self.parse_return(),
             TN::Break => self.parse_break(),
+            TN::Continue => self.parse_continue(),
             TN::LBra => self.parse_body(),
             TN::Identifier => self.parse_expression_statement(),
-            // TODO: aliasing, ecc...
             _ => self.parse_expression_statement(),
         }
     }
@@ -104,23 +215,101 @@ impl Parser {
         Ok(ASTNode::Print { expressions, span })
     }
 
-    fn parse_while(&mut self) -> ParseProduction {
+    /// Parses a loop preceded by a label, as in `'outer: while cond { ... }`.
+    fn parse_labeled_loop(&mut self) -> ParseProduction {
+        let label_tok = self.expect_tok(TN::Label)?;
+        self.expect_tok(TN::Colon)?;
+        let label = Some(Self::label_name(&label_tok));
+        let mut loop_node = match self.current_tok().tok_name {
+            TN::While => self.parse_while(label)?,
+            TN::For => self.parse_for(label)?,
+            _ => {
+                let tok = self.current_tok().clone();
+                return Err(FlavorError::with_span(
+                    ErrorPhase::Parsing,
+                    format!(
+                        "Expected a loop after label '{}', found {:?} ('{}')",
+                        Self::label_name(&label_tok),
+                        tok.tok_name,
+                        tok.lexeme
+                    ),
+                    tok.span,
+                )
+                .with_code(ErrorCode::LabelWithoutLoop));
+            }
+        };
+        if let ASTNode::While { span, .. } | ASTNode::For { span, .. } = &mut loop_node {
+            *span = label_tok.span.merge(span);
+        }
+        Ok(loop_node)
+    }
+
+    /// Strips the leading `'` from a label token.
+    fn label_name(label_tok: &Token) -> String {
+        label_tok.lexeme.trim_start_matches('\'').to_string()
+    }
+
+    /// Parses the optional label following `break` or `continue`.
+    fn parse_optional_label(&mut self) -> Result<(Option<String>, Option<Span>), FlavorError> {
+        if self.current_tok().tok_name == TN::Label {
+            let label_tok = self.expect_tok(TN::Label)?;
+            Ok((Some(Self::label_name(&label_tok)), Some(label_tok.span)))
+        } else {
+            Ok((None, None))
+        }
+    }
+
+    fn parse_while(&mut self, label: Option<String>) -> ParseProduction {
         let while_tok = self.expect_tok(TN::While)?;
-        let guard = self.parse_expression()?;
+        let guard = self.with_struct_literals(false, |p| p.parse_expression())?;
         let body = self.parse_body()?;
 
         let span = while_tok.span.merge(guard.span()).merge(body.span());
 
         Ok(ASTNode::While {
+            label,
             guard: Box::new(guard),
             body: Box::new(body),
             span,
         })
     }
 
+    /// Parses `for name in iterable { ... }`, where the iterable is either an array expression
+    /// or a range `start..end` / `start..=end`.
+    fn parse_for(&mut self, label: Option<String>) -> ParseProduction {
+        let for_tok = self.expect_tok(TN::For)?;
+        let variable = self.expect_tok(TN::Identifier)?;
+        self.expect_tok(TN::In)?;
+        let mut iterable = self.with_struct_literals(false, |p| p.parse_expression())?;
+
+        if matches!(self.current_tok().tok_name, TN::DotDot | TN::DotDotEq) {
+            let inclusive = self.current_tok().tok_name == TN::DotDotEq;
+            self.consume_tok();
+            let end = self.with_struct_literals(false, |p| p.parse_expression())?;
+            let span = iterable.span().merge(end.span());
+            iterable = ASTNode::Range {
+                start: Box::new(iterable),
+                end: Box::new(end),
+                inclusive,
+                span,
+            };
+        }
+
+        let body = self.parse_body()?;
+        let span = for_tok.span.merge(body.span());
+
+        Ok(ASTNode::For {
+            label,
+            variable: variable.lexeme,
+            iterable: Box::new(iterable),
+            body: Box::new(body),
+            span,
+        })
+    }
+
     fn parse_if(&mut self) -> ParseProduction {
         let if_tok = self.expect_tok(TN::If)?;
-        let guard = self.parse_expression()?;
+        let guard = self.with_struct_literals(false, |p| p.parse_expression())?;
         let then_body = self.parse_body()?;
 
         let mut span = if_tok.span.merge(guard.span()).merge(then_body.span());
@@ -143,11 +332,28 @@ impl Parser {
     }
     fn parse_break(&mut self) -> ParseProduction {
         let break_tok = self.expect_tok(TN::Break)?;
+        let (label, label_span) = self.parse_optional_label()?;
         let semicolon = self.expect_tok(TN::Semicolon)?;
         let _span = break_tok.span.merge(&semicolon.span);
 
         Ok(ASTNode::Break {
-            span: break_tok.span,
+            label,
+            span: label_span.map_or(break_tok.span, |label_span| {
+                break_tok.span.merge(&label_span)
+            }),
+        })
+    }
+
+    fn parse_continue(&mut self) -> ParseProduction {
+        let continue_tok = self.expect_tok(TN::Continue)?;
+        let (label, label_span) = self.parse_optional_label()?;
+        self.expect_tok(TN::Semicolon)?;
+
+        Ok(ASTNode::Continue {
+            label,
+            span: label_span.map_or(continue_tok.span, |label_span| {
+                continue_tok.span.merge(&label_span)
+            }),
         })
     }
 
@@ -190,20 +396,288 @@ impl Parser {
             name: fn_name.lexeme,
             parameters,
             return_type: return_ty,
-            body: Box::new(body),
+            body: Rc::new(body),
+            span,
+            location: Cell::new(None),
+        })
+    }
+
+    fn parse_alias_declaration(&mut self) -> ParseProduction {
+        let alias_tok = self.expect_tok(TN::Alias)?;
+        let name_tok = self.expect_tok(TN::Identifier)?;
+        let assign_tok = self.expect_tok(TN::Assign)?;
+        let (aliased_type, ty_span) = self.parse_type()?;
+        let semicolon = self.expect_tok(TN::Semicolon)?;
+
+        let span = alias_tok
+            .span
+            .merge(&name_tok.span)
+            .merge(&assign_tok.span)
+            .merge(&ty_span)
+            .merge(&semicolon.span);
+
+        Ok(ASTNode::AliasDeclaration {
+            name: name_tok.lexeme,
+            aliased_type,
+            span,
+        })
+    }
+
+    fn parse_struct_declaration(&mut self) -> ParseProduction {
+        let struct_tok = self.expect_tok(TN::Struct)?;
+        let name_tok = self.expect_tok(TN::Identifier)?;
+        let lbra = self.expect_tok(TN::LBra)?;
+        let mut span = struct_tok.span.merge(&name_tok.span).merge(&lbra.span);
+
+        let mut fields: Vec<(String, Type)> = vec![];
+        while self.current_tok().tok_name != TN::RBra {
+            let field_name = self.expect_tok(TN::Identifier)?;
+            let colon_tok = self.expect_tok(TN::Colon)?;
+            let (field_ty, ty_span) = self.parse_type()?;
+            span = span
+                .merge(&field_name.span)
+                .merge(&colon_tok.span)
+                .merge(&ty_span);
+            fields.push((field_name.lexeme, field_ty));
+            if self.current_tok().tok_name == TN::Comma {
+                let comma = self.expect_tok(TN::Comma)?;
+                span = span.merge(&comma.span);
+            } else {
+                break;
+            }
+        }
+        let rbra = self.expect_tok(TN::RBra)?;
+        span = span.merge(&rbra.span);
+
+        Ok(ASTNode::StructDeclaration {
+            name: name_tok.lexeme,
+            fields,
+            span,
+        })
+    }
+
+    fn parse_enum_declaration(&mut self) -> ParseProduction {
+        let enum_tok = self.expect_tok(TN::Enum)?;
+        let name_tok = self.expect_tok(TN::Identifier)?;
+        let lbra = self.expect_tok(TN::LBra)?;
+        let mut span = enum_tok.span.merge(&name_tok.span).merge(&lbra.span);
+
+        let mut variants: Vec<(String, Vec<Type>)> = vec![];
+        while self.current_tok().tok_name != TN::RBra {
+            let variant_tok = self.expect_tok(TN::Identifier)?;
+            span = span.merge(&variant_tok.span);
+            let mut payload = vec![];
+            if self.current_tok().tok_name == TN::LPar {
+                self.expect_tok(TN::LPar)?;
+                if self.current_tok().tok_name != TN::RPar {
+                    loop {
+                        let (ty, ty_span) = self.parse_type()?;
+                        span = span.merge(&ty_span);
+                        payload.push(ty);
+                        if self.current_tok().tok_name == TN::Comma {
+                            self.expect_tok(TN::Comma)?;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                let rpar = self.expect_tok(TN::RPar)?;
+                span = span.merge(&rpar.span);
+            }
+            variants.push((variant_tok.lexeme, payload));
+            if self.current_tok().tok_name == TN::Comma {
+                let comma = self.expect_tok(TN::Comma)?;
+                span = span.merge(&comma.span);
+            } else {
+                break;
+            }
+        }
+        let rbra = self.expect_tok(TN::RBra)?;
+        span = span.merge(&rbra.span);
+
+        Ok(ASTNode::EnumDeclaration {
+            name: name_tok.lexeme,
+            variants,
+            span,
+        })
+    }
+
+    /// Parses `Enum::Variant` or `Enum::Variant(args, ...)`.
+    fn parse_enum_variant(&mut self) -> ParseProduction {
+        let enum_tok = self.expect_tok(TN::Identifier)?;
+        self.expect_tok(TN::DoubleColon)?;
+        let variant_tok = self.expect_tok(TN::Identifier)?;
+        let mut span = enum_tok.span.merge(&variant_tok.span);
+
+        let mut arguments = vec![];
+        if self.current_tok().tok_name == TN::LPar {
+            self.expect_tok(TN::LPar)?;
+            if self.current_tok().tok_name != TN::RPar {
+                loop {
+                    let arg = self.with_struct_literals(true, |p| p.parse_expression())?;
+                    span = span.merge(arg.span());
+                    arguments.push(arg);
+                    if self.current_tok().tok_name == TN::Comma {
+                        self.expect_tok(TN::Comma)?;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            let rpar = self.expect_tok(TN::RPar)?;
+            span = span.merge(&rpar.span);
+        }
+
+        Ok(ASTNode::EnumVariant {
+            enum_name: enum_tok.lexeme,
+            variant: variant_tok.lexeme,
+            arguments,
+            span,
+        })
+    }
+
+    /// A `match` in statement position does not need a trailing semicolon.
+    fn parse_match_statement(&mut self) -> ParseProduction {
+        let match_expr = self.parse_match()?;
+        if self.current_tok().tok_name == TN::Semicolon {
+            self.consume_tok();
+        }
+        Ok(match_expr)
+    }
+
+    fn parse_match(&mut self) -> ParseProduction {
+        let match_tok = self.expect_tok(TN::Match)?;
+        let scrutinee = self.with_struct_literals(false, |p| p.parse_expression())?;
+        self.expect_tok(TN::LBra)?;
+
+        let mut arms = vec![];
+        while self.current_tok().tok_name != TN::RBra {
+            let pattern = self.parse_pattern()?;
+            self.expect_tok(TN::BoldArrow)?;
+            let body = if self.current_tok().tok_name == TN::LBra {
+                self.parse_body()?
+            } else {
+                self.with_struct_literals(true, |p| p.parse_expression())?
+            };
+            let span = pattern.span().merge(body.span());
+            let is_block = matches!(body, ASTNode::Body { .. });
+            arms.push(MatchArm {
+                pattern,
+                body,
+                span,
+            });
+
+            // Expression arms are separated by commas, block arms may omit them.
+            if self.current_tok().tok_name == TN::Comma {
+                self.consume_tok();
+            } else if !is_block && self.current_tok().tok_name != TN::RBra {
+                self.expect_tok(TN::Comma)?;
+            }
+        }
+        let rbra = self.expect_tok(TN::RBra)?;
+        let span = match_tok.span.merge(scrutinee.span()).merge(&rbra.span);
+
+        Ok(ASTNode::Match {
+            scrutinee: Box::new(scrutinee),
+            arms,
+            span,
+        })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, FlavorError> {
+        let tok = self.expect_tok(TN::Identifier)?;
+        if self.current_tok().tok_name != TN::DoubleColon {
+            if tok.lexeme == "_" {
+                return Ok(Pattern::Wildcard { span: tok.span });
+            }
+            return Ok(Pattern::Binding {
+                name: tok.lexeme,
+                span: tok.span,
+            });
+        }
+
+        self.expect_tok(TN::DoubleColon)?;
+        let variant_tok = self.expect_tok(TN::Identifier)?;
+        let mut span = tok.span.merge(&variant_tok.span);
+        let mut bindings = vec![];
+        if self.current_tok().tok_name == TN::LPar {
+            self.expect_tok(TN::LPar)?;
+            if self.current_tok().tok_name != TN::RPar {
+                loop {
+                    let binding = self.expect_tok(TN::Identifier)?;
+                    bindings.push(binding.lexeme);
+                    if self.current_tok().tok_name == TN::Comma {
+                        self.expect_tok(TN::Comma)?;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            let rpar = self.expect_tok(TN::RPar)?;
+            span = span.merge(&rpar.span);
+        }
+
+        Ok(Pattern::Variant {
+            enum_name: tok.lexeme,
+            variant: variant_tok.lexeme,
+            bindings,
+            span,
+        })
+    }
+
+    /// Tells whether the identifier under the cursor starts a struct literal such as
+    /// `Point { x: 1, y: 2 }` or `Empty {}`.
+    fn at_struct_literal(&self) -> bool {
+        self.allow_struct_literal
+            && self.peek_tok(1).tok_name == TN::LBra
+            && (self.peek_tok(2).tok_name == TN::RBra
+                || (self.peek_tok(2).tok_name == TN::Identifier
+                    && self.peek_tok(3).tok_name == TN::Colon))
+    }
+
+    fn parse_struct_literal(&mut self) -> ParseProduction {
+        let name_tok = self.expect_tok(TN::Identifier)?;
+        let lbra = self.expect_tok(TN::LBra)?;
+        let mut span = name_tok.span.merge(&lbra.span);
+
+        let mut fields: Vec<(String, ASTNode)> = vec![];
+        while self.current_tok().tok_name != TN::RBra {
+            let field_name = self.expect_tok(TN::Identifier)?;
+            self.expect_tok(TN::Colon)?;
+            let value = self.with_struct_literals(true, |p| p.parse_expression())?;
+            span = span.merge(&field_name.span).merge(value.span());
+            fields.push((field_name.lexeme, value));
+            if self.current_tok().tok_name == TN::Comma {
+                let comma = self.expect_tok(TN::Comma)?;
+                span = span.merge(&comma.span);
+            } else {
+                break;
+            }
+        }
+        let rbra = self.expect_tok(TN::RBra)?;
+        span = span.merge(&rbra.span);
+
+        Ok(ASTNode::StructLiteral {
+            name: name_tok.lexeme,
+            fields,
             span,
         })
     }
 
     fn parse_body(&mut self) -> ParseProduction {
+        self.with_struct_literals(true, |p| p.parse_body_statements())
+    }
+
+    fn parse_body_statements(&mut self) -> ParseProduction {
         let lbra = self.expect_tok(TN::LBra)?;
 
         let mut statements: Vec<ASTNode> = vec![];
         let mut span = lbra.span;
-        while self.current_tok().tok_name != TN::RBra {
-            let statement = self.parse_statement()?;
-            span = span.merge(statement.span());
-            statements.push(statement);
+        while !matches!(self.current_tok().tok_name, TN::RBra | TN::Eof) {
+            if let Some(statement) = self.parse_statement_recovering() {
+                span = span.merge(statement.span());
+                statements.push(statement);
+            }
         }
         let rbra = self.expect_tok(TN::RBra)?;
         span = span.merge(&rbra.span);
@@ -213,19 +687,15 @@ impl Parser {
         })
     }
 
-    fn parse_fn_parameters(&mut self) -> Result<(Vec<(String, Type)>, Span), FlavorError> {
+    fn parse_fn_parameters(&mut self) -> Result<(Vec<Parameter>, Span), FlavorError> {
         let lpar = self.expect_tok(TN::LPar)?;
         let mut span = lpar.span;
-        let mut params: Vec<(String, Type)> = vec![];
+        let mut params: Vec<Parameter> = vec![];
         if self.current_tok().tok_name != TN::RPar {
             loop {
-                let param_name = self.expect_tok(TN::Identifier)?;
-                span = span.merge(&param_name.span);
-                let colon_tok = self.expect_tok(TN::Colon)?;
-                span = span.merge(&colon_tok.span);
-                let (param_ty, ty_span) = self.parse_type()?;
-                span = span.merge(&ty_span);
-                params.push((param_name.lexeme, param_ty));
+                let param = self.parse_parameter()?;
+                span = span.merge(&param.span);
+                params.push(param);
                 if self.current_tok().tok_name == TN::Comma {
                     let comma = self.expect_tok(TN::Comma)?;
                     span = span.merge(&comma.span);
@@ -241,6 +711,18 @@ impl Parser {
         Ok((params, span))
     }
 
+    /// Parses `name: type`.
+    fn parse_parameter(&mut self) -> Result<Parameter, FlavorError> {
+        let name = self.expect_tok(TN::Identifier)?;
+        self.expect_tok(TN::Colon)?;
+        let (ty, ty_span) = self.parse_type()?;
+        Ok(Parameter {
+            name: name.lexeme,
+            ty,
+            span: name.span.merge(&ty_span),
+        })
+    }
+
     fn parse_function_type_signature(&mut self) -> Result<(Type, Span), FlavorError> {
         let lpar = self.expect_tok(TN::LPar)?;
         let mut span = lpar.span;
@@ -304,6 +786,7 @@ impl Parser {
             var_type,
             expr: Box::new(expr),
             span,
+            location: Cell::new(None),
         })
     }
 
@@ -375,6 +858,16 @@ impl Parser {
                         span,
                     };
                 }
+                TN::Dot => {
+                    let dot = self.expect_tok(TN::Dot)?;
+                    let field_tok = self.expect_tok(TN::Identifier)?;
+                    let span = expr.span().merge(&dot.span).merge(&field_tok.span);
+                    expr = ASTNode::FieldAccess {
+                        object: Box::new(expr),
+                        field: field_tok.lexeme,
+                        span,
+                    };
+                }
                 TN::LPar => {
                     let lpar = self.expect_tok(TN::LPar)?;
                     let mut span = expr.span().merge(&lpar.span);
@@ -437,6 +930,14 @@ impl Parser {
             }
             TN::Number => {
                 let tok = self.expect_tok(TN::Number)?;
+                if tok.lexeme.parse::<i64>().is_err() {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::Parsing,
+                        format!("Integer literal {} does not fit in an int", tok.lexeme),
+                        tok.span,
+                    )
+                    .with_code(ErrorCode::IntegerLiteralTooLarge));
+                }
                 Ok(ASTNode::NumberLiteral {
                     value: tok.lexeme,
                     span: tok.span,
@@ -464,7 +965,7 @@ impl Parser {
             TN::StringLiteral => {
                 let tok = self.expect_tok(TN::StringLiteral)?;
                 Ok(ASTNode::StringLiteral {
-                    value: tok.lexeme,
+                    value: tok.value.unwrap_or(tok.lexeme),
                     span: tok.span,
                 })
             }
@@ -472,16 +973,12 @@ impl Parser {
                 let lt_tok = self.expect_tok(TN::Lt)?;
 
                 let mut span = lt_tok.span;
-                let mut params: Vec<(String, Type)> = vec![];
+                let mut params: Vec<Parameter> = vec![];
                 if self.current_tok().tok_name != TN::Gt {
                     loop {
-                        let param_name = self.expect_tok(TN::Identifier)?;
-                        span = span.merge(&param_name.span);
-                        let colon_tok = self.expect_tok(TN::Colon)?;
-                        span = span.merge(&colon_tok.span);
-                        let (param_ty, ty_span) = self.parse_type()?;
-                        span = span.merge(&ty_span);
-                        params.push((param_name.lexeme, param_ty));
+                        let param = self.parse_parameter()?;
+                        span = span.merge(&param.span);
+                        params.push(param);
                         if self.current_tok().tok_name == TN::Comma {
                             let comma = self.expect_tok(TN::Comma)?;
                             span = span.merge(&comma.span);
@@ -502,7 +999,7 @@ impl Parser {
                 Ok(ASTNode::FunctionExpression {
                     parameters: params,
                     return_type: return_ty,
-                    body: Box::new(body),
+                    body: Rc::new(body),
                     span,
                 })
             }
@@ -546,16 +1043,22 @@ impl Parser {
                 span = span.merge(&rsqu.span);
                 Ok(ASTNode::ArrayLiteral { elements, span })
             }
+            TN::Identifier if self.peek_tok(1).tok_name == TN::DoubleColon => {
+                self.parse_enum_variant()
+            }
+            TN::Identifier if self.at_struct_literal() => self.parse_struct_literal(),
+            TN::Match => self.parse_match(),
             TN::Identifier => {
                 let tok = self.expect_tok(TN::Identifier)?;
                 Ok(ASTNode::Identifier {
                     name: tok.lexeme,
                     span: tok.span,
+                    location: Cell::new(None),
                 })
             }
             TN::LPar => {
                 let _lpar = self.expect_tok(TN::LPar)?;
-                let expr = self.parse_expression()?;
+                let expr = self.with_struct_literals(true, |p| p.parse_expression())?;
                 self.expect_tok(TN::RPar)?;
                 Ok(expr)
             }
@@ -566,7 +1069,8 @@ impl Parser {
                     tok.tok_name, tok.lexeme
                 ),
                 tok.span,
-            )),
+            )
+            .with_code(ErrorCode::ExpectedExpression)),
         }
     }
 
@@ -613,7 +1117,8 @@ impl Parser {
                         tok.tok_name, tok.lexeme
                     ),
                     tok.span,
-                ))
+                )
+                .with_code(ErrorCode::ExpectedType))
             }
         }
     }
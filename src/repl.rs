@@ -0,0 +1,197 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::engine::Engine;
+use crate::error::FlavorError;
+use crate::error_codes::ErrorCode;
+use crate::interpreter::EvaluationType;
+use crate::lexer::Lexer;
+use crate::types::{ASTNode, TokenName as TN};
+
+const PROMPT: &str = "flavor> ";
+const CONTINUATION_PROMPT: &str = "   ...> ";
+
+const HELP: &str = "\
+Enter statements to run them, or an expression to print its value.
+Input continues on the next line while braces, brackets or parentheses are open.
+
+Commands:
+    :type <expr>    Print the type of an expression without running it
+    :ast <input>    Print the syntax tree of the input
+    :reset          Forget every declaration made so far
+    :help           Print this message
+    :quit           Leave the REPL";
+
+/// Interactive session: every input is checked and run by one `Engine`, against the
+/// declarations of the inputs before it. Inputs that fail to type check or run are
+/// forgotten by the type checker, so a mistake can simply be typed again.
+pub struct Repl {
+    engine: Engine,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            engine: Engine::new(),
+        }
+    }
+
+    /// Reads inputs from standard input until it is closed or `:quit` is entered.
+    /// Prompts are only shown when standard input is a terminal.
+    pub fn run(&mut self) {
+        let stdin = std::io::stdin();
+        let interactive = stdin.is_terminal();
+        if interactive {
+            println!("Flavor REPL, :help for the commands, :quit to leave");
+        }
+
+        let mut buffer = String::new();
+        loop {
+            if interactive {
+                let prompt = if buffer.is_empty() {
+                    PROMPT
+                } else {
+                    CONTINUATION_PROMPT
+                };
+                print!("{prompt}");
+                let _ = std::io::stdout().flush();
+            }
+
+            let mut line = String::new();
+            let closed = matches!(stdin.lock().read_line(&mut line), Ok(0) | Err(_));
+            buffer.push_str(&line);
+            if !closed && !is_complete(&buffer) {
+                continue;
+            }
+
+            let input = std::mem::take(&mut buffer);
+            if matches!(input.trim(), ":quit" | ":q") {
+                break;
+            }
+            match self.eval(&input) {
+                Ok(Some(output)) => println!("{output}"),
+                Ok(None) => {}
+                Err(diagnostics) => eprintln!("{diagnostics}"),
+            }
+            if closed {
+                break;
+            }
+        }
+    }
+
+    /// Handles one complete input, either a command or Flavor statements.
+    /// Returns the text to print, or the rendered diagnostics on failure.
+    pub fn eval(&mut self, input: &str) -> Result<Option<String>, String> {
+        let input = input.trim();
+        let Some(command) = input.strip_prefix(':') else {
+            return if input.is_empty() {
+                Ok(None)
+            } else {
+                self.run_statements(input)
+            };
+        };
+
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+        match name {
+            "type" => self.type_of(argument).map(Some),
+            "ast" => {
+                if argument.is_empty() {
+                    return Err("':ast' expects some input".to_string());
+                }
+                let nodes = parse(argument)?;
+                let trees: Vec<String> = nodes.iter().map(|node| format!("{node:#?}")).collect();
+                Ok(Some(trees.join("\n")))
+            }
+            "reset" => {
+                *self = Repl::new();
+                Ok(Some("Every declaration has been forgotten".to_string()))
+            }
+            "help" => Ok(Some(HELP.to_string())),
+            _ => Err(format!("Unknown command ':{name}', try :help")),
+        }
+    }
+
+    /// Type checks and runs `input`, echoing the value of a trailing expression statement
+    /// unless it is an assignment.
+    fn run_statements(&mut self, input: &str) -> Result<Option<String>, String> {
+        let nodes = parse(input)?;
+
+        let value = self
+            .engine
+            .execute(&nodes)
+            .map_err(|errors| render(input, &errors))?;
+        let echoes = match nodes.last() {
+            Some(ASTNode::ExpressionStatement { expr, .. }) => !matches!(
+                expr.as_ref(),
+                ASTNode::BinaryExpression { operator, .. } if operator == "="
+            ),
+            _ => false,
+        };
+        if echoes && !matches!(value, EvaluationType::Unit) {
+            Ok(Some(value.to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Type checks a single expression without running it.
+    fn type_of(&self, input: &str) -> Result<String, String> {
+        let nodes = parse(input)?;
+        let [ASTNode::ExpressionStatement { expr, .. }] = nodes.as_slice() else {
+            return Err("':type' expects a single expression".to_string());
+        };
+        self.engine
+            .type_of(expr)
+            .map(|ty| ty.to_string())
+            .map_err(|errors| render(input, &errors))
+    }
+}
+
+/// Tells whether `input` can be run as is, or whether the REPL should keep reading lines
+/// because a bracket, a block comment or a `"""` string is still open.
+pub fn is_complete(input: &str) -> bool {
+    let mut lexer = Lexer::new(input);
+    if let Err(err) = lexer.lexe() {
+        return match err.code {
+            Some(ErrorCode::UnterminatedComment) => false,
+            // Only block strings, whose opening quotes span three columns, go past a line
+            Some(ErrorCode::UnterminatedString) => err
+                .span
+                .is_none_or(|span| span.end_column - span.start_column != 2),
+            _ => true,
+        };
+    }
+
+    let mut depth = 0i64;
+    for token in &lexer.tokens {
+        match token.tok_name {
+            TN::LBra | TN::LPar | TN::LSqu => depth += 1,
+            TN::RBra | TN::RPar | TN::RSqu => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+/// Lexes and parses `input`. A trailing expression may leave out its `;`, so a failed parse
+/// is retried with one appended. Spans stay the same, so later diagnostics are rendered
+/// against `input`, as it was typed.
+fn parse(input: &str) -> Result<Vec<ASTNode>, String> {
+    Engine::parse(input)
+        .or_else(|errors| Engine::parse(&format!("{input};")).map_err(|_| render(input, &errors)))
+}
+
+fn render(source: &str, errors: &[FlavorError]) -> String {
+    errors
+        .iter()
+        .map(|error| error.render(source))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,250 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use crate::compiler::Globals;
+use crate::types::{ASTNode as AST, Location, MatchArm, Parameter, Pattern};
+
+/// Tells whether a block holding `nodes` declares variables or types. Only such blocks get a
+/// scope of their own, the others run in the scope around them.
+pub(crate) fn declares_names(nodes: &[AST]) -> bool {
+    nodes.iter().any(|node| {
+        matches!(
+            node,
+            AST::LetDeclaration { .. }
+                | AST::FunctionDeclaration { .. }
+                | AST::AliasDeclaration { .. }
+                | AST::StructDeclaration { .. }
+                | AST::EnumDeclaration { .. }
+        )
+    })
+}
+
+/// The names bound by `pattern`, in the order the interpreter stores their values.
+pub(crate) fn pattern_bindings(pattern: &Pattern) -> Vec<&str> {
+    match pattern {
+        Pattern::Wildcard { .. } => vec![],
+        Pattern::Binding { name, .. } => vec![name],
+        Pattern::Variant { bindings, .. } => bindings
+            .iter()
+            .filter(|name| *name != "_")
+            .map(String::as_str)
+            .collect(),
+    }
+}
+
+/// Variables declared in one scope, by name.
+#[derive(Default)]
+struct Scope {
+    slots: HashMap<String, usize>,
+    len: usize,
+}
+
+impl Scope {
+    /// A `let` or a `fn` redeclaring a name of the same scope overwrites its slot, so closures
+    /// that captured the old variable see the new value.
+    fn declare(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.slots.get(name) {
+            return slot;
+        }
+        self.bind(name)
+    }
+
+    /// Parameters and pattern bindings each get a slot, the last one winning a repeated name.
+    fn bind(&mut self, name: &str) -> usize {
+        let slot = self.len;
+        self.len += 1;
+        self.slots.insert(name.to_string(), slot);
+        slot
+    }
+}
+
+/// Finds the location of every variable of a checked program before the interpreter runs it.
+///
+/// The resolver opens a scope wherever the interpreter creates one: for each call, each
+/// iteration of a `for` loop, each match arm binding names and each block declaring names.
+/// A variable is then found by going `depth` scopes up and reading a slot, and closures find
+/// the variables they capture the same way through the scope they were created in. Names
+/// declared at the top level are globals, numbered once for all the programs an interpreter
+/// runs.
+///
+/// Resolution follows the order of the source, so a variable declared after a closure is not
+/// visible inside of it, even if it is declared before the closure is called.
+pub(crate) struct Resolver<'a> {
+    globals: &'a mut Globals,
+    scopes: Vec<Scope>,
+}
+
+impl<'a> Resolver<'a> {
+    pub(crate) fn new(globals: &'a mut Globals) -> Self {
+        Self {
+            globals,
+            scopes: vec![],
+        }
+    }
+
+    pub(crate) fn resolve_program(&mut self, nodes: &[AST]) {
+        for node in nodes {
+            self.resolve(node);
+        }
+    }
+
+    fn declare(&mut self, name: &str, location: &Cell<Option<Location>>) {
+        let resolved = match self.scopes.last_mut() {
+            Some(scope) => Location::Local {
+                depth: 0,
+                slot: scope.declare(name),
+            },
+            None => Location::Global(self.globals.declare(name) as usize),
+        };
+        location.set(Some(resolved));
+    }
+
+    fn lookup(&mut self, name: &str) -> Location {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(&slot) = scope.slots.get(name) {
+                return Location::Local { depth, slot };
+            }
+        }
+        Location::Global(self.globals.intern(name) as usize)
+    }
+
+    /// Runs `resolve` in a new scope where `names` are bound first.
+    fn scoped<'n>(
+        &mut self,
+        names: impl IntoIterator<Item = &'n str>,
+        resolve: impl FnOnce(&mut Self),
+    ) {
+        let mut scope = Scope::default();
+        for name in names {
+            scope.bind(name);
+        }
+        self.scopes.push(scope);
+        resolve(self);
+        self.scopes.pop();
+    }
+
+    fn resolve_function(&mut self, parameters: &[Parameter], body: &AST) {
+        self.scoped(
+            parameters.iter().map(|parameter| parameter.name.as_str()),
+            |resolver| resolver.resolve(body),
+        );
+    }
+
+    fn resolve_arm(&mut self, arm: &MatchArm) {
+        let names = pattern_bindings(&arm.pattern);
+        if names.is_empty() {
+            self.resolve(&arm.body);
+        } else {
+            self.scoped(names, |resolver| resolver.resolve(&arm.body));
+        }
+    }
+
+    fn resolve(&mut self, node: &AST) {
+        match node {
+            AST::Print { expressions, .. } => self.resolve_program(expressions),
+            AST::Body { nodes, .. } => {
+                if declares_names(nodes) {
+                    self.scoped([], |resolver| resolver.resolve_program(nodes));
+                } else {
+                    self.resolve_program(nodes);
+                }
+            }
+            AST::If {
+                guard,
+                then_body,
+                else_body,
+                ..
+            } => {
+                self.resolve(guard);
+                self.resolve(then_body);
+                if let Some(else_body) = else_body {
+                    self.resolve(else_body);
+                }
+            }
+            AST::While { guard, body, .. } => {
+                self.resolve(guard);
+                self.resolve(body);
+            }
+            AST::For {
+                variable,
+                iterable,
+                body,
+                ..
+            } => {
+                self.resolve(iterable);
+                self.scoped([variable.as_str()], |resolver| resolver.resolve(body));
+            }
+            AST::Range { start, end, .. } => {
+                self.resolve(start);
+                self.resolve(end);
+            }
+            AST::LetDeclaration {
+                identifier,
+                expr,
+                location,
+                ..
+            } => {
+                self.resolve(expr);
+                self.declare(identifier, location);
+            }
+            AST::FunctionDeclaration {
+                name,
+                parameters,
+                body,
+                location,
+                ..
+            } => {
+                // Declared first, so the body can call the function recursively.
+                self.declare(name, location);
+                self.resolve_function(parameters, body);
+            }
+            AST::FunctionExpression {
+                parameters, body, ..
+            } => self.resolve_function(parameters, body),
+            AST::Return { expr, .. } => self.resolve(expr),
+            AST::FunctionCall {
+                callee, arguments, ..
+            } => {
+                self.resolve(callee);
+                self.resolve_program(arguments);
+            }
+            AST::Identifier { name, location, .. } => location.set(Some(self.lookup(name))),
+            AST::ArrayLiteral { elements, .. } => self.resolve_program(elements),
+            AST::ArrayAccess { array, index, .. } => {
+                self.resolve(array);
+                self.resolve(index);
+            }
+            AST::StructLiteral { fields, .. } => {
+                for (_, expr) in fields {
+                    self.resolve(expr);
+                }
+            }
+            AST::FieldAccess { object, .. } => self.resolve(object),
+            AST::EnumVariant { arguments, .. } => self.resolve_program(arguments),
+            AST::Match {
+                scrutinee, arms, ..
+            } => {
+                self.resolve(scrutinee);
+                for arm in arms {
+                    self.resolve_arm(arm);
+                }
+            }
+            AST::BinaryExpression { left, right, .. } => {
+                self.resolve(left);
+                self.resolve(right);
+            }
+            AST::UnaryExpression { operand, .. }
+            | AST::ExpressionStatement { expr: operand, .. } => self.resolve(operand),
+            AST::AliasDeclaration { .. }
+            | AST::StructDeclaration { .. }
+            | AST::EnumDeclaration { .. }
+            | AST::Break { .. }
+            | AST::Continue { .. }
+            | AST::UnitLiteral { .. }
+            | AST::NumberLiteral { .. }
+            | AST::FloatLiteral { .. }
+            | AST::StringLiteral { .. }
+            | AST::BoolLiteral { .. } => {}
+        }
+    }
+}
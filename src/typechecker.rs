@@ -1,13 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use crate::builtins::{self, Builtin, BuiltinCall};
 use crate::error::{ErrorPhase, FlavorError};
-use crate::types::{ASTNode, Type};
+use crate::error_codes::ErrorCode;
+use crate::types::{ASTNode, MatchArm, Parameter, Pattern, Span, Type};
 
+#[derive(Clone)]
 pub struct TypeChecker {
     scopes: Vec<HashMap<String, Type>>,
+    /// Where the variables and functions of each scope were declared, for diagnostics
+    declarations: Vec<HashMap<String, Span>>,
+    type_scopes: Vec<HashMap<String, Type>>,
+    /// Where the types of each scope were declared, for diagnostics
+    type_declarations: Vec<HashMap<String, Span>>,
+    /// Declarations of the variables holding an untyped `[]` whose element type is not known
+    /// yet. The first value pushed or inserted into one of them gives it.
+    open_arrays: HashSet<Span>,
     current_expected_return: Option<Type>,
     current_expected_type: Option<Type>,
     loop_depth: usize,
+    loop_labels: Vec<String>,
+    errors: Vec<FlavorError>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TypeChecker {
@@ -16,20 +35,93 @@ impl TypeChecker {
             current_expected_return: None,
             current_expected_type: None,
             scopes: vec![HashMap::new()],
+            declarations: vec![HashMap::new()],
+            type_scopes: vec![HashMap::new()],
+            type_declarations: vec![HashMap::new()],
+            open_arrays: HashSet::new(),
             loop_depth: 0,
+            loop_labels: vec![],
+            errors: vec![],
         }
     }
 
-    fn within_loop<F, R>(&mut self, f: F) -> R
+    /// Records an error and lets checking go on.
+    fn report(&mut self, error: FlavorError) {
+        self.errors.push(error);
+    }
+
+    fn within_loop<F, R>(&mut self, label: &Option<String>, f: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
     {
         self.loop_depth += 1;
+        if let Some(label) = label {
+            self.loop_labels.push(label.clone());
+        }
         let result = f(self);
+        if label.is_some() {
+            self.loop_labels.pop();
+        }
         self.loop_depth -= 1;
         result
     }
 
+    /// Runs `f` as the body of a function: loops enclosing the function definition cannot be
+    /// targeted by `break` or `continue` from inside it.
+    fn within_function<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous_depth = std::mem::take(&mut self.loop_depth);
+        let previous_labels = std::mem::take(&mut self.loop_labels);
+        let result = f(self);
+        self.loop_depth = previous_depth;
+        self.loop_labels = previous_labels;
+        result
+    }
+
+    /// Validates a `break` or `continue`, which must sit inside a loop carrying `label` if
+    /// one is given.
+    fn check_loop_jump(
+        &self,
+        keyword: &str,
+        label: &Option<String>,
+        span: Span,
+    ) -> Result<(Type, bool), FlavorError> {
+        if self.loop_depth == 0 {
+            return Err(FlavorError::with_span(
+                ErrorPhase::TypeChecking,
+                format!("{keyword} statement not within a loop"),
+                span,
+            )
+            .with_code(ErrorCode::JumpOutsideLoop));
+        }
+        if let Some(label) = label
+            && !self.loop_labels.contains(label)
+        {
+            return Err(FlavorError::with_span(
+                ErrorPhase::TypeChecking,
+                format!("Use of undeclared loop label '{label}'"),
+                span,
+            )
+            .with_code(ErrorCode::UndeclaredLabel));
+        }
+        Ok((Type::Unit, true))
+    }
+
+    /// Rejects a loop label that is already used by an enclosing loop.
+    fn check_loop_label(&self, label: &Option<String>, span: Span) -> Result<(), FlavorError> {
+        match label {
+            Some(label) if self.loop_labels.contains(label) => Err(FlavorError::with_span(
+                ErrorPhase::TypeChecking,
+                format!("Loop label '{label}' shadows the label of an enclosing loop"),
+                span,
+            )
+            .with_code(ErrorCode::ShadowedLabel)),
+            _ => Ok(()),
+        }
+    }
+
     fn with_expected_type<F, R>(&mut self, expected: Option<Type>, f: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
@@ -56,19 +148,330 @@ impl TypeChecker {
         None
     }
 
+    fn record_declaration(&mut self, name: &str, span: Span) {
+        if let Some(declarations) = self.declarations.last_mut() {
+            declarations.insert(name.to_string(), span);
+        }
+    }
+
+    /// Where the variable or function `name` visible from the current scope was declared.
+    fn declaration_of(&self, name: &str) -> Option<Span> {
+        self.scopes
+            .iter()
+            .zip(&self.declarations)
+            .rev()
+            .find(|(scope, _)| scope.contains_key(name))
+            .and_then(|(_, declarations)| declarations.get(name).copied())
+    }
+
+    /// Where the type `name` visible from the current scope was declared.
+    fn type_declaration_of(&self, name: &str) -> Option<Span> {
+        self.type_scopes
+            .iter()
+            .zip(&self.type_declarations)
+            .rev()
+            .find(|(scope, _)| scope.contains_key(name))
+            .and_then(|(_, declarations)| declarations.get(name).copied())
+    }
+
     pub fn enter_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.declarations.push(HashMap::new());
+        self.type_scopes.push(HashMap::new());
+        self.type_declarations.push(HashMap::new());
     }
 
     pub fn exit_scope(&mut self) {
         self.scopes.pop();
+        self.declarations.pop();
+        self.type_scopes.pop();
+        self.type_declarations.pop();
+    }
+
+    /// Registers every alias, struct and enum declared directly in `nodes` into the current scope, so
+    /// named types can refer to each other regardless of the order they are written in.
+    ///
+    /// Duplicate declarations are reported and skipped.
+    ///
+    /// * `nodes`: statements of the scope being entered
+    fn declare_types(&mut self, nodes: &[ASTNode]) {
+        for node in nodes {
+            let (name, ty, span) = match node {
+                ASTNode::AliasDeclaration {
+                    name,
+                    aliased_type,
+                    span,
+                } => (name, aliased_type.clone(), span),
+                ASTNode::StructDeclaration { name, fields, span } => {
+                    for (index, (field_name, _)) in fields.iter().enumerate() {
+                        if fields[..index].iter().any(|(other, _)| other == field_name) {
+                            let message = format!(
+                                "Field '{field_name}' is declared twice in struct '{name}'"
+                            );
+                            self.report(
+                                FlavorError::with_span(ErrorPhase::TypeChecking, message, *span)
+                                    .with_code(ErrorCode::DuplicateField),
+                            );
+                        }
+                    }
+                    let ty = Type::Struct {
+                        name: name.clone(),
+                        fields: fields.clone(),
+                    };
+                    (name, ty, span)
+                }
+                ASTNode::EnumDeclaration {
+                    name,
+                    variants,
+                    span,
+                } => {
+                    for (index, (variant, _)) in variants.iter().enumerate() {
+                        if variants[..index].iter().any(|(other, _)| other == variant) {
+                            let message =
+                                format!("Variant '{variant}' is declared twice in enum '{name}'");
+                            self.report(
+                                FlavorError::with_span(ErrorPhase::TypeChecking, message, *span)
+                                    .with_code(ErrorCode::DuplicateVariant),
+                            );
+                        }
+                    }
+                    let ty = Type::Enum {
+                        name: name.clone(),
+                        variants: variants.clone(),
+                    };
+                    (name, ty, span)
+                }
+                _ => continue,
+            };
+
+            let scope = self
+                .type_scopes
+                .last_mut()
+                .expect("type scopes should never be empty");
+            if scope.contains_key(name) {
+                self.report(
+                    FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!("Type '{name}' is already declared in this scope"),
+                        *span,
+                    )
+                    .with_code(ErrorCode::DuplicateType),
+                );
+                continue;
+            }
+            scope.insert(name.clone(), ty);
+            if let Some(declarations) = self.type_declarations.last_mut() {
+                declarations.insert(name.clone(), *span);
+            }
+        }
+
+        // Each cycle of aliases is reported once, at its first alias. Its aliases then stand
+        // for the poison type, so their uses report nothing more.
+        let depth = self.type_scopes.len() - 1;
+        for node in nodes {
+            let ASTNode::AliasDeclaration { name, span, .. } = node else {
+                continue;
+            };
+            let mut chain = vec![];
+            if !self.alias_cycle(&Type::Custom(name.clone()), name, depth, &mut chain) {
+                continue;
+            }
+            chain.push(name.clone());
+            self.report(recursive_alias(&chain, *span));
+            for alias in chain {
+                self.type_scopes[depth].insert(alias, Type::Error);
+            }
+        }
+    }
+
+    /// Expands every type name contained in `ty` into the type it stands for.
+    ///
+    /// * `ty`: the type as written in the source
+    /// * `span`: location used to report unknown or recursive types
+    pub fn resolve_type(&self, ty: &Type, span: Span) -> Result<Type, FlavorError> {
+        self.resolve_type_at(ty, span, self.type_scopes.len() - 1, &mut Vec::new())
+    }
+
+    /// Resolves `ty` looking only at the type scopes up to `depth`, which is the scope the
+    /// name being expanded was declared in.
+    /// `visiting` holds the chain of names currently being expanded to detect cycles.
+    fn resolve_type_at(
+        &self,
+        ty: &Type,
+        span: Span,
+        depth: usize,
+        visiting: &mut Vec<String>,
+    ) -> Result<Type, FlavorError> {
+        match ty {
+            Type::Custom(name) => {
+                let Some((aliased, alias_depth)) = self.lookup_type(name, depth) else {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!("Unknown type '{name}'"),
+                        span,
+                    )
+                    .with_code(ErrorCode::UnknownType));
+                };
+
+                if let Some(start) = visiting.iter().position(|visited| visited == name) {
+                    // A struct or enum holding itself, e.g. through an array, stays a name.
+                    if matches!(aliased, Type::Struct { .. } | Type::Enum { .. }) {
+                        return Ok(ty.clone());
+                    }
+                    let mut cycle = visiting[start..].to_vec();
+                    cycle.push(name.clone());
+                    return Err(recursive_alias(&cycle, span));
+                }
+
+                visiting.push(name.clone());
+                let resolved = self.resolve_type_at(&aliased, span, alias_depth, visiting);
+                visiting.pop();
+                resolved
+            }
+            Type::Array(inner) => Ok(Type::Array(Box::new(
+                self.resolve_type_at(inner, span, depth, visiting)?,
+            ))),
+            Type::Function {
+                param_types,
+                return_type,
+            } => {
+                let mut resolved_params = Vec::with_capacity(param_types.len());
+                for param_ty in param_types {
+                    resolved_params.push(self.resolve_type_at(param_ty, span, depth, visiting)?);
+                }
+                Ok(Type::Function {
+                    param_types: resolved_params,
+                    return_type: Box::new(self.resolve_type_at(
+                        return_type,
+                        span,
+                        depth,
+                        visiting,
+                    )?),
+                })
+            }
+            Type::Struct { name, fields } => {
+                let mut resolved_fields = Vec::with_capacity(fields.len());
+                for (field_name, field_ty) in fields {
+                    resolved_fields.push((
+                        field_name.clone(),
+                        self.resolve_type_at(field_ty, span, depth, visiting)?,
+                    ));
+                }
+                Ok(Type::Struct {
+                    name: name.clone(),
+                    fields: resolved_fields,
+                })
+            }
+            Type::Enum { name, variants } => {
+                let mut resolved_variants = Vec::with_capacity(variants.len());
+                for (variant, payload) in variants {
+                    let mut resolved_payload = Vec::with_capacity(payload.len());
+                    for payload_ty in payload {
+                        resolved_payload
+                            .push(self.resolve_type_at(payload_ty, span, depth, visiting)?);
+                    }
+                    resolved_variants.push((variant.clone(), resolved_payload));
+                }
+                Ok(Type::Enum {
+                    name: name.clone(),
+                    variants: resolved_variants,
+                })
+            }
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// The type declared as `name` in the type scopes up to `depth`, and the scope it is in.
+    fn lookup_type(&self, name: &str, depth: usize) -> Option<(Type, usize)> {
+        (0..=depth).rev().find_map(|scope_depth| {
+            self.type_scopes[scope_depth]
+                .get(name)
+                .map(|aliased| (aliased.clone(), scope_depth))
+        })
+    }
+
+    /// Follows the aliases `ty` refers to and returns the chain leading back to `start`, if
+    /// there is one. Structs and enums end the search, as they may refer to themselves.
+    fn alias_cycle(&self, ty: &Type, start: &str, depth: usize, chain: &mut Vec<String>) -> bool {
+        match ty {
+            Type::Custom(name) if name == start && !chain.is_empty() => true,
+            // A cycle not going through `start` is found from one of its own aliases.
+            Type::Custom(name) if chain.contains(name) => false,
+            Type::Custom(name) => match self.lookup_type(name, depth) {
+                Some((Type::Struct { .. } | Type::Enum { .. }, _)) | None => false,
+                Some((aliased, alias_depth)) => {
+                    chain.push(name.clone());
+                    let found = self.alias_cycle(&aliased, start, alias_depth, chain);
+                    if !found {
+                        chain.pop();
+                    }
+                    found
+                }
+            },
+            Type::Array(inner) => self.alias_cycle(inner, start, depth, chain),
+            Type::Function {
+                param_types,
+                return_type,
+            } => param_types
+                .iter()
+                .chain(std::iter::once(return_type.as_ref()))
+                .any(|ty| self.alias_cycle(ty, start, depth, chain)),
+            _ => false,
+        }
     }
 
-    pub fn check_program(&mut self, nodes: &[ASTNode]) -> Result<(), FlavorError> {
+    /// Like `resolve_type`, but reports a failure and yields the poison type instead.
+    fn resolve_or_poison(&mut self, ty: &Type, span: Span) -> Type {
+        self.resolve_type(ty, span).unwrap_or_else(|err| {
+            self.report(err);
+            Type::Error
+        })
+    }
+
+    fn resolve_parameters(&mut self, parameters: &[Parameter]) -> Vec<(String, Type)> {
+        parameters
+            .iter()
+            .map(|parameter| {
+                let ty = self.resolve_or_poison(&parameter.ty, parameter.span);
+                (parameter.name.clone(), ty)
+            })
+            .collect()
+    }
+
+    /// Checks the whole program and returns every error found, in source order.
+    pub fn check_program(&mut self, nodes: &[ASTNode]) -> Result<(), Vec<FlavorError>> {
+        self.declare_types(nodes);
         for n in nodes {
-            self.check(n)?;
+            self.check(n);
+        }
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Checks a lone expression against the current scopes and returns its type.
+    pub fn check_expression(&mut self, expr: &ASTNode) -> Result<Type, Vec<FlavorError>> {
+        let (ty, _) = self.check(expr);
+        if self.errors.is_empty() {
+            Ok(ty)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Checks `node` and returns its type together with whether it is a return on all paths.
+    /// A failing node is recorded and given the poison type, so its parent keeps checking.
+    /// The poison counts as a guaranteed return to avoid a second "missing return" error.
+    fn check(&mut self, node: &ASTNode) -> (Type, bool) {
+        match self.check_node(node) {
+            Ok(result) => result,
+            Err(err) => {
+                self.report(err);
+                (Type::Error, true)
+            }
         }
-        Ok(())
     }
 
     /// Return a Result type
@@ -77,11 +480,11 @@ impl TypeChecker {
     /// Negative case => Error String
     ///
     /// * `node`:
-    fn check(&mut self, node: &ASTNode) -> Result<(Type, bool), FlavorError> {
+    fn check_node(&mut self, node: &ASTNode) -> Result<(Type, bool), FlavorError> {
         match node {
             ASTNode::Print { expressions, .. } => {
                 for expr in expressions.clone().into_iter() {
-                    let _ = self.check(&expr)?;
+                    let _ = self.check(&expr);
                 }
                 Ok((Type::Unit, false))
             }
@@ -103,23 +506,25 @@ impl TypeChecker {
                 let mut element_type: Option<Type> = None;
 
                 for elem in elements {
-                    let (elem_ty, _returns) =
-                        if let Some(ref expected_elem_ty) = expected_element_type {
-                            self.with_expected_type(Some(expected_elem_ty.clone()), |tc| {
-                                tc.check(elem)
-                            })?
-                        } else {
-                            self.check(elem)?
-                        };
-                    if let Some(ref t) = element_type {
-                        if *t != elem_ty {
+                    let (elem_ty, _returns) = if let Some(ref expected_elem_ty) =
+                        expected_element_type
+                    {
+                        self.with_expected_type(Some(expected_elem_ty.clone()), |tc| tc.check(elem))
+                    } else {
+                        self.check(elem)
+                    };
+                    if let Some(ref t) = element_type
+                        && *t != Type::Error
+                    {
+                        if !compatible(t, &elem_ty) {
                             return Err(FlavorError::with_span(
                                 ErrorPhase::TypeChecking,
                                 format!(
                                     "Array elements must be of the same type, found {t:?} and {elem_ty:?}"
                                 ),
                                 *elem.span(),
-                            ));
+                            )
+                            .with_code(ErrorCode::ArrayElementMismatch));
                         }
                     } else {
                         element_type = Some(elem_ty.clone());
@@ -129,7 +534,7 @@ impl TypeChecker {
                 let final_elem_type = element_type.unwrap();
 
                 if let Some(Type::Array(expected)) = self.current_expected_type.clone() {
-                    if *expected != final_elem_type {
+                    if !compatible(&expected, &final_elem_type) {
                         return Err(FlavorError::with_span(
                             ErrorPhase::TypeChecking,
                             format!(
@@ -137,7 +542,8 @@ impl TypeChecker {
                                 *expected, final_elem_type
                             ),
                             *span,
-                        ));
+                        )
+                        .with_code(ErrorCode::ArrayElementMismatch));
                     }
                     return Ok((Type::Array(expected), false));
                 }
@@ -145,24 +551,27 @@ impl TypeChecker {
                 Ok((Type::Array(Box::new(final_elem_type)), false))
             }
             ASTNode::ArrayAccess { array, index, .. } => {
-                let (array_ty, _array_ret) = self.check(array)?;
-                let (index_ty, _index_ret) = self.check(index)?;
+                let (array_ty, _array_ret) = self.check(array);
+                let (index_ty, _index_ret) = self.check(index);
 
-                if index_ty != Type::Int {
+                if !compatible(&Type::Int, &index_ty) {
                     return Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Array index must be of type Int, found {index_ty:?}",),
                         *index.span(),
-                    ));
+                    )
+                    .with_code(ErrorCode::NonIntegerIndex));
                 }
 
                 match array_ty {
                     Type::Array(elem_type) => Ok((*elem_type, false)),
+                    Type::Error => Ok((Type::Error, false)),
                     other => Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Attempted to index non-array type {other:?}"),
                         *array.span(),
-                    )),
+                    )
+                    .with_code(ErrorCode::NotIndexable)),
                 }
             }
             ASTNode::LetDeclaration {
@@ -170,58 +579,43 @@ impl TypeChecker {
                 var_type,
                 expr,
                 span,
+                ..
             } => {
+                let var_type = var_type
+                    .as_ref()
+                    .map(|ty| self.resolve_or_poison(ty, *span));
+
                 if let ASTNode::FunctionExpression {
                     parameters,
                     return_type,
                     body,
-                    ..
+                    span: fn_span,
                 } = expr.as_ref()
                 {
+                    let parameters = &self.resolve_parameters(parameters);
+                    let return_type = &self.resolve_or_poison(return_type, *fn_span);
                     let inferred_ty = Type::Function {
                         param_types: parameters.iter().map(|(_, ty)| ty.clone()).collect(),
                         return_type: Box::new(return_type.clone()),
                     };
 
-                    if let Some(declared_ty) = var_type {
-                        let mut matches = true;
-                        match declared_ty {
-                            Type::Function {
-                                param_types,
-                                return_type: declared_return,
-                            } => {
-                                if param_types.len() != parameters.len() {
-                                    matches = false;
-                                } else {
-                                    for ((_, actual_ty), expected_ty) in
-                                        parameters.iter().zip(param_types.iter())
-                                    {
-                                        if actual_ty != expected_ty {
-                                            matches = false;
-                                            break;
-                                        }
-                                    }
-                                    if *declared_return.as_ref() != return_type.clone() {
-                                        matches = false;
-                                    }
-                                }
-                            }
-                            _ => matches = false,
-                        }
-
-                        if !matches {
-                            return Err(FlavorError::with_span(
-                                ErrorPhase::TypeChecking,
-                                format!(
-                                    "Type mismatch in let declaration: variable '{identifier}' declared as {declared_ty:?} but expression has type {inferred_ty:?}"
-                                ),
-                                *span,
-                            ));
-                        }
+                    // Reported without stopping: the body is still worth checking
+                    if let Some(declared_ty) = &var_type
+                        && !compatible(declared_ty, &inferred_ty)
+                    {
+                        self.report(FlavorError::with_span(
+                            ErrorPhase::TypeChecking,
+                            format!(
+                                "Type mismatch in let declaration: variable '{identifier}' declared as {declared_ty:?} but expression has type {inferred_ty:?}"
+                            ),
+                            *span,
+                        )
+                        .with_code(ErrorCode::LetTypeMismatch));
                     }
 
                     let stored_ty = var_type.clone().unwrap_or(inferred_ty.clone());
                     self.insert(identifier.clone(), stored_ty.clone());
+                    self.record_declaration(identifier, *span);
 
                     let previous_expected_return = self.current_expected_return.clone();
                     let expected_return = if let Type::Function { return_type, .. } = &stored_ty {
@@ -235,46 +629,54 @@ impl TypeChecker {
                     for (param_name, param_ty) in parameters {
                         self.insert(param_name.clone(), param_ty.clone());
                     }
-                    let (_, guaranteed_return) = self.check(body)?;
+                    let (_, guaranteed_return) = self.within_function(|tc| tc.check(body));
                     self.exit_scope();
                     self.current_expected_return = previous_expected_return;
 
-                    if expected_return != Type::Unit && !guaranteed_return {
+                    if !matches!(expected_return, Type::Unit | Type::Error) && !guaranteed_return {
                         return Err(FlavorError::with_span(
                             ErrorPhase::TypeChecking,
                             format!(
                                 "Function assigned to '{identifier}' does not guarantee a return on all paths"
                             ),
                             *body.span(),
-                        ));
+                        )
+                        .with_code(ErrorCode::MissingReturn)
+                        .with_note(format!(
+                            "'{identifier}' returns {expected_return:?}, so every path through its body must end in a return"
+                        )));
                     }
 
                     return Ok((stored_ty, false));
                 }
 
-                let expr_result = if let Some(declared_ty) = var_type {
-                    self.with_expected_type(Some(declared_ty.clone()), |tc| tc.check(expr))?
+                let (expr_ty, _) = if let Some(declared_ty) = &var_type {
+                    self.with_expected_type(Some(declared_ty.clone()), |tc| tc.check(expr))
                 } else {
-                    self.check(expr)?
+                    self.check(expr)
                 };
 
-                if let Some(declared_ty) = var_type {
-                    if *declared_ty != expr_result.0 {
-                        return Err(FlavorError::with_span(
-                            ErrorPhase::TypeChecking,
-                            format!(
-                                "Type mismatch in let declaration: variable '{identifier}' declared as {declared_ty:?} but expression has type {:?}",
-                                expr_result.0
-                            ),
-                            *expr.span(),
-                        ));
-                    }
-                    self.insert(identifier.clone(), declared_ty.clone());
-                    Ok((declared_ty.clone(), false))
-                } else {
-                    self.insert(identifier.clone(), expr_result.0.clone());
-                    Ok((expr_result.0, false))
+                // The variable is declared even on a mismatch, so its later uses are still checked
+                if var_type.is_none()
+                    && let ASTNode::ArrayLiteral { elements, .. } = expr.as_ref()
+                    && elements.is_empty()
+                {
+                    self.open_arrays.insert(*span);
                 }
+                let stored_ty = var_type.unwrap_or_else(|| expr_ty.clone());
+                self.insert(identifier.clone(), stored_ty.clone());
+                self.record_declaration(identifier, *span);
+                if !compatible(&stored_ty, &expr_ty) {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!(
+                            "Type mismatch in let declaration: variable '{identifier}' declared as {stored_ty:?} but expression has type {expr_ty:?}"
+                        ),
+                        *expr.span(),
+                    )
+                    .with_code(ErrorCode::LetTypeMismatch));
+                }
+                Ok((stored_ty, false))
             }
             ASTNode::FunctionDeclaration {
                 name,
@@ -282,7 +684,10 @@ impl TypeChecker {
                 return_type,
                 body,
                 span,
+                ..
             } => {
+                let parameters = &self.resolve_parameters(parameters);
+                let return_type = &self.resolve_or_poison(return_type, *span);
                 let func_ty = Type::Function {
                     param_types: parameters.iter().map(|(_, t)| t.clone()).collect(),
                     return_type: Box::new(return_type.clone()),
@@ -292,6 +697,7 @@ impl TypeChecker {
                 self.current_expected_return = Some(return_type.clone());
 
                 self.insert(name.clone(), func_ty.clone());
+                self.record_declaration(name, *span);
 
                 // Enter Scope for function Body
                 self.enter_scope();
@@ -300,18 +706,22 @@ impl TypeChecker {
                     self.insert(param_name.clone(), param_ty.clone());
                 }
 
-                let (_, guaranteed_ret) = self.check(body)?;
+                let (_, guaranteed_ret) = self.within_function(|tc| tc.check(body));
 
                 self.exit_scope();
 
                 self.current_expected_return = old_expected;
 
-                if *return_type != Type::Unit && !guaranteed_ret {
+                if !matches!(return_type, Type::Unit | Type::Error) && !guaranteed_ret {
                     return Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Function '{name}' does not guarantee a return on all paths"),
                         *span,
-                    ));
+                    )
+                    .with_code(ErrorCode::MissingReturn)
+                    .with_note(format!(
+                        "'{name}' returns {return_type:?}, so every path through its body must end in a return"
+                    )));
                 }
 
                 Ok((func_ty, false))
@@ -322,6 +732,8 @@ impl TypeChecker {
                 body,
                 span,
             } => {
+                let parameters = &self.resolve_parameters(parameters);
+                let return_type = &self.resolve_or_poison(return_type, *span);
                 let func_ty = Type::Function {
                     param_types: parameters.iter().map(|(_, t)| t.clone()).collect(),
                     return_type: Box::new(return_type.clone()),
@@ -342,29 +754,32 @@ impl TypeChecker {
                                 parameters.len()
                             ),
                             *span,
-                        ));
+                        )
+                        .with_code(ErrorCode::FunctionExpressionMismatch));
                     }
                     for ((_, actual_ty), expected_ty) in
                         parameters.iter().zip(expected_params.iter())
                     {
-                        if actual_ty != expected_ty {
+                        if !compatible(expected_ty, actual_ty) {
                             return Err(FlavorError::with_span(
                                 ErrorPhase::TypeChecking,
                                 format!(
                                     "Function expression parameter type mismatch: expected {expected_ty:?}, found {actual_ty:?}"
                                 ),
                                 *span,
-                            ));
+                            )
+                            .with_code(ErrorCode::FunctionExpressionMismatch));
                         }
                     }
-                    if *expected_return.as_ref() != return_type.clone() {
+                    if !compatible(&expected_return, return_type) {
                         return Err(FlavorError::with_span(
                             ErrorPhase::TypeChecking,
                             format!(
                                 "Function expression return type mismatch: expected {expected_return:?}, found {return_type:?}"
                             ),
                             *span,
-                        ));
+                        )
+                        .with_code(ErrorCode::FunctionExpressionMismatch));
                     }
                     enforced_return = *expected_return.clone();
                 }
@@ -376,41 +791,188 @@ impl TypeChecker {
                 for (param_name, param_ty) in parameters {
                     self.insert(param_name.clone(), param_ty.clone());
                 }
-                let (_, guaranteed_ret) = self.check(body)?;
+                let (_, guaranteed_ret) = self.within_function(|tc| tc.check(body));
                 self.exit_scope();
 
                 self.current_expected_return = previous_expected_return;
 
-                if enforced_return != Type::Unit && !guaranteed_ret {
+                if !matches!(enforced_return, Type::Unit | Type::Error) && !guaranteed_ret {
                     return Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         "Function expression does not guarantee a return on all paths",
                         *body.span(),
-                    ));
+                    )
+                    .with_code(ErrorCode::MissingReturn));
                 }
 
                 Ok((func_ty, false))
             }
+            ASTNode::AliasDeclaration { name, span, .. }
+            | ASTNode::StructDeclaration { name, span, .. }
+            | ASTNode::EnumDeclaration { name, span, .. } => {
+                self.resolve_type(&Type::Custom(name.clone()), *span)?;
+                Ok((Type::Unit, false))
+            }
+            ASTNode::StructLiteral { name, fields, span } => {
+                let struct_ty = self.resolve_type(&Type::Custom(name.clone()), *span)?;
+                let Type::Struct {
+                    fields: declared_fields,
+                    ..
+                } = &struct_ty
+                else {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!("Type '{name}' is not a struct and cannot be built with a literal"),
+                        *span,
+                    )
+                    .with_code(ErrorCode::NotAStruct));
+                };
+
+                for (index, (field_name, value)) in fields.iter().enumerate() {
+                    if fields[..index].iter().any(|(other, _)| other == field_name) {
+                        self.report(
+                            FlavorError::with_span(
+                                ErrorPhase::TypeChecking,
+                                format!("Field '{field_name}' is initialized twice"),
+                                *value.span(),
+                            )
+                            .with_code(ErrorCode::FieldInitializedTwice),
+                        );
+                        continue;
+                    }
+                    let Some((_, field_ty)) = declared_fields
+                        .iter()
+                        .find(|(declared, _)| declared == field_name)
+                    else {
+                        self.report(
+                            FlavorError::with_span(
+                                ErrorPhase::TypeChecking,
+                                format!("Struct '{name}' has no field named '{field_name}'"),
+                                *value.span(),
+                            )
+                            .with_code(ErrorCode::UnknownField),
+                        );
+                        self.check(value);
+                        continue;
+                    };
+                    let (value_ty, _) =
+                        self.with_expected_type(Some(field_ty.clone()), |tc| tc.check(value));
+                    if !compatible(field_ty, &value_ty) {
+                        self.report(FlavorError::with_span(
+                            ErrorPhase::TypeChecking,
+                            format!(
+                                "Field type mismatch: '{name}.{field_name}' is {field_ty:?} but value has type {value_ty:?}"
+                            ),
+                            *value.span(),
+                        )
+                        .with_code(ErrorCode::FieldTypeMismatch));
+                    }
+                }
+
+                let missing: Vec<&str> = declared_fields
+                    .iter()
+                    .filter(|(declared, _)| !fields.iter().any(|(given, _)| given == declared))
+                    .map(|(declared, _)| declared.as_str())
+                    .collect();
+                if !missing.is_empty() {
+                    self.report(
+                        FlavorError::with_span(
+                            ErrorPhase::TypeChecking,
+                            format!(
+                                "Missing fields in struct literal '{name}': {}",
+                                missing.join(", ")
+                            ),
+                            *span,
+                        )
+                        .with_code(ErrorCode::MissingFields),
+                    );
+                }
+
+                Ok((struct_ty, false))
+            }
+            ASTNode::EnumVariant {
+                enum_name,
+                variant,
+                arguments,
+                span,
+            } => {
+                let enum_ty = self.resolve_type(&Type::Custom(enum_name.clone()), *span)?;
+                let payload = Self::variant_payload(&enum_ty, enum_name, variant, *span)?;
+
+                if payload.len() != arguments.len() {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!(
+                            "Variant '{enum_name}::{variant}' expects {} value(s), found {}",
+                            payload.len(),
+                            arguments.len()
+                        ),
+                        *span,
+                    )
+                    .with_code(ErrorCode::VariantArityMismatch));
+                }
+                for (arg, expected_ty) in arguments.iter().zip(payload.iter()) {
+                    let (arg_ty, _) =
+                        self.with_expected_type(Some(expected_ty.clone()), |tc| tc.check(arg));
+                    if !compatible(expected_ty, &arg_ty) {
+                        self.report(FlavorError::with_span(
+                            ErrorPhase::TypeChecking,
+                            format!(
+                                "Variant payload type mismatch: expected {expected_ty:?}, found {arg_ty:?}"
+                            ),
+                            *arg.span(),
+                        )
+                        .with_code(ErrorCode::VariantPayloadMismatch));
+                    }
+                }
+
+                Ok((enum_ty, false))
+            }
+            ASTNode::Match {
+                scrutinee, arms, ..
+            } => self.check_match(scrutinee, arms),
+            ASTNode::FieldAccess {
+                object,
+                field,
+                span,
+            } => {
+                let (object_ty, _object_ret) = self.check(object);
+                match &object_ty {
+                    Type::Error => Ok((Type::Error, false)),
+                    Type::Struct { name, fields } => {
+                        match fields.iter().find(|(field_name, _)| field_name == field) {
+                            // A recursive struct names itself in its fields, which expands here.
+                            Some((_, field_ty)) => Ok((self.resolve_type(field_ty, *span)?, false)),
+                            None => Err(FlavorError::with_span(
+                                ErrorPhase::TypeChecking,
+                                format!("Struct '{name}' has no field named '{field}'"),
+                                *span,
+                            )
+                            .with_code(ErrorCode::UnknownField)),
+                        }
+                    }
+                    other => Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!("Attempted to access field '{field}' on non-struct type {other:?}"),
+                        *object.span(),
+                    )
+                    .with_code(ErrorCode::FieldAccessOnNonStruct)),
+                }
+            }
             ASTNode::Body { nodes, .. } => {
                 self.enter_scope();
+                self.declare_types(nodes);
 
                 let mut guaranteed_return = false;
                 let mut last_type = Type::Unit;
 
+                // Statements after a return are still checked so their errors are reported,
+                // but they do not decide the type of the body
                 for n in nodes {
-                    let check_res = self.check(n);
-                    match check_res {
-                        Ok((ty, returns)) => {
-                            last_type = ty;
-                            if returns {
-                                guaranteed_return = true;
-                                break; // unreachable code after return
-                            }
-                        }
-                        Err(err) => {
-                            self.exit_scope();
-                            return Err(err);
-                        }
+                    let (ty, returns) = self.check(n);
+                    if !guaranteed_return {
+                        last_type = ty;
+                        guaranteed_return = returns;
                     }
                 }
 
@@ -424,23 +986,26 @@ impl TypeChecker {
                 else_body,
                 ..
             } => {
-                let guard_ty = self.check(guard)?;
+                let guard_ty = self.check(guard);
 
-                if guard_ty.0 != Type::Bool {
-                    return Err(FlavorError::with_span(
-                        ErrorPhase::TypeChecking,
-                        format!(
-                            "Guard in If statement should be of type Bool, but was {:?}",
-                            guard_ty.0,
-                        ),
-                        *guard.span(),
-                    ));
+                if !compatible(&Type::Bool, &guard_ty.0) {
+                    self.report(
+                        FlavorError::with_span(
+                            ErrorPhase::TypeChecking,
+                            format!(
+                                "Guard in If statement should be of type Bool, but was {:?}",
+                                guard_ty.0,
+                            ),
+                            *guard.span(),
+                        )
+                        .with_code(ErrorCode::NonBooleanGuard),
+                    );
                 }
 
-                let (then_ty, then_returns) = self.check(then_body)?;
+                let (then_ty, then_returns) = self.check(then_body);
 
                 if let Some(else_body) = else_body {
-                    let (_else_ty, else_returns) = self.check(else_body)?;
+                    let (_else_ty, else_returns) = self.check(else_body);
 
                     // Relaxed: Don't require then_ty == else_ty here.
                     // Instead, only require both branches guarantee return.
@@ -460,37 +1025,100 @@ impl TypeChecker {
                     Ok((then_ty, false))
                 }
             }
-            ASTNode::While { guard, body, .. } => {
-                let guard_ty = self.check(guard)?;
-                if guard_ty.0 != Type::Bool {
-                    return Err(FlavorError::with_span(
-                        ErrorPhase::TypeChecking,
-                        format!(
-                            "Guard in While statement should be of type Bool, but was {:?}",
-                            guard_ty.0,
-                        ),
-                        *guard.span(),
-                    ));
+            ASTNode::While {
+                label,
+                guard,
+                body,
+                span,
+            } => {
+                if let Err(err) = self.check_loop_label(label, *span) {
+                    self.report(err);
+                }
+                let guard_ty = self.check(guard);
+                if !compatible(&Type::Bool, &guard_ty.0) {
+                    self.report(
+                        FlavorError::with_span(
+                            ErrorPhase::TypeChecking,
+                            format!(
+                                "Guard in While statement should be of type Bool, but was {:?}",
+                                guard_ty.0,
+                            ),
+                            *guard.span(),
+                        )
+                        .with_code(ErrorCode::NonBooleanGuard),
+                    );
                 }
-                self.within_loop(|tc| tc.check(body))?;
+                self.within_loop(label, |tc| tc.check(body));
                 Ok((Type::Unit, false))
             }
-            ASTNode::Break { span } => {
-                if self.loop_depth == 0 {
-                    return Err(FlavorError::with_span(
-                        ErrorPhase::TypeChecking,
-                        "Break statement not within a loop",
-                        *span,
-                    ));
+            ASTNode::For {
+                label,
+                variable,
+                iterable,
+                body,
+                span,
+            } => {
+                if let Err(err) = self.check_loop_label(label, *span) {
+                    self.report(err);
                 }
-                Ok((Type::Unit, true))
+                let element_ty =
+                    match iterable.as_ref() {
+                        ASTNode::Range { start, end, .. } => {
+                            for bound in [start, end] {
+                                let (bound_ty, _) = self.check(bound);
+                                if !compatible(&Type::Int, &bound_ty) {
+                                    self.report(FlavorError::with_span(
+                                    ErrorPhase::TypeChecking,
+                                    format!("Range bounds must be of type Int, found {bound_ty:?}"),
+                                    *bound.span(),
+                                )
+                                .with_code(ErrorCode::NonIntegerRange));
+                                }
+                            }
+                            Type::Int
+                        }
+                        _ => match self.check(iterable) {
+                            (Type::Array(element_ty), _) => *element_ty,
+                            (Type::Error, _) => Type::Error,
+                            (other, _) => {
+                                self.report(FlavorError::with_span(
+                                ErrorPhase::TypeChecking,
+                                format!("For loops iterate over arrays or ranges, found {other:?}"),
+                                *iterable.span(),
+                            )
+                            .with_code(ErrorCode::NotIterable));
+                                Type::Error
+                            }
+                        },
+                    };
+
+                self.enter_scope();
+                self.insert(variable.clone(), element_ty);
+                self.within_loop(label, |tc| tc.check(body));
+                self.exit_scope();
+                Ok((Type::Unit, false))
             }
+            ASTNode::Range { span, .. } => Err(FlavorError::with_span(
+                ErrorPhase::TypeChecking,
+                "Ranges can only be used as the iterable of a for loop",
+                *span,
+            )
+            .with_code(ErrorCode::RangeOutsideFor)),
+            ASTNode::Break { label, span } => self.check_loop_jump("Break", label, *span),
+            ASTNode::Continue { label, span } => self.check_loop_jump("Continue", label, *span),
             ASTNode::FunctionCall {
                 callee,
                 arguments,
                 span,
             } => {
-                let (callee_ty, _callee_ret) = self.check(callee)?;
+                if let ASTNode::Identifier { name, .. } = callee.as_ref()
+                    && self.get(name.to_string()).is_none()
+                    && let Some(builtin) = builtins::lookup(name)
+                {
+                    return self.check_builtin_call(builtin, arguments, *span);
+                }
+
+                let (callee_ty, _callee_ret) = self.check(callee);
 
                 match callee_ty {
                     Type::Function {
@@ -507,32 +1135,50 @@ impl TypeChecker {
                                     arguments.len()
                                 ),
                                 *span,
-                            ));
+                            )
+                            .with_code(ErrorCode::ArgumentCountMismatch));
                         }
 
                         for (arg_node, param_ty) in arguments.iter().zip(param_types.iter()) {
                             let arg_ty = self.with_expected_type(Some(param_ty.clone()), |tc| {
                                 tc.check(arg_node)
-                            })?;
-                            if *param_ty != arg_ty.0 {
-                                return Err(FlavorError::with_span(
+                            });
+                            if !compatible(param_ty, &arg_ty.0) {
+                                let mut error = FlavorError::with_span(
                                     ErrorPhase::TypeChecking,
                                     format!(
                                         "Function argument type mismatch: expected {param_ty:?}, found {:?}",
                                         arg_ty.0
                                     ),
                                     *arg_node.span(),
-                                ));
+                                )
+                                .with_code(ErrorCode::ArgumentTypeMismatch);
+                                if let ASTNode::Identifier { name, .. } = callee.as_ref()
+                                    && let Some(declared) = self.declaration_of(name)
+                                {
+                                    error = error.with_label(
+                                        declared,
+                                        format!("function '{name}' declared here"),
+                                    );
+                                }
+                                self.report(error);
                             }
                         }
 
                         Ok((*return_type.clone(), false))
                     }
+                    Type::Error => {
+                        for arg_node in arguments {
+                            self.check(arg_node);
+                        }
+                        Ok((Type::Error, false))
+                    }
                     other => Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Attempted to call non-function type {other:?}"),
                         *span,
-                    )),
+                    )
+                    .with_code(ErrorCode::NotCallable)),
                 }
             }
             ASTNode::UnitLiteral { .. } => Ok((Type::Unit, false)),
@@ -540,40 +1186,49 @@ impl TypeChecker {
             ASTNode::FloatLiteral { .. } => Ok((Type::Float, false)),
             ASTNode::StringLiteral { .. } => Ok((Type::String, false)),
             ASTNode::BoolLiteral { .. } => Ok((Type::Bool, false)),
-            ASTNode::Identifier { name, span } => {
+            ASTNode::Identifier { name, span, .. } => {
                 if let Some(t) = self.get(name.to_string()) {
                     Ok((t.clone(), false))
+                } else if builtins::lookup(name).is_some() {
+                    Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!("Builtin function '{name}' can only be called directly"),
+                        *span,
+                    )
+                    .with_code(ErrorCode::BuiltinNotCalled))
                 } else {
                     Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Use of undeclared identifier '{name}'"),
                         *span,
-                    ))
+                    )
+                    .with_code(ErrorCode::UndeclaredIdentifier))
                 }
             }
             ASTNode::Return { expr, span } => {
                 let expected_return = self.current_expected_return.clone();
                 let expr_ty = if expected_return.is_some() {
-                    self.with_expected_type(expected_return.clone(), |tc| tc.check(expr))?
+                    self.with_expected_type(expected_return.clone(), |tc| tc.check(expr))
                 } else {
-                    self.check(expr)?
+                    self.check(expr)
                 };
-                if let Some(expected) = &expected_return {
-                    if expr_ty.0 != *expected {
-                        return Err(FlavorError::with_span(
-                            ErrorPhase::TypeChecking,
-                            format!(
-                                "Return type mismatch: expected {expected:?}, found {:?}",
-                                expr_ty.0
-                            ),
-                            *span,
-                        ));
-                    }
+                if let Some(expected) = &expected_return
+                    && !compatible(expected, &expr_ty.0)
+                {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!(
+                            "Return type mismatch: expected {expected:?}, found {:?}",
+                            expr_ty.0
+                        ),
+                        *span,
+                    )
+                    .with_code(ErrorCode::ReturnTypeMismatch));
                 }
                 Ok((expr_ty.0, true))
             }
             ASTNode::ExpressionStatement { expr, .. } => {
-                let _ = self.check(expr)?;
+                let _ = self.check(expr);
                 Ok((Type::Unit, false))
             }
             ASTNode::UnaryExpression {
@@ -582,7 +1237,10 @@ impl TypeChecker {
                 is_postfix: _,
                 span,
             } => {
-                let (operand_ty, _operand_ret) = self.check(operand)?;
+                let (operand_ty, _operand_ret) = self.check(operand);
+                if operand_ty == Type::Error {
+                    return Ok((Type::Error, false));
+                }
 
                 match operator.as_str() {
                     "!" => {
@@ -595,7 +1253,8 @@ impl TypeChecker {
                                     "Unary operator '{operator}' requires Boolean operand but found {operand_ty:?}",
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "--" | "++" => {
@@ -606,7 +1265,8 @@ impl TypeChecker {
                                     "Unary operator '{operator}' requires an assignable operand",
                                 ),
                                 *span,
-                            ));
+                            )
+                            .with_code(ErrorCode::NotAssignable));
                         }
                         if operand_ty == Type::Int {
                             Ok((Type::Int, false))
@@ -617,7 +1277,8 @@ impl TypeChecker {
                                     "Unary operator '{operator}' requires Integer operand but found {operand_ty:?}",
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "-" | "+" => {
@@ -630,14 +1291,16 @@ impl TypeChecker {
                                     "Unary operator '{operator}' requires Integer operand but found {operand_ty:?}",
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     _ => Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Unknown unary operator '{operator}'"),
                         *span,
-                    )),
+                    )
+                    .with_code(ErrorCode::UnknownOperator)),
                 }
             }
             ASTNode::BinaryExpression {
@@ -646,24 +1309,45 @@ impl TypeChecker {
                 right,
                 span,
             } => {
-                let (left_ty, _left_ret) = self.check(left)?;
+                let (left_ty, _left_ret) = self.check(left);
                 let (right_ty, _right_ret) = if operator == "=" {
-                    self.with_expected_type(Some(left_ty.clone()), |tc| tc.check(right))?
+                    self.with_expected_type(Some(left_ty.clone()), |tc| tc.check(right))
                 } else {
-                    self.check(right)?
+                    self.check(right)
                 };
 
+                if left_ty == Type::Error || right_ty == Type::Error {
+                    if operator == "=" {
+                        self.ensure_assignable(left)?;
+                    }
+                    let ty = match operator.as_str() {
+                        ">" | "<" | ">=" | "<=" | "&&" | "||" | "==" | "!=" => Type::Bool,
+                        _ => Type::Error,
+                    };
+                    return Ok((ty, false));
+                }
+
                 match operator.as_str() {
                     "=" => {
                         self.ensure_assignable(left)?;
                         if left_ty != right_ty {
-                            return Err(FlavorError::with_span(
+                            let mut error = FlavorError::with_span(
                                 ErrorPhase::TypeChecking,
                                 format!(
                                     "Assignment type mismatch: left is {left_ty:?}, right is {right_ty:?}"
                                 ),
                                 *span,
-                            ));
+                            )
+                            .with_code(ErrorCode::AssignmentTypeMismatch);
+                            if let ASTNode::Identifier { name, .. } = left.as_ref()
+                                && let Some(declared) = self.declaration_of(name)
+                            {
+                                error = error.with_label(
+                                    declared,
+                                    format!("'{name}' declared here as {left_ty:?}"),
+                                );
+                            }
+                            return Err(error);
                         }
                         Ok((left_ty, false))
                     }
@@ -679,7 +1363,8 @@ impl TypeChecker {
                                     "Operator '{operator}' requires Integer operands but found left: {left_ty:?}, right: {right_ty:?}"
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "+" => {
@@ -687,7 +1372,7 @@ impl TypeChecker {
                             || left_ty == Type::Float && right_ty == Type::Float
                             || left_ty == Type::String && right_ty == Type::String
                         {
-                            Ok((Type::Int, false))
+                            Ok((left_ty, false))
                         } else {
                             Err(FlavorError::with_span(
                                 ErrorPhase::TypeChecking,
@@ -695,14 +1380,15 @@ impl TypeChecker {
                                     "Operator '{operator}' requires Integer operands but found left: {left_ty:?}, right: {right_ty:?}"
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "-" | "*" | "/" => {
                         if left_ty == Type::Int && right_ty == Type::Int
                             || left_ty == Type::Float && right_ty == Type::Float
                         {
-                            Ok((Type::Int, false))
+                            Ok((left_ty, false))
                         } else {
                             Err(FlavorError::with_span(
                                 ErrorPhase::TypeChecking,
@@ -710,7 +1396,8 @@ impl TypeChecker {
                                     "Operator '{operator}' requires Integer operands but found left: {left_ty:?}, right: {right_ty:?}"
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "%" => {
@@ -723,7 +1410,8 @@ impl TypeChecker {
                                     "Operator '{operator}' requires Integer operands but found left: {left_ty:?}, right: {right_ty:?}"
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "&&" | "||" => {
@@ -736,11 +1424,21 @@ impl TypeChecker {
                                     "Operator '{operator}' requires Boolean operands but found left: {left_ty:?}, right: {right_ty:?}"
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     "==" | "!=" => {
-                        if left_ty == right_ty {
+                        if left_ty == right_ty && !comparable(&left_ty) {
+                            Err(FlavorError::with_span(
+                                ErrorPhase::TypeChecking,
+                                format!(
+                                    "Operator '{operator}' cannot compare values of type {left_ty:?}, which hold functions"
+                                ),
+                                *span,
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
+                        } else if left_ty == right_ty {
                             Ok((Type::Bool, false))
                         } else {
                             Err(FlavorError::with_span(
@@ -749,28 +1447,339 @@ impl TypeChecker {
                                     "Operator '{operator}' requires operands of the same type but found left: {left_ty:?}, right: {right_ty:?}"
                                 ),
                                 *span,
-                            ))
+                            )
+                            .with_code(ErrorCode::InvalidOperandTypes))
                         }
                     }
                     _ => Err(FlavorError::with_span(
                         ErrorPhase::TypeChecking,
                         format!("Unknown binary operator '{operator}'"),
                         *span,
-                    )),
+                    )
+                    .with_code(ErrorCode::UnknownOperator)),
+                }
+            }
+        }
+    }
+
+    /// Finds the payload types of `variant` inside `enum_ty`, which must be an enum type.
+    fn variant_payload(
+        enum_ty: &Type,
+        enum_name: &str,
+        variant: &str,
+        span: Span,
+    ) -> Result<Vec<Type>, FlavorError> {
+        let Type::Enum { variants, .. } = enum_ty else {
+            return Err(FlavorError::with_span(
+                ErrorPhase::TypeChecking,
+                format!("Type '{enum_name}' is not an enum"),
+                span,
+            )
+            .with_code(ErrorCode::NotAnEnum));
+        };
+        variants
+            .iter()
+            .find(|(name, _)| name == variant)
+            .map(|(_, payload)| payload.clone())
+            .ok_or_else(|| {
+                FlavorError::with_span(
+                    ErrorPhase::TypeChecking,
+                    format!("Enum '{enum_name}' has no variant named '{variant}'"),
+                    span,
+                )
+                .with_code(ErrorCode::UnknownVariant)
+            })
+    }
+
+    /// Checks every arm of a match and makes sure all variants of the enum are covered.
+    ///
+    /// An arm made of an expression has the type of that expression, while a block arm has
+    /// type Unit. Arms that guarantee a return do not take part in deciding the type of the
+    /// match, and the match guarantees a return only if all of its arms do.
+    fn check_match(
+        &mut self,
+        scrutinee: &ASTNode,
+        arms: &[MatchArm],
+    ) -> Result<(Type, bool), FlavorError> {
+        let (scrutinee_ty, _) = self.with_expected_type(None, |tc| tc.check(scrutinee));
+
+        let mut covered: Vec<&str> = vec![];
+        let mut has_catch_all = false;
+        let mut match_ty: Option<Type> = None;
+        let mut all_return = true;
+
+        for arm in arms {
+            self.enter_scope();
+            let arm_result = self.check_match_arm(&scrutinee_ty, arm);
+            self.exit_scope();
+            let (arm_ty, arm_returns) = arm_result.unwrap_or_else(|err| {
+                self.report(err);
+                (Type::Error, true)
+            });
+
+            match &arm.pattern {
+                Pattern::Wildcard { .. } | Pattern::Binding { .. } => has_catch_all = true,
+                Pattern::Variant { variant, .. } => covered.push(variant),
+            }
+
+            if arm_returns {
+                continue;
+            }
+            all_return = false;
+            match &match_ty {
+                Some(expected) if *expected != Type::Error && !compatible(expected, &arm_ty) => {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!(
+                            "Match arms have different types: expected {expected:?}, found {arm_ty:?}"
+                        ),
+                        *arm.body.span(),
+                    )
+                    .with_code(ErrorCode::MatchArmTypeMismatch));
+                }
+                Some(expected) if *expected != Type::Error => {}
+                _ => match_ty = Some(arm_ty),
+            }
+        }
+
+        if !has_catch_all && scrutinee_ty != Type::Error {
+            let Type::Enum { name, variants } = &scrutinee_ty else {
+                return Err(FlavorError::with_span(
+                    ErrorPhase::TypeChecking,
+                    format!(
+                        "Non-exhaustive match on {scrutinee_ty:?}: add a '_' arm to cover every value"
+                    ),
+                    *scrutinee.span(),
+                )
+                .with_code(ErrorCode::NonExhaustiveMatch));
+            };
+            let missing: Vec<String> = variants
+                .iter()
+                .filter(|(variant, _)| !covered.contains(&variant.as_str()))
+                .map(|(variant, _)| format!("{name}::{variant}"))
+                .collect();
+            if !missing.is_empty() {
+                let mut error = FlavorError::with_span(
+                    ErrorPhase::TypeChecking,
+                    format!(
+                        "Non-exhaustive match on enum '{name}': missing {}",
+                        missing.join(", ")
+                    ),
+                    *scrutinee.span(),
+                )
+                .with_code(ErrorCode::NonExhaustiveMatch);
+                if let Some(declared) = self.type_declaration_of(name) {
+                    error = error.with_label(declared, format!("enum '{name}' declared here"));
+                }
+                return Err(error);
+            }
+        }
+
+        let guaranteed_return = all_return && !arms.is_empty();
+        if guaranteed_return {
+            let ty = self.current_expected_return.clone().unwrap_or(Type::Unit);
+            return Ok((ty, true));
+        }
+        Ok((match_ty.unwrap_or(Type::Unit), false))
+    }
+
+    /// Binds the names introduced by the arm pattern in the current scope and checks its body.
+    fn check_match_arm(
+        &mut self,
+        scrutinee_ty: &Type,
+        arm: &MatchArm,
+    ) -> Result<(Type, bool), FlavorError> {
+        match &arm.pattern {
+            Pattern::Wildcard { .. } => {}
+            Pattern::Binding { name, .. } => self.insert(name.clone(), scrutinee_ty.clone()),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                bindings,
+                span,
+            } => {
+                let pattern_ty = self.resolve_type(&Type::Custom(enum_name.clone()), *span)?;
+                let payload = Self::variant_payload(&pattern_ty, enum_name, variant, *span)?;
+                if !compatible(scrutinee_ty, &pattern_ty) {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!(
+                            "Pattern '{enum_name}::{variant}' cannot match a value of type {scrutinee_ty:?}"
+                        ),
+                        *span,
+                    )
+                    .with_code(ErrorCode::PatternTypeMismatch));
+                }
+                if payload.len() != bindings.len() {
+                    return Err(FlavorError::with_span(
+                        ErrorPhase::TypeChecking,
+                        format!(
+                            "Pattern '{enum_name}::{variant}' binds {} value(s) but the variant carries {}",
+                            bindings.len(),
+                            payload.len()
+                        ),
+                        *span,
+                    )
+                    .with_code(ErrorCode::PatternArityMismatch));
+                }
+                for (binding, ty) in bindings.iter().zip(payload) {
+                    if binding != "_" {
+                        // A recursive enum names itself in its payloads, which expands here.
+                        let ty = self.resolve_type(&ty, *span)?;
+                        self.insert(binding.clone(), ty);
+                    }
                 }
             }
         }
+
+        let (body_ty, returns) = self.check(&arm.body);
+        if matches!(arm.body, ASTNode::Body { .. }) {
+            Ok((Type::Unit, returns))
+        } else {
+            Ok((body_ty, returns))
+        }
+    }
+
+    /// Builtins have polymorphic signatures, so the return type is computed
+    /// from the argument types instead of being read from a `Type::Function`.
+    fn check_builtin_call(
+        &mut self,
+        builtin: &Builtin,
+        arguments: &[ASTNode],
+        span: Span,
+    ) -> Result<(Type, bool), FlavorError> {
+        let mut arg_types = Vec::with_capacity(arguments.len());
+        for arg_node in arguments {
+            arg_types.push(self.with_expected_type(None, |tc| tc.check(arg_node)).0);
+        }
+        if arg_types.iter().any(contains_error) {
+            return Ok((Type::Error, false));
+        }
+        if matches!(builtin.name, "push" | "insert")
+            && let ASTNode::Identifier { name, .. } = &arguments[0]
+        {
+            self.close_array(name, &mut arg_types);
+        }
+
+        let return_type = (builtin.signature)(&arg_types).map_err(|message| {
+            FlavorError::with_span(ErrorPhase::TypeChecking, message, span)
+                .with_code(ErrorCode::BuiltinArgumentMismatch)
+        })?;
+
+        if let BuiltinCall::Mutating(_) = builtin.call {
+            self.ensure_assignable(&arguments[0]).map_err(|_| {
+                FlavorError::with_span(
+                    ErrorPhase::TypeChecking,
+                    format!(
+                        "First argument of '{}' must be a variable, array element or struct field",
+                        builtin.name
+                    ),
+                    *arguments[0].span(),
+                )
+                .with_code(ErrorCode::NotAssignable)
+            })?;
+        }
+
+        Ok((return_type, false))
+    }
+
+    /// Gives the untyped `[]` held by the variable `name` the type of the value pushed or
+    /// inserted into it, the last of `arg_types`.
+    fn close_array(&mut self, name: &str, arg_types: &mut [Type]) {
+        let value_ty = arg_types.last().cloned().unwrap_or(Type::Unit);
+        let is_open = arg_types[0] == Type::Array(Box::new(Type::Unit))
+            && value_ty != Type::Unit
+            && self
+                .declaration_of(name)
+                .is_some_and(|declared| self.open_arrays.remove(&declared));
+        if !is_open {
+            return;
+        }
+        let ty = Type::Array(Box::new(value_ty));
+        if let Some(scope) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(name))
+        {
+            scope.insert(name.to_string(), ty.clone());
+        }
+        arg_types[0] = ty;
     }
 
     fn ensure_assignable(&mut self, node: &ASTNode) -> Result<(), FlavorError> {
         match node {
             ASTNode::Identifier { .. } => Ok(()),
             ASTNode::ArrayAccess { array, .. } => self.ensure_assignable(array),
+            ASTNode::FieldAccess { object, .. } => self.ensure_assignable(object),
             other => Err(FlavorError::with_span(
                 ErrorPhase::TypeChecking,
-                "Left side of assignment must be a variable or array element",
+                "Left side of assignment must be a variable, array element or struct field",
                 *other.span(),
-            )),
+            )
+            .with_code(ErrorCode::NotAssignable)),
         }
     }
 }
+
+/// Type equality where the poison type matches anything, so an expression that already
+/// failed does not raise a second error in its parent.
+pub(crate) fn compatible(expected: &Type, found: &Type) -> bool {
+    match (expected, found) {
+        (Type::Error, _) | (_, Type::Error) => true,
+        (Type::Array(expected), Type::Array(found)) => compatible(expected, found),
+        (
+            Type::Function {
+                param_types: expected_params,
+                return_type: expected_return,
+            },
+            Type::Function {
+                param_types: found_params,
+                return_type: found_return,
+            },
+        ) => {
+            expected_params.len() == found_params.len()
+                && expected_params
+                    .iter()
+                    .zip(found_params)
+                    .all(|(expected, found)| compatible(expected, found))
+                && compatible(expected_return, found_return)
+        }
+        _ => expected == found,
+    }
+}
+
+fn recursive_alias(cycle: &[String], span: Span) -> FlavorError {
+    FlavorError::with_span(
+        ErrorPhase::TypeChecking,
+        format!("Recursive type definition detected: {}", cycle.join(" -> ")),
+        span,
+    )
+    .with_code(ErrorCode::RecursiveType)
+}
+
+/// Tells whether `==` can compare two values of type `ty`, which is the case unless they hold
+/// functions.
+fn comparable(ty: &Type) -> bool {
+    match ty {
+        Type::Function { .. } => false,
+        Type::Array(inner) => comparable(inner),
+        Type::Struct { fields, .. } => fields.iter().all(|(_, ty)| comparable(ty)),
+        Type::Enum { variants, .. } => variants
+            .iter()
+            .all(|(_, payload)| payload.iter().all(comparable)),
+        _ => true,
+    }
+}
+
+fn contains_error(ty: &Type) -> bool {
+    match ty {
+        Type::Error => true,
+        Type::Array(inner) => contains_error(inner),
+        Type::Function {
+            param_types,
+            return_type,
+        } => param_types.iter().any(contains_error) || contains_error(return_type),
+        _ => false,
+    }
+}
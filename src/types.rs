@@ -1,6 +1,9 @@
 #![allow(unused)]
 
-#[derive(Clone, PartialEq, Debug, Copy)]
+use std::cell::Cell;
+use std::rc::Rc;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Copy)]
 pub struct Span {
     pub start_line: usize,
     pub start_column: usize,
@@ -49,11 +52,23 @@ impl Span {
     }
 }
 
+/// Where a variable lives at runtime, found by the resolver of the interpreter.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Location {
+    /// Slot of the scope `depth` scopes out from the one the variable is used in
+    Local { depth: usize, slot: usize },
+    /// Variable of the global scope, or the builtin of the same name if none is defined
+    Global(usize),
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Token {
     pub tok_name: TokenName,
     pub lexeme: String,
     pub span: Span,
+    /// Decoded value of literals whose source text differs from their value,
+    /// such as string literals with quotes and escape sequences.
+    pub value: Option<String>,
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -65,11 +80,17 @@ pub enum TokenName {
     Let,
     Fn,
     Alias,
+    Struct,
+    Enum,
+    Match,
     Return,
     If,
     Else,
     While,
+    For,
+    In,
     Break,
+    Continue,
 
     // Types
     Int,
@@ -80,9 +101,12 @@ pub enum TokenName {
     Array,
 
     // Symbols
+    DotDotEq,
+    DotDot,
     Dot,
     Comma,
     Colon,
+    DoubleColon,
     Semicolon,
     SlimArrow,
     BoldArrow,
@@ -113,6 +137,8 @@ pub enum TokenName {
     RBra,
 
     // Complex Elements
+    DocComment,
+    Label,
     Number,
     FloatLiteral,
     StringLiteral,
@@ -125,7 +151,7 @@ pub enum TokenName {
     Eof,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Type {
     Int,
     Bool,
@@ -134,11 +160,131 @@ pub enum Type {
     Unit,
     Custom(String),
     Array(Box<Type>),
+    Struct {
+        name: String,
+        fields: Vec<(String, Type)>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<(String, Vec<Type>)>,
+    },
     Function {
         param_types: Vec<Type>,
         return_type: Box<Type>,
     },
-    // Unknown,
+    /// Given by the type checker to an expression that failed to check.
+    /// It is compatible with every type, so a single mistake is reported only once.
+    Error,
+}
+
+/// Structs and enums are nominal: two of them are the same type when they have the same name,
+/// whatever the expansion of their fields. A name left inside a recursive struct or enum is the
+/// same type as the struct or enum it names.
+impl PartialEq for Type {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Type::Int, Type::Int)
+            | (Type::Bool, Type::Bool)
+            | (Type::Float, Type::Float)
+            | (Type::String, Type::String)
+            | (Type::Unit, Type::Unit)
+            | (Type::Error, Type::Error) => true,
+            (
+                Type::Custom(name) | Type::Struct { name, .. } | Type::Enum { name, .. },
+                Type::Custom(other),
+            )
+            | (
+                Type::Custom(name),
+                Type::Struct { name: other, .. } | Type::Enum { name: other, .. },
+            )
+            | (Type::Struct { name, .. }, Type::Struct { name: other, .. })
+            | (Type::Enum { name, .. }, Type::Enum { name: other, .. }) => name == other,
+            (Type::Array(element), Type::Array(other)) => element == other,
+            (
+                Type::Function {
+                    param_types,
+                    return_type,
+                },
+                Type::Function {
+                    param_types: other_params,
+                    return_type: other_return,
+                },
+            ) => param_types == other_params && return_type == other_return,
+            _ => false,
+        }
+    }
+}
+
+/// Writes the type the way it is spelled in Flavor source, e.g. `[int]` or `(int) -> bool`.
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Unit => write!(f, "nothing"),
+            Type::Custom(name) | Type::Struct { name, .. } | Type::Enum { name, .. } => {
+                write!(f, "{name}")
+            }
+            Type::Array(element) => write!(f, "[{element}]"),
+            Type::Function {
+                param_types,
+                return_type,
+            } => {
+                write!(f, "(")?;
+                for (index, param) in param_types.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{param}")?;
+                }
+                write!(f, ") -> {return_type}")
+            }
+            Type::Error => write!(f, "{{error}}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`, matches anything without binding it
+    Wildcard { span: Span },
+    /// A plain identifier, matches anything and binds it to the name
+    Binding { name: String, span: Span },
+    /// `Enum::Variant(a, b)`, matches one variant and binds its payload
+    Variant {
+        enum_name: String,
+        variant: String,
+        bindings: Vec<String>,
+        span: Span,
+    },
+}
+
+impl Pattern {
+    pub fn span(&self) -> &Span {
+        match self {
+            Pattern::Wildcard { span }
+            | Pattern::Binding { span, .. }
+            | Pattern::Variant { span, .. } => span,
+        }
+    }
+}
+
+/// A parameter of a function declaration or expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub ty: Type,
+    /// From the name to the end of the type
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: ASTNode,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -158,27 +304,61 @@ pub enum ASTNode {
         span: Span,
     },
     While {
+        label: Option<String>,
         guard: Box<ASTNode>,
         body: Box<ASTNode>,
         span: Span,
     },
+    For {
+        label: Option<String>,
+        variable: String,
+        iterable: Box<ASTNode>,
+        body: Box<ASTNode>,
+        span: Span,
+    },
+    Range {
+        start: Box<ASTNode>,
+        end: Box<ASTNode>,
+        inclusive: bool,
+        span: Span,
+    },
     LetDeclaration {
         identifier: String,
         var_type: Option<Type>,
         expr: Box<ASTNode>,
         span: Span,
+        /// Variable the declaration defines, set by the resolver
+        location: Cell<Option<Location>>,
     },
     FunctionDeclaration {
         name: String,
-        parameters: Vec<(String, Type)>,
+        parameters: Vec<Parameter>,
         return_type: Type,
-        body: Box<ASTNode>,
+        /// Shared with the function values the interpreter creates from the declaration
+        body: Rc<ASTNode>,
         span: Span,
+        /// Variable the declaration defines, set by the resolver
+        location: Cell<Option<Location>>,
     },
     FunctionExpression {
-        parameters: Vec<(String, Type)>,
+        parameters: Vec<Parameter>,
         return_type: Type,
-        body: Box<ASTNode>,
+        body: Rc<ASTNode>,
+        span: Span,
+    },
+    AliasDeclaration {
+        name: String,
+        aliased_type: Type,
+        span: Span,
+    },
+    StructDeclaration {
+        name: String,
+        fields: Vec<(String, Type)>,
+        span: Span,
+    },
+    EnumDeclaration {
+        name: String,
+        variants: Vec<(String, Vec<Type>)>,
         span: Span,
     },
     Return {
@@ -186,6 +366,11 @@ pub enum ASTNode {
         span: Span,
     },
     Break {
+        label: Option<String>,
+        span: Span,
+    },
+    Continue {
+        label: Option<String>,
         span: Span,
     },
     FunctionCall {
@@ -215,6 +400,8 @@ pub enum ASTNode {
     Identifier {
         name: String,
         span: Span,
+        /// Variable the identifier refers to, set by the resolver
+        location: Cell<Option<Location>>,
     },
     ArrayLiteral {
         elements: Vec<ASTNode>,
@@ -225,6 +412,27 @@ pub enum ASTNode {
         index: Box<ASTNode>,
         span: Span,
     },
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, ASTNode)>,
+        span: Span,
+    },
+    FieldAccess {
+        object: Box<ASTNode>,
+        field: String,
+        span: Span,
+    },
+    EnumVariant {
+        enum_name: String,
+        variant: String,
+        arguments: Vec<ASTNode>,
+        span: Span,
+    },
+    Match {
+        scrutinee: Box<ASTNode>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
     BinaryExpression {
         left: Box<ASTNode>,
         operator: String,
@@ -250,11 +458,17 @@ impl ASTNode {
             | ASTNode::Body { span, .. }
             | ASTNode::If { span, .. }
             | ASTNode::While { span, .. }
+            | ASTNode::For { span, .. }
+            | ASTNode::Range { span, .. }
             | ASTNode::LetDeclaration { span, .. }
             | ASTNode::FunctionDeclaration { span, .. }
             | ASTNode::FunctionExpression { span, .. }
+            | ASTNode::AliasDeclaration { span, .. }
+            | ASTNode::StructDeclaration { span, .. }
+            | ASTNode::EnumDeclaration { span, .. }
             | ASTNode::Return { span, .. }
             | ASTNode::Break { span, .. }
+            | ASTNode::Continue { span, .. }
             | ASTNode::FunctionCall { span, .. }
             | ASTNode::UnitLiteral { span, .. }
             | ASTNode::NumberLiteral { span, .. }
@@ -264,6 +478,10 @@ impl ASTNode {
             | ASTNode::Identifier { span, .. }
             | ASTNode::ArrayLiteral { span, .. }
             | ASTNode::ArrayAccess { span, .. }
+            | ASTNode::StructLiteral { span, .. }
+            | ASTNode::FieldAccess { span, .. }
+            | ASTNode::EnumVariant { span, .. }
+            | ASTNode::Match { span, .. }
             | ASTNode::BinaryExpression { span, .. }
             | ASTNode::UnaryExpression { span, .. }
             | ASTNode::ExpressionStatement { span, .. } => span,
@@ -0,0 +1,599 @@
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use crate::builtins::{BUILTINS, BuiltinCall};
+use crate::bytecode::{Accessor, Capture, Closure, Op, Place, PlaceRoot, Upvalue};
+use crate::compiler::{Compiler, Globals};
+use crate::error::{ErrorPhase, FlavorError};
+use crate::error_codes::ErrorCode;
+use crate::gc::{Collector, Tracked};
+use crate::interpreter::{EnvFrame, EvaluationType as ET};
+use crate::operations::{self, PlaceStep};
+use crate::types::{ASTNode, Span};
+
+/// A function running on the virtual machine.
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// Position of the first argument on the stack
+    base: usize,
+}
+
+/// Runs checked programs compiled to bytecode, as a faster alternative to the `Interpreter`.
+///
+/// Programs behave as they do in the interpreter, except that the declared type of a `let`
+/// is not checked again at runtime. Globals are kept from one program to the next.
+pub struct Vm {
+    stack: Vec<ET>,
+    frames: Vec<Frame>,
+    /// Upvalues still pointing into the stack, ordered by position
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// Frees the upvalues left in reference cycles by closures capturing themselves
+    collector: Collector,
+    globals: Vec<Option<ET>>,
+    names: Globals,
+    /// Types declared at the top level, used to check the results of host functions
+    types: Rc<RefCell<EnvFrame>>,
+    /// Where `print` writes, standard output unless replaced with `with_output`
+    pub(crate) output: Box<dyn Write>,
+    /// Where `read_line` reads from, standard input unless replaced with `with_input`
+    pub(crate) input: Option<Box<dyn BufRead>>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            open_upvalues: Vec::new(),
+            collector: Collector::new(),
+            globals: Vec::new(),
+            names: Globals::default(),
+            types: Rc::new(RefCell::new(EnvFrame::new(None))),
+            output: Box::new(std::io::stdout()),
+            input: None,
+        }
+    }
+
+    /// Sends the output of `print` to `output` instead of standard output.
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Makes `read_line` read from `input` instead of standard input.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Defines a variable visible to every program run afterwards.
+    pub fn define_global(&mut self, name: &str, value: ET) {
+        let index = self.names.declare(name) as usize;
+        self.globals.resize(self.names.len(), None);
+        self.globals[index] = Some(value);
+    }
+
+    /// Number of upvalues still allocated, counting those the next collection will free.
+    pub fn live_upvalues(&self) -> usize {
+        self.collector.live()
+    }
+
+    /// Frees the upvalues that only reference cycles between closures and the upvalues they
+    /// capture keep alive, and returns how many there were. Collections also happen on their
+    /// own as closures capture variables.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.collector.collect()
+    }
+
+    /// Compiles and runs a checked program, returning the value of its last statement.
+    pub fn run(&mut self, nodes: &[ASTNode]) -> Result<ET, FlavorError> {
+        let function = Compiler::new(&mut self.names, &self.types).compile_program(nodes);
+        self.globals.resize(self.names.len(), None);
+
+        let closure = Rc::new(Closure {
+            function: Rc::new(function),
+            upvalues: vec![],
+        });
+        self.stack.push(ET::Closure(Rc::clone(&closure)));
+        let frame = Frame {
+            closure,
+            ip: 0,
+            base: self.stack.len(),
+        };
+        self.execute(frame).inspect_err(|_| {
+            self.close_upvalues(0);
+            self.stack.clear();
+            self.frames.clear();
+        })
+    }
+
+    fn pop(&mut self) -> ET {
+        self.stack.pop().expect("the stack holds the operands")
+    }
+
+    fn top(&self) -> &ET {
+        self.stack.last().expect("the stack holds the operands")
+    }
+
+    /// Removes the `count` values on top of the stack.
+    fn pop_many(&mut self, count: u32) -> Vec<ET> {
+        self.stack.split_off(self.stack.len() - count as usize)
+    }
+
+    fn execute(&mut self, mut frame: Frame) -> Result<ET, FlavorError> {
+        loop {
+            let at = frame.ip;
+            frame.ip += 1;
+            let function = &frame.closure.function;
+            let chunk = &function.chunk;
+            let span = || chunk.spans[at];
+            let runtime_error = |message: String, code: ErrorCode| {
+                FlavorError::with_span(ErrorPhase::Runtime, message, chunk.spans[at])
+                    .with_code(code)
+            };
+
+            match chunk.code[at] {
+                Op::Constant(index) => self.stack.push(chunk.constants[index as usize].clone()),
+                Op::Unit => self.stack.push(ET::Unit),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::GetLocal(slot) => {
+                    let value = self.stack[frame.base + slot as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::SetLocal(slot) => {
+                    let value = self.top().clone();
+                    self.stack[frame.base + slot as usize] = value;
+                }
+                Op::GetUpvalue(index) => {
+                    let value = match &*frame.closure.upvalues[index as usize].borrow() {
+                        Upvalue::Open(position) => self.stack[*position].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                Op::SetUpvalue(index) => {
+                    let value = self.top().clone();
+                    match &mut *frame.closure.upvalues[index as usize].borrow_mut() {
+                        Upvalue::Open(position) => self.stack[*position] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                Op::GetGlobal(index) => {
+                    let value = self.global(index, span())?;
+                    self.stack.push(value);
+                }
+                Op::SetGlobal(index) => {
+                    let value = self.top().clone();
+                    let name = &self.names.names[index as usize];
+                    match &mut self.globals[index as usize] {
+                        Some(global) => *global = value,
+                        None => {
+                            return Err(runtime_error(
+                                format!("Undefined variable: {name}"),
+                                ErrorCode::RuntimeTypeError,
+                            ));
+                        }
+                    }
+                }
+                Op::DefineGlobal(index) => {
+                    self.globals[index as usize] = Some(self.pop());
+                }
+                Op::EndScope { locals, keep } => {
+                    let kept = keep.then(|| self.pop());
+                    let len = self.stack.len() - locals as usize;
+                    self.close_upvalues(len);
+                    self.stack.truncate(len);
+                    self.stack.extend(kept);
+                }
+                Op::Jump(target) => frame.ip = target as usize,
+                Op::JumpIfFalse(target) => {
+                    if !matches!(self.pop(), ET::Bool(true)) {
+                        frame.ip = target as usize;
+                    }
+                }
+                Op::Binary(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = operations::binary(operator, left, right, span())?;
+                    self.stack.push(value);
+                }
+                Op::Negate => {
+                    let value = operations::negate(self.pop(), span())?;
+                    self.stack.push(value);
+                }
+                Op::Not => {
+                    let value = operations::not(self.pop(), span())?;
+                    self.stack.push(value);
+                }
+                Op::ExpectInt => {
+                    if !matches!(self.top(), ET::Int(_)) {
+                        return Err(runtime_error(
+                            "Range bounds must be integers".to_string(),
+                            ErrorCode::RuntimeTypeError,
+                        ));
+                    }
+                }
+                Op::CheckIndex => {
+                    operations::place_index(self.top(), span())?;
+                }
+                Op::Index => {
+                    let index = self.pop();
+                    let array = self.pop();
+                    let value = operations::index(array, index, span())?;
+                    self.stack.push(value);
+                }
+                Op::GetField(name) => {
+                    let object = self.pop();
+                    let value = operations::field(object, &chunk.names[name as usize], span())?;
+                    self.stack.push(value);
+                }
+                Op::SetPlace(place) => {
+                    let place = &chunk.places[place as usize];
+                    let indices = self.pop_many(place.indices);
+                    let value = self.top().clone();
+                    self.with_place(&frame, place, indices, |target| {
+                        *target = value;
+                        Ok(())
+                    })?;
+                }
+                Op::IncrementPlace {
+                    place,
+                    decrement,
+                    postfix,
+                } => {
+                    let place = &chunk.places[place as usize];
+                    let indices = self.pop_many(place.indices);
+                    let delta = if decrement { -1 } else { 1 };
+                    let value = self.with_place(&frame, place, indices, |target| {
+                        operations::increment(target, delta, postfix, span())
+                    })?;
+                    self.stack.push(value);
+                }
+                Op::CallBuiltin { builtin, arguments } => {
+                    let arguments = self.pop_many(arguments);
+                    let value = self.call_builtin(builtin as usize, &arguments, span())?;
+                    self.stack.push(value);
+                }
+                Op::CallMutating {
+                    builtin,
+                    place,
+                    arguments,
+                } => {
+                    let BuiltinCall::Mutating(call) = BUILTINS[builtin as usize].call else {
+                        unreachable!("only mutating builtins take a place");
+                    };
+                    let arguments = self.pop_many(arguments);
+                    let place = &chunk.places[place as usize];
+                    let indices = self.pop_many(place.indices);
+                    let value = self.with_place(&frame, place, indices, |target| {
+                        call(target, &arguments).map_err(|(code, message)| {
+                            FlavorError::with_span(ErrorPhase::Runtime, message, span())
+                                .with_code(code)
+                        })
+                    })?;
+                    self.stack.push(value);
+                }
+                Op::Call(arguments) => {
+                    let callee = self.stack.len() - arguments as usize - 1;
+                    match &self.stack[callee] {
+                        ET::Closure(closure) => {
+                            operations::check_arity(
+                                closure.function.arity,
+                                arguments as usize,
+                                span(),
+                            )?;
+                            let called = Frame {
+                                closure: Rc::clone(closure),
+                                ip: 0,
+                                base: callee + 1,
+                            };
+                            self.frames.push(std::mem::replace(&mut frame, called));
+                        }
+                        ET::Native(native) => {
+                            let native = Rc::clone(native);
+                            operations::check_arity(
+                                native.param_types.len(),
+                                arguments as usize,
+                                span(),
+                            )?;
+                            let arguments = self.pop_many(arguments);
+                            self.pop();
+                            let value = native.invoke(&arguments, span(), &self.types)?;
+                            self.stack.push(value);
+                        }
+                        ET::Builtin(builtin)
+                            if !matches!(builtin.call, BuiltinCall::Mutating(_)) =>
+                        {
+                            let builtin = BUILTINS
+                                .iter()
+                                .position(|known| known.name == builtin.name)
+                                .expect("builtins come from the table");
+                            let arguments = self.pop_many(arguments);
+                            self.pop();
+                            let value = self.call_builtin(builtin, &arguments, span())?;
+                            self.stack.push(value);
+                        }
+                        _ => {
+                            return Err(runtime_error(
+                                "Callee is not a function".to_string(),
+                                ErrorCode::RuntimeTypeError,
+                            ));
+                        }
+                    }
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base - 1);
+                    match self.frames.pop() {
+                        Some(caller) => {
+                            frame = caller;
+                            self.stack.push(value);
+                        }
+                        None => return Ok(value),
+                    }
+                }
+                Op::Closure(index) => {
+                    let function = Rc::clone(&chunk.functions[index as usize]);
+                    let upvalues = function
+                        .captures
+                        .iter()
+                        .map(|capture| match *capture {
+                            Capture::Local(slot) => self.capture(frame.base + slot as usize),
+                            Capture::Upvalue(index) => {
+                                Rc::clone(&frame.closure.upvalues[index as usize])
+                            }
+                        })
+                        .collect();
+                    let closure = Closure { function, upvalues };
+                    self.stack.push(ET::Closure(Rc::new(closure)));
+                }
+                Op::MakeArray(len) => {
+                    let values = self.pop_many(len);
+                    self.stack.push(ET::Array(Rc::new(values)));
+                }
+                Op::MakeStruct(shape) => {
+                    let shape = &chunk.shapes[shape as usize];
+                    let mut values: Vec<Option<ET>> = self
+                        .pop_many(shape.fields.len() as u32)
+                        .into_iter()
+                        .map(Some)
+                        .collect();
+                    let fields = shape
+                        .fields
+                        .iter()
+                        .map(|(position, field)| {
+                            (field.clone(), values[*position].take().unwrap_or(ET::Unit))
+                        })
+                        .collect();
+                    self.stack.push(ET::Struct {
+                        name: shape.name.clone(),
+                        fields,
+                    });
+                }
+                Op::MakeVariant { variant, len } => {
+                    let (enum_name, variant) = &chunk.variants[variant as usize];
+                    let payload = self.pop_many(len);
+                    self.stack.push(ET::Variant {
+                        enum_name: enum_name.clone(),
+                        variant: variant.clone(),
+                        payload,
+                    });
+                }
+                Op::JumpIfNotVariant {
+                    slot,
+                    variant,
+                    target,
+                } => {
+                    let expected = &chunk.names[variant as usize];
+                    let matches = matches!(
+                        &self.stack[frame.base + slot as usize],
+                        ET::Variant { variant, .. } if variant == expected
+                    );
+                    if !matches {
+                        frame.ip = target as usize;
+                    }
+                }
+                Op::GetVariantField { slot, index } => {
+                    let value = match &self.stack[frame.base + slot as usize] {
+                        ET::Variant { payload, .. } => payload.get(index as usize).cloned(),
+                        _ => None,
+                    };
+                    self.stack.push(value.unwrap_or(ET::Unit));
+                }
+                Op::NoMatch(slot) => {
+                    let value = &self.stack[frame.base + slot as usize];
+                    return Err(runtime_error(
+                        format!("No match arm matched the value {value}"),
+                        ErrorCode::NoMatchingArm,
+                    ));
+                }
+                Op::RangeNext {
+                    slot,
+                    inclusive,
+                    exit,
+                } => {
+                    let position = frame.base + slot as usize;
+                    match (&self.stack[position], &self.stack[position + 1]) {
+                        (ET::Int(current), ET::Int(end))
+                            if current < end || (inclusive && current == end) =>
+                        {
+                            let current = *current;
+                            // The last value of an inclusive range may be `i64::MAX`, so the
+                            // range is marked as done instead of going past it.
+                            self.stack[position] = if current == *end {
+                                ET::Unit
+                            } else {
+                                ET::Int(current + 1)
+                            };
+                            self.stack.push(ET::Int(current));
+                        }
+                        _ => frame.ip = exit as usize,
+                    }
+                }
+                Op::ArrayNext { slot, exit } => {
+                    let position = frame.base + slot as usize;
+                    let next = match (&self.stack[position], &self.stack[position + 1]) {
+                        (ET::Array(values), ET::Int(index)) => values.get(*index as usize).cloned(),
+                        (other, _) => {
+                            return Err(runtime_error(
+                                format!("Cannot iterate over {}", other.type_name()),
+                                ErrorCode::RuntimeTypeError,
+                            ));
+                        }
+                    };
+                    match next {
+                        Some(value) => {
+                            if let ET::Int(index) = &mut self.stack[position + 1] {
+                                *index += 1;
+                            }
+                            self.stack.push(value);
+                        }
+                        None => frame.ip = exit as usize,
+                    }
+                }
+                Op::Print(len) => {
+                    let values = self.pop_many(len);
+                    operations::print(self.output.as_mut(), &values, span())?;
+                }
+                Op::Fail(error) => return Err(chunk.errors[error as usize].clone()),
+            }
+        }
+    }
+
+    fn global(&self, index: u32, span: Span) -> Result<ET, FlavorError> {
+        if let Some(value) = &self.globals[index as usize] {
+            return Ok(value.clone());
+        }
+        let name = &self.names.names[index as usize];
+        crate::builtins::lookup(name)
+            .map(ET::Builtin)
+            .ok_or_else(|| {
+                FlavorError::with_span(
+                    ErrorPhase::Runtime,
+                    format!("Undefined variable: {name}"),
+                    span,
+                )
+                .with_code(ErrorCode::RuntimeTypeError)
+            })
+    }
+
+    /// Runs a builtin taking its arguments by value.
+    fn call_builtin(
+        &mut self,
+        builtin: usize,
+        arguments: &[ET],
+        span: Span,
+    ) -> Result<ET, FlavorError> {
+        let builtin = &BUILTINS[builtin];
+        match builtin.call {
+            BuiltinCall::Pure(call) => call(arguments),
+            BuiltinCall::Input(call) => match &mut self.input {
+                Some(input) => call(input.as_mut(), arguments),
+                None => call(&mut std::io::stdin().lock(), arguments),
+            },
+            BuiltinCall::Mutating(_) => unreachable!("mutating builtins take a place"),
+        }
+        .map_err(|(code, message)| {
+            FlavorError::with_span(ErrorPhase::Runtime, message, span).with_code(code)
+        })
+    }
+
+    /// Walks `place` from its variable, following `indices` for its array accesses, and
+    /// hands the targeted value to `f`.
+    fn with_place<R>(
+        &mut self,
+        frame: &Frame,
+        place: &Place,
+        indices: Vec<ET>,
+        f: impl FnOnce(&mut ET) -> Result<R, FlavorError>,
+    ) -> Result<R, FlavorError> {
+        let mut indices = indices.into_iter();
+        let steps = place.accessors.iter().map(|accessor| match accessor {
+            Accessor::Index {
+                index_span,
+                array_span,
+            } => PlaceStep::Index {
+                index: match indices.next() {
+                    Some(ET::Int(index)) => index as usize,
+                    _ => unreachable!("indices are checked when they are evaluated"),
+                },
+                index_span: *index_span,
+                array_span: *array_span,
+            },
+            Accessor::Field { name, span } => PlaceStep::Field { name, span: *span },
+        });
+
+        let undefined = || {
+            FlavorError::with_span(
+                ErrorPhase::Runtime,
+                format!("Undefined variable: {}", place.name),
+                place.span,
+            )
+            .with_code(ErrorCode::RuntimeTypeError)
+        };
+        match place.root {
+            PlaceRoot::Local(slot) => {
+                let root = &mut self.stack[frame.base + slot as usize];
+                f(operations::place_mut(root, &place.name, place.span, steps)?)
+            }
+            PlaceRoot::Upvalue(index) => {
+                let mut upvalue = frame.closure.upvalues[index as usize].borrow_mut();
+                let root = match &mut *upvalue {
+                    Upvalue::Open(position) => &mut self.stack[*position],
+                    Upvalue::Closed(value) => value,
+                };
+                f(operations::place_mut(root, &place.name, place.span, steps)?)
+            }
+            PlaceRoot::Global(index) => {
+                let root = self.globals[index as usize]
+                    .as_mut()
+                    .ok_or_else(undefined)?;
+                f(operations::place_mut(root, &place.name, place.span, steps)?)
+            }
+        }
+    }
+
+    /// Returns the upvalue for the stack position, creating it if no closure captured it yet.
+    fn capture(&mut self, position: usize) -> Rc<RefCell<Upvalue>> {
+        let mut insert_at = self.open_upvalues.len();
+        for (at, upvalue) in self.open_upvalues.iter().enumerate().rev() {
+            match *upvalue.borrow() {
+                Upvalue::Open(open) if open == position => return Rc::clone(upvalue),
+                Upvalue::Open(open) if open < position => break,
+                _ => insert_at = at,
+            }
+        }
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(position)));
+        self.collector
+            .register(Tracked::Upvalue(Rc::downgrade(&upvalue)));
+        self.open_upvalues.insert(insert_at, Rc::clone(&upvalue));
+        upvalue
+    }
+
+    /// Moves the values of the stack from `from` upwards into the upvalues capturing them.
+    fn close_upvalues(&mut self, from: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let mut upvalue = upvalue.borrow_mut();
+            let Upvalue::Open(position) = *upvalue else {
+                unreachable!("only open upvalues are listed");
+            };
+            if position < from {
+                break;
+            }
+            *upvalue = Upvalue::Closed(self.stack[position].clone());
+            drop(upvalue);
+            self.open_upvalues.pop();
+        }
+    }
+}